@@ -5,10 +5,7 @@ extern crate rusip;
 use bencher::Bencher;
 
 fn is_mark(i: u8) -> bool {
-  match i {
-    b'-' | b'_' | b'.' | b'!' | b'~' | b'*' | b'\'' | b'(' | b')' => true,
-    _ => false
-  }
+  matches!(i, b'-' | b'_' | b'.' | b'!' | b'~' | b'*' | b'\'' | b'(' | b')')
 }
 
 fn to_mark(i: u8) -> Option<u8> {
@@ -18,7 +15,7 @@ fn to_mark(i: u8) -> Option<u8> {
   }
 }
 
-named!(#[inline], verify_mark<u8>, map!(verify!(take!(1), |i:&[u8]| is_mark(unsafe{*i.get_unchecked(0)})), |i| unsafe{*i.get_unchecked(0)} as u8));
+named!(#[inline], verify_mark<u8>, map!(verify!(take!(1), |i:&[u8]| is_mark(unsafe{*i.get_unchecked(0)})), |i| unsafe{*i.get_unchecked(0)}));
 named!(#[inline], one_of_mark<u8>, map!(one_of!("-_.!~*'()"), |ch| ch as u8));
 named!(#[inline], option_mark<u8>, map!(verify!(map!(take!(1), |i| to_mark(unsafe{*i.get_unchecked(0)})), Option::is_some), Option::unwrap));
 
@@ -69,7 +66,7 @@ named!(#[inline], single_digit<u8>,
 
 fn is_version_prefix(buf: &[u8]) -> bool {
   let prefix = &[(b's', b'S'), (b'i', b'I'), (b'p', b'P'), (b'/', b'/')];
-  Iterator::zip(prefix.into_iter(), buf.into_iter()).all(|((l, u), i)| l == i || u == i)
+  Iterator::zip(prefix.iter(), buf).all(|((l, u), i)| l == i || u == i)
 }
 
 named!(#[inline], pub do_parse_version<(u8, u8)>, do_parse!(
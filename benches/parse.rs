@@ -56,10 +56,42 @@ fn bench_std_ipv6_parse(b: &mut Bencher) {
   b.bytes = ip.len() as u64;
 }
 
+static INVITE: &[u8] = b"INVITE sip:bob@biloxi.com SIP/2.0\r\n\
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bKnashds8\r\n\
+Max-Forwards: 70\r\n\
+To: Bob <sip:bob@biloxi.com>\r\n\
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+Call-ID: a84b4c76e66710\r\n\
+CSeq: 314159 INVITE\r\n\
+Contact: <sip:alice@pc33.atlanta.com>\r\n\
+Content-Type: application/sdp\r\n\
+Content-Length: 0\r\n\
+\r\n";
+
+fn bench_message_parse(b: &mut Bencher) {
+  b.iter(|| {
+    let (_, m) = rusip::msg::parse::message(INVITE).unwrap();
+    assert_eq!(m.headers().iter().count(), 9);
+  });
+  b.bytes = INVITE.len() as u64;
+}
+
+fn bench_parsip_message_parse(b: &mut Bencher) {
+  b.iter(|| {
+    let mut headers = [parsip::EMPTY_HEADER; 16];
+    let mut request = parsip::Request::new(&mut headers);
+    assert!(request.parse(INVITE).is_done());
+    assert_eq!(request.headers.len(), 9);
+  });
+  b.bytes = INVITE.len() as u64;
+}
+
 benchmark_group!(benches,
   bench_ipv4_parse,
   bench_std_ipv4_parse,
   bench_ipv6_parse,
-  bench_std_ipv6_parse
+  bench_std_ipv6_parse,
+  bench_message_parse,
+  bench_parsip_message_parse
 );
 benchmark_main!(benches);
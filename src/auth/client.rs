@@ -0,0 +1,341 @@
+use crate::msg::Binary;
+use crate::msg::abnf::userinfo::{unquote, escape_quoted};
+use crate::msg::header::HeaderName;
+use crate::msg::header::auth::{
+  Algorithm, Qop, Challenge, DigestChallenge, DigestResponse, Credentials, AuthParams, assemble
+};
+use crate::msg::id;
+use crate::msg::output::AssembleError;
+use super::Request;
+use super::digest::{Hash, Input, ha1, request_digest};
+use std::collections::HashMap;
+
+// Username and password for a realm
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Secret<'a> {
+  pub username: &'a Binary,
+  pub password: &'a Binary
+}
+
+pub trait CredentialStore {
+  // credentials for the unquoted realm of a challenge
+  fn lookup(&self, realm: &Binary) -> Option<Secret<'_>>;
+}
+
+// the same credentials for every realm
+impl<'s> CredentialStore for Secret<'s> {
+  fn lookup(&self, _realm: &Binary) -> Option<Secret<'_>> {
+    Some(*self)
+  }
+}
+
+impl CredentialStore for HashMap<Vec<u8>, (Vec<u8>, Vec<u8>)> {
+  fn lookup(&self, realm: &Binary) -> Option<Secret<'_>> {
+    self.get(realm).map(|(username, password)| Secret { username, password })
+  }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Error {
+  NoCredentials,
+  UnsupportedAlgorithm,
+  UnsupportedQop,
+  Assemble(AssembleError)
+}
+
+impl From<AssembleError> for Error {
+  fn from(e: AssembleError) -> Error {
+    Error::Assemble(e)
+  }
+}
+
+// Header which answers the challenge header: Authorization for
+// WWW-Authenticate of a 401, Proxy-Authorization for Proxy-Authenticate
+// of a 407
+pub fn authorization_header(challenge: HeaderName) -> Option<HeaderName<'static>> {
+  match challenge {
+    HeaderName::WwwAuthenticate => Some(HeaderName::Authorization),
+    HeaderName::ProxyAuthenticate => Some(HeaderName::ProxyAuthorization),
+    _ => None
+  }
+}
+
+// Digest challenge with the strongest supported algorithm, the first one
+// of equal strength (RFC 8760 2.4)
+pub fn select<'a, I>(challenges: I) -> Option<DigestChallenge<'a>>
+  where I: IntoIterator<Item = Challenge<'a>> {
+  let mut best: Option<(Hash, DigestChallenge<'a>)> = None;
+  for c in challenges {
+    if let Challenge::Digest(d) = c {
+      if let Some(hash) = Hash::of(d.algorithm.unwrap_or(Algorithm::Md5)) {
+        if best.is_none_or(|(h, _)| hash > h) {
+          best = Some((hash, d));
+        }
+      }
+    }
+  }
+  best.map(|(_, d)| d)
+}
+
+// UAC side of Digest, RFC 3261 22.2. Nonce counts are kept per realm
+// for its latest nonce, a challenge with another nonce starts over.
+// cnonce values come out of the seed, which should be unpredictable.
+pub struct DigestClient<S> {
+  store: S,
+  seed: Vec<u8>,
+  integrity: bool,
+  requests: u64,
+  nonces: HashMap<Vec<u8>, (Vec<u8>, u32)>
+}
+
+impl<S: CredentialStore> DigestClient<S> {
+  pub fn new(store: S, seed: &Binary) -> DigestClient<S> {
+    DigestClient { store, seed: seed.to_vec(), integrity: false, requests: 0, nonces: HashMap::new() }
+  }
+
+  // Prefer qop=auth-int when the challenge offers it along with auth
+  pub fn with_integrity(mut self, integrity: bool) -> DigestClient<S> {
+    self.integrity = integrity;
+    self
+  }
+
+  fn qop<'a>(&self, challenge: &DigestChallenge<'a>) -> Result<Option<Qop<'a>>, Error> {
+    let options = match challenge.qop {
+      Some(options) => options,
+      None => return Ok(None)
+    };
+    let auth = options.contains(Qop::Auth);
+    let auth_int = options.contains(Qop::AuthInt);
+    match (auth, auth_int) {
+      (true, true) if self.integrity => Ok(Some(Qop::AuthInt)),
+      (true, _) => Ok(Some(Qop::Auth)),
+      (false, true) => Ok(Some(Qop::AuthInt)),
+      (false, false) => Err(Error::UnsupportedQop)
+    }
+  }
+
+  // nonce-count to send with the nonce
+  fn count(&mut self, realm: &Binary, nonce: &Binary) -> u32 {
+    let entry = self.nonces.entry(realm.to_vec()).or_insert_with(|| (nonce.to_vec(), 0));
+    if entry.0 != nonce {
+      *entry = (nonce.to_vec(), 0);
+    }
+    entry.1 = entry.1.wrapping_add(1);
+    entry.1
+  }
+
+  fn cnonce(&mut self, hash: Hash, nonce: &Binary) -> Vec<u8> {
+    self.requests += 1;
+    let material = hash.hex(&[&self.seed, nonce, &self.requests.to_be_bytes()]);
+    let mut cnonce = vec![0u8; 24];
+    let n = id::token(&material, &mut cnonce);
+    cnonce.truncate(n);
+    cnonce
+  }
+
+  // Writes the Authorization or Proxy-Authorization value answering the
+  // challenge for the request. Every call counts as a request sent with
+  // the challenge's nonce.
+  pub fn authorize(&mut self, challenge: DigestChallenge, request: &Request, buf: &mut Binary) -> Result<usize, Error> {
+    let algorithm = challenge.algorithm.unwrap_or(Algorithm::Md5);
+    let hash = Hash::of(algorithm).ok_or(Error::UnsupportedAlgorithm)?;
+    let qop = self.qop(&challenge)?;
+
+    let realm = unquote(challenge.realm);
+    let nonce = unquote(challenge.nonce);
+    let (username, password) = {
+      let secret = self.store.lookup(&realm).ok_or(Error::NoCredentials)?;
+      (secret.username.to_vec(), secret.password.to_vec())
+    };
+
+    let nc = self.count(&realm, &nonce);
+    let cnonce = if qop.is_some() || algorithm.is_session() {
+      Some(self.cnonce(hash, &nonce))
+    } else {
+      None
+    };
+
+    let ha1 = ha1(algorithm, &username, &realm, &password).ok_or(Error::UnsupportedAlgorithm)?;
+    let input = Input {
+      algorithm,
+      nonce: &nonce,
+      cnonce: cnonce.as_deref().unwrap_or(b""),
+      nc,
+      qop,
+      method: request.method.as_bytes(),
+      uri: request.uri,
+      body: request.body
+    };
+    let response = request_digest(&ha1, &input).ok_or(Error::UnsupportedAlgorithm)?;
+
+    let username = escape_quoted(&username);
+    let credentials = Credentials::Digest(DigestResponse {
+      username: &username,
+      realm: challenge.realm,
+      nonce: challenge.nonce,
+      uri: request.uri,
+      response: &response,
+      algorithm: challenge.algorithm,
+      cnonce: cnonce.as_deref(),
+      opaque: challenge.opaque,
+      qop,
+      nc: qop.map(|_| nc),
+      params: AuthParams::default()
+    });
+    Ok(assemble::credentials(credentials, buf)?)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{DigestClient, Secret, Error, select, authorization_header};
+  use crate::auth::Request;
+  use crate::auth::digest::{Input, ha1, request_digest};
+  use crate::msg::header::HeaderName;
+  use crate::msg::header::auth::{
+    Algorithm, Qop, Challenge, Credentials, DigestChallenge, DigestResponse, QopOptions, parse
+  };
+  use crate::msg::method::Method;
+  use crate::msg::output::AssembleError;
+  use std::collections::HashMap;
+
+  const ALICE: Secret<'static> = Secret { username: b"alice", password: b"secret" };
+
+  fn challenge(value: &[u8]) -> DigestChallenge<'_> {
+    match parse::challenge(value).unwrap().1 {
+      Challenge::Digest(d) => d,
+      c => panic!("{:?}", c)
+    }
+  }
+
+  // checks the response the way a server does
+  fn verify<'v>(value: &'v [u8], request: &Request, password: &[u8]) -> DigestResponse<'v> {
+    let (rest, c) = parse::credentials(value).unwrap();
+    assert_eq!(rest, b"");
+    let d = match c {
+      Credentials::Digest(d) => d,
+      _ => panic!("{:?}", c)
+    };
+    let algorithm = d.algorithm.unwrap_or(Algorithm::Md5);
+    let ha1 = ha1(algorithm, d.username, d.realm, password).unwrap();
+    let input = Input {
+      algorithm,
+      nonce: d.nonce,
+      cnonce: d.cnonce.unwrap_or(b""),
+      nc: d.nc.unwrap_or(0),
+      qop: d.qop,
+      method: request.method.as_bytes(),
+      uri: d.uri,
+      body: request.body
+    };
+    assert_eq!(request_digest(&ha1, &input).unwrap(), d.response.to_vec());
+    d
+  }
+
+  #[test]
+  fn authorize_test() {
+    let request = Request { method: Method::Register, uri: b"sip:atlanta.com", body: b"" };
+    let mut client = DigestClient::new(ALICE, b"seed");
+    let mut buf = [0u8; 512];
+
+    let c = challenge(b"Digest realm=\"atlanta.com\", nonce=\"84a4cc6f3082121f32b42a2187831a9e\", qop=\"auth\", opaque=\"o\"");
+    let n = client.authorize(c, &request, &mut buf).unwrap();
+    let value = buf[..n].to_vec();
+    let first = verify(&value, &request, b"secret");
+    assert_eq!(first.username, b"alice");
+    assert_eq!(first.realm, b"atlanta.com");
+    assert_eq!(first.uri, b"sip:atlanta.com");
+    assert_eq!(first.qop, Some(Qop::Auth));
+    assert_eq!(first.nc, Some(1));
+    assert_eq!(first.opaque, Some(&b"o"[..]));
+    assert_eq!(first.algorithm, None);
+
+    // the same nonce counts up and gets a fresh cnonce
+    let n = client.authorize(c, &request, &mut buf).unwrap();
+    let second = verify(&buf[..n], &request, b"secret");
+    assert_eq!(second.nc, Some(2));
+    assert_ne!(second.cnonce, first.cnonce);
+
+    // a new nonce starts over
+    let c = challenge(b"Digest realm=\"atlanta.com\", nonce=\"2\", qop=\"auth\", stale=true");
+    let n = client.authorize(c, &request, &mut buf).unwrap();
+    assert_eq!(verify(&buf[..n], &request, b"secret").nc, Some(1));
+
+    // RFC 2069 compatible challenge has neither cnonce nor nc
+    let c = challenge(b"Digest realm=\"atlanta.com\", nonce=\"3\"");
+    let n = client.authorize(c, &request, &mut buf).unwrap();
+    let d = verify(&buf[..n], &request, b"secret");
+    assert!(d.nc.is_none() && d.cnonce.is_none());
+
+    assert_eq!(
+      client.authorize(c, &request, &mut buf[..10]),
+      Err(Error::Assemble(AssembleError::NotEnoughOutSpace { written: 7 }))
+    );
+  }
+
+  #[test]
+  fn algorithm_test() {
+    let request = Request { method: Method::Invite, uri: b"sip:bob@biloxi.com", body: b"v=0\r\n" };
+    let mut buf = [0u8; 512];
+    let mut client = DigestClient::new(ALICE, b"seed").with_integrity(true);
+
+    for algorithm in &[b"MD5-sess".as_ref(), b"SHA-256", b"SHA-256-sess", b"SHA-512-256", b"SHA-512-256-sess"] {
+      let mut c = challenge(b"Digest realm=\"biloxi.com\", nonce=\"n\", qop=\"auth,auth-int\"");
+      c.algorithm = Some(Algorithm::from_bytes(algorithm));
+      let n = client.authorize(c, &request, &mut buf).unwrap();
+      let d = verify(&buf[..n], &request, b"secret");
+      assert_eq!(d.algorithm.map(|a| a.as_bytes()), Some(*algorithm));
+      assert_eq!(d.qop, Some(Qop::AuthInt));
+      assert_eq!(d.response.len(), if c.algorithm == Some(Algorithm::Md5Sess) { 32 } else { 64 });
+    }
+
+    let mut c = challenge(b"Digest realm=\"biloxi.com\", nonce=\"n\", algorithm=AKAv1-MD5");
+    assert_eq!(client.authorize(c, &request, &mut buf), Err(Error::UnsupportedAlgorithm));
+    c.algorithm = None;
+    c.qop = QopOptions::new(b"x-other");
+    assert_eq!(client.authorize(c, &request, &mut buf), Err(Error::UnsupportedQop));
+  }
+
+  #[test]
+  fn store_test() {
+    let mut store = HashMap::new();
+    store.insert(b"biloxi.com".to_vec(), (b"b\"ob".to_vec(), b"pw".to_vec()));
+    let mut client = DigestClient::new(store, b"seed");
+    let request = Request { method: Method::Bye, uri: b"sip:b@biloxi.com", body: b"" };
+    let mut buf = [0u8; 512];
+
+    let c = challenge(b"Digest realm=\"atlanta.com\", nonce=\"n\"");
+    assert_eq!(client.authorize(c, &request, &mut buf), Err(Error::NoCredentials));
+
+    // username is escaped in the header and hashed unescaped
+    let c = challenge(b"Digest realm=\"biloxi.com\", nonce=\"n\"");
+    let n = client.authorize(c, &request, &mut buf).unwrap();
+    let d = match parse::credentials(&buf[..n]).unwrap().1 {
+      Credentials::Digest(d) => d,
+      _ => unreachable!()
+    };
+    assert_eq!(d.username, b"b\\\"ob");
+    let ha1 = ha1(Algorithm::Md5, b"b\"ob", b"biloxi.com", b"pw").unwrap();
+    let input = Input {
+      algorithm: Algorithm::Md5, nonce: b"n", cnonce: b"", nc: 0, qop: None,
+      method: b"BYE", uri: b"sip:b@biloxi.com", body: b""
+    };
+    assert_eq!(request_digest(&ha1, &input).unwrap(), d.response.to_vec());
+  }
+
+  #[test]
+  fn select_test() {
+    let values: [&[u8]; 4] = [
+      b"Basic realm=\"a\"",
+      b"Digest realm=\"a\", nonce=\"1\"",
+      b"Digest realm=\"a\", nonce=\"2\", algorithm=SHA-256",
+      b"Digest realm=\"a\", nonce=\"3\", algorithm=SHA-256-sess"
+    ];
+    let challenges = values.iter().map(|v| parse::challenge(v).unwrap().1);
+    assert_eq!(select(challenges).map(|d| d.nonce), Some(&b"2"[..]));
+    assert_eq!(select(None), None);
+
+    assert_eq!(authorization_header(HeaderName::ProxyAuthenticate), Some(HeaderName::ProxyAuthorization));
+    assert_eq!(authorization_header(HeaderName::Via), None);
+  }
+}
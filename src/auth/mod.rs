@@ -0,0 +1,18 @@
+// Digest access authentication, RFC 3261 22.4 with the algorithms of
+// RFC 8760. Header grammar lives in msg::header::auth.
+
+use crate::msg::Binary;
+use crate::msg::method::Method;
+
+pub mod client;
+pub mod digest;
+pub mod server;
+
+// Request the credentials are for, `uri` is the Request-URI as it is
+// sent and `body` only matters for qop=auth-int
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Request<'a> {
+  pub method: Method<'a>,
+  pub uri: &'a Binary,
+  pub body: &'a Binary
+}
@@ -0,0 +1,431 @@
+use crate::msg::Binary;
+use crate::msg::abnf::userinfo::unquote;
+use crate::msg::header::{Headers, HeaderName};
+use crate::msg::header::auth::{
+  Algorithm, Qop, QopOptions, Credentials, DigestChallenge, DigestResponse, AuthenticationInfo,
+  Challenge, credentials, assemble
+};
+use crate::msg::output::AssembleError;
+use super::Request;
+use super::digest::{Hash, Input, request_digest};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::HashMap;
+
+// length of the timestamp in front of a nonce, 16 LHEX
+const STAMP_LEN: usize = 16;
+
+pub trait Ha1Store {
+  // H(username ":" realm ":" password) with the hash, None for unknown
+  // users. Username and realm are unquoted.
+  fn ha1(&self, username: &Binary, realm: &Binary, hash: Hash) -> Option<Vec<u8>>;
+}
+
+// passwords by username
+impl Ha1Store for HashMap<Vec<u8>, Vec<u8>> {
+  fn ha1(&self, username: &Binary, realm: &Binary, hash: Hash) -> Option<Vec<u8>> {
+    self.get(username).map(|password| hash.hex(&[username, realm, password]))
+  }
+}
+
+// lookup into a user database which keeps the hashes only
+impl<F> Ha1Store for F
+  where F: Fn(&Binary, &Binary, Hash) -> Option<Vec<u8>> {
+  fn ha1(&self, username: &Binary, realm: &Binary, hash: Hash) -> Option<Vec<u8>> {
+    self(username, realm, hash)
+  }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Error {
+  // no Digest credentials for the realm
+  NoCredentials,
+  UnknownUser,
+  UnsupportedAlgorithm,
+  // qop missing or not one of the offered ones
+  UnsupportedQop,
+  // digest-uri is not the Request-URI
+  UriMismatch,
+  // nonce wasn't issued by this server for the realm and algorithm
+  BadNonce,
+  // nonce was issued here but is too old, challenge with stale=true
+  Stale,
+  // nonce-count was seen already for the nonce
+  Replay,
+  BadResponse,
+  Assemble(AssembleError)
+}
+
+impl From<AssembleError> for Error {
+  fn from(e: AssembleError) -> Error {
+    Error::Assemble(e)
+  }
+}
+
+// compares without leaking the position of the first difference
+fn same(a: &Binary, b: &Binary) -> bool {
+  a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// Credentials which passed verification
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Authorized<'a> {
+  pub credentials: DigestResponse<'a>,
+  ha1: Vec<u8>
+}
+
+impl<'a> Authorized<'a> {
+  // Writes the Authentication-Info value of the response, rspauth covers
+  // the response body with qop=auth-int
+  pub fn authentication_info(&self, body: &Binary, buf: &mut Binary) -> Result<usize, Error> {
+    let c = &self.credentials;
+    let nonce = unquote(c.nonce);
+    let cnonce = unquote(c.cnonce.unwrap_or(b""));
+    let input = Input {
+      algorithm: c.algorithm.unwrap_or(Algorithm::Md5),
+      nonce: &nonce,
+      cnonce: &cnonce,
+      nc: c.nc.unwrap_or(0),
+      qop: c.qop,
+      method: b"",
+      uri: c.uri,
+      body
+    };
+    let rspauth = request_digest(&self.ha1, &input).ok_or(Error::UnsupportedAlgorithm)?;
+    let info = AuthenticationInfo {
+      qop: c.qop,
+      rspauth: Some(&rspauth),
+      cnonce: c.cnonce,
+      nc: c.nc,
+      ..Default::default()
+    };
+    Ok(assemble::authentication_info(info, buf)?)
+  }
+}
+
+// UAS side of Digest, RFC 3261 22.1, for a single realm.
+//
+// Nonces are the time they were issued at followed by an HMAC of it
+// together with the realm and the algorithm, so any nonce this server
+// gave out can be checked without keeping it. Times are seconds of
+// whatever clock the caller uses. Nonce counts are kept per nonce until
+// it expires to tell replays.
+pub struct DigestServer<S> {
+  realm: Vec<u8>,
+  key: Vec<u8>,
+  store: S,
+  lifetime: u64,
+  counts: HashMap<Vec<u8>, (u64, u32)>
+}
+
+impl<S: Ha1Store> DigestServer<S> {
+  pub const DEFAULT_LIFETIME: u64 = 300;
+
+  // `key` is the HMAC secret, every server which should accept the
+  // nonces of another one needs the same
+  pub fn new(realm: &Binary, key: &Binary, store: S) -> DigestServer<S> {
+    DigestServer {
+      realm: realm.to_vec(),
+      key: key.to_vec(),
+      store,
+      lifetime: Self::DEFAULT_LIFETIME,
+      counts: HashMap::new()
+    }
+  }
+
+  // Seconds a nonce is accepted for
+  pub fn with_lifetime(mut self, lifetime: u64) -> DigestServer<S> {
+    self.lifetime = lifetime;
+    self
+  }
+
+  pub fn realm(&self) -> &Binary {
+    &self.realm
+  }
+
+  fn mac(&self, stamp: &Binary, algorithm: Algorithm) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes keys of any length");
+    mac.update(stamp);
+    mac.update(b":");
+    mac.update(&self.realm);
+    mac.update(b":");
+    mac.update(algorithm.as_bytes());
+    let mut out = stamp.to_vec();
+    for b in mac.finalize().into_bytes().iter() {
+      out.extend_from_slice(format!("{:02x}", b).as_bytes());
+    }
+    out
+  }
+
+  pub fn nonce(&self, algorithm: Algorithm, now: u64) -> Vec<u8> {
+    self.mac(format!("{:016x}", now).as_bytes(), algorithm)
+  }
+
+  // time the nonce was issued at when this server issued it
+  fn issued(&self, nonce: &Binary, algorithm: Algorithm) -> Option<u64> {
+    let stamp = nonce.get(..STAMP_LEN)?;
+    if !stamp.iter().all(u8::is_ascii_hexdigit) || !same(&self.mac(stamp, algorithm), nonce) {
+      return None;
+    }
+    u64::from_str_radix(std::str::from_utf8(stamp).ok()?, 16).ok()
+  }
+
+  // Writes a WWW-Authenticate or Proxy-Authenticate value with a fresh
+  // nonce. RFC 8760 asks for one header per algorithm, the most
+  // preferred first.
+  pub fn challenge(&self, algorithm: Algorithm, stale: bool, now: u64, buf: &mut Binary) -> Result<usize, Error> {
+    Hash::of(algorithm).ok_or(Error::UnsupportedAlgorithm)?;
+    let nonce = self.nonce(algorithm, now);
+    let mut c = DigestChallenge::new(&self.realm, &nonce);
+    c.algorithm = Some(algorithm);
+    c.qop = QopOptions::new(b"auth,auth-int");
+    c.stale = stale;
+    Ok(assemble::challenge(Challenge::Digest(c), buf)?)
+  }
+
+  // Verifies the Digest credentials for the realm out of the
+  // Authorization or Proxy-Authorization headers of the request
+  pub fn authenticate<'a>(&mut self, headers: Headers<'a>, name: HeaderName, request: &Request, now: u64)
+    -> Result<Authorized<'a>, Error> {
+    let found = credentials(headers, name).find_map(|c| match c {
+      Credentials::Digest(d) if unquote(d.realm) == self.realm.as_slice() => Some(d),
+      _ => None
+    });
+    self.verify(found.ok_or(Error::NoCredentials)?, request, now)
+  }
+
+  pub fn verify<'a>(&mut self, credentials: DigestResponse<'a>, request: &Request, now: u64) -> Result<Authorized<'a>, Error> {
+    let c = credentials;
+    if unquote(c.realm) != self.realm.as_slice() {
+      return Err(Error::NoCredentials);
+    }
+    let algorithm = c.algorithm.unwrap_or(Algorithm::Md5);
+    let hash = Hash::of(algorithm).ok_or(Error::UnsupportedAlgorithm)?;
+    let qop = match c.qop {
+      Some(qop @ Qop::Auth) | Some(qop @ Qop::AuthInt) => qop,
+      _ => return Err(Error::UnsupportedQop)
+    };
+    let (nc, cnonce) = match (c.nc, c.cnonce) {
+      (Some(nc), Some(cnonce)) => (nc, unquote(cnonce)),
+      _ => return Err(Error::BadResponse)
+    };
+    if c.uri != request.uri {
+      return Err(Error::UriMismatch);
+    }
+
+    let nonce = unquote(c.nonce);
+    let issued = self.issued(&nonce, algorithm).ok_or(Error::BadNonce)?;
+    if issued > now {
+      return Err(Error::BadNonce);
+    }
+
+    let username = unquote(c.username);
+    let ha1 = self.store.ha1(&username, &self.realm, hash).ok_or(Error::UnknownUser)?;
+    let input = Input {
+      algorithm,
+      nonce: &nonce,
+      cnonce: &cnonce,
+      nc,
+      qop: Some(qop),
+      method: request.method.as_bytes(),
+      uri: c.uri,
+      body: request.body
+    };
+    let expected = request_digest(&ha1, &input).ok_or(Error::UnsupportedAlgorithm)?;
+    if !same(&expected, &c.response.to_ascii_lowercase()) {
+      return Err(Error::BadResponse);
+    }
+    // stale only when the digest was right for the old nonce (RFC 2617
+    // 3.2.1), wrong credentials have to be asked for again instead
+    if now - issued > self.lifetime {
+      return Err(Error::Stale);
+    }
+
+    // only a correct response takes the count, so a forged one can't
+    // burn counts of the legitimate client
+    let lifetime = self.lifetime;
+    self.counts.retain(|_, (issued, _)| now.saturating_sub(*issued) <= lifetime);
+    let last = self.counts.entry(nonce.to_vec()).or_insert((issued, 0));
+    if nc <= last.1 {
+      return Err(Error::Replay);
+    }
+    last.1 = nc;
+
+    Ok(Authorized { credentials: c, ha1 })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{DigestServer, Error};
+  use crate::auth::Request;
+  use crate::auth::client::{DigestClient, Secret};
+  use crate::auth::digest::{Hash, ha1};
+  use crate::msg::header::HeaderName;
+  use crate::msg::header::auth::{Algorithm, Qop, Challenge, Credentials, DigestResponse, parse};
+  use crate::msg::header::parse::headers;
+  use crate::msg::method::Method;
+  use std::collections::HashMap;
+
+  const REALM: &[u8] = b"biloxi.com";
+  const REGISTER: Request<'static> = Request { method: Method::Register, uri: b"sip:biloxi.com", body: b"" };
+
+  fn server() -> DigestServer<HashMap<Vec<u8>, Vec<u8>>> {
+    let mut users = HashMap::new();
+    users.insert(b"bob".to_vec(), b"zanzibar".to_vec());
+    DigestServer::new(REALM, b"key", users)
+  }
+
+  fn client() -> DigestClient<Secret<'static>> {
+    DigestClient::new(Secret { username: b"bob", password: b"zanzibar" }, b"seed")
+  }
+
+  // challenge of the server answered by the client
+  fn answer(server: &DigestServer<HashMap<Vec<u8>, Vec<u8>>>, client: &mut DigestClient<Secret<'static>>,
+            algorithm: Algorithm, now: u64, request: &Request) -> Vec<u8> {
+    let mut buf = [0u8; 512];
+    let n = server.challenge(algorithm, false, now, &mut buf).unwrap();
+    let value = buf[..n].to_vec();
+    let c = match parse::challenge(&value).unwrap().1 {
+      Challenge::Digest(d) => d,
+      c => panic!("{:?}", c)
+    };
+    let n = client.authorize(c, request, &mut buf).unwrap();
+    buf[..n].to_vec()
+  }
+
+  fn response(value: &[u8]) -> DigestResponse<'_> {
+    match parse::credentials(value).unwrap().1 {
+      Credentials::Digest(d) => d,
+      c => panic!("{:?}", c)
+    }
+  }
+
+  #[test]
+  fn verify_test() {
+    let mut server = server();
+    let mut client = client();
+
+    for algorithm in &[Algorithm::Md5, Algorithm::Sha256Sess, Algorithm::Sha512_256] {
+      let value = answer(&server, &mut client, *algorithm, 1000, &REGISTER);
+      let authorized = server.verify(response(&value), &REGISTER, 1010).unwrap();
+      assert_eq!(authorized.credentials.username, b"bob");
+    }
+
+    // nonce expired, but it is still ours
+    let value = answer(&server, &mut client, Algorithm::Md5, 1000, &REGISTER);
+    assert_eq!(server.verify(response(&value), &REGISTER, 1000 + 301), Err(Error::Stale));
+    assert_eq!(server.verify(response(&value), &REGISTER, 999), Err(Error::BadNonce));
+
+    // other realm, key or algorithm
+    let other = DigestServer::new(REALM, b"other key", HashMap::new());
+    let value = answer(&other, &mut client, Algorithm::Md5, 1000, &REGISTER);
+    assert_eq!(server.verify(response(&value), &REGISTER, 1000), Err(Error::BadNonce));
+    let value = answer(&server, &mut client, Algorithm::Md5, 1000, &REGISTER);
+    let mut forged = response(&value);
+    forged.algorithm = Some(Algorithm::Sha256);
+    assert_eq!(server.verify(forged, &REGISTER, 1000), Err(Error::BadNonce));
+    forged.realm = b"atlanta.com";
+    assert_eq!(server.verify(forged, &REGISTER, 1000), Err(Error::NoCredentials));
+
+    let value = answer(&server, &mut client, Algorithm::Md5, 1000, &REGISTER);
+    let mut wrong = response(&value);
+    wrong.response = b"00000000000000000000000000000000";
+    assert_eq!(server.verify(wrong, &REGISTER, 1000), Err(Error::BadResponse));
+    let mut wrong = response(&value);
+    wrong.qop = None;
+    assert_eq!(server.verify(wrong, &REGISTER, 1000), Err(Error::UnsupportedQop));
+    let invite = Request { method: Method::Invite, ..REGISTER };
+    assert_eq!(server.verify(response(&value), &invite, 1000), Err(Error::BadResponse));
+    let elsewhere = Request { uri: b"sip:atlanta.com", ..REGISTER };
+    assert_eq!(server.verify(response(&value), &elsewhere, 1000), Err(Error::UriMismatch));
+
+    let mut stranger = DigestClient::new(Secret { username: b"eve", password: b"x" }, b"seed");
+    let value = answer(&server, &mut stranger, Algorithm::Md5, 1000, &REGISTER);
+    assert_eq!(server.verify(response(&value), &REGISTER, 1000), Err(Error::UnknownUser));
+    assert_eq!(server.verify(response(&value), &REGISTER, 1000 + 301), Err(Error::UnknownUser));
+
+    // an expired nonce with the wrong password is not stale
+    let mut guesser = DigestClient::new(Secret { username: b"bob", password: b"guess" }, b"seed");
+    let value = answer(&server, &mut guesser, Algorithm::Md5, 1000, &REGISTER);
+    assert_eq!(server.verify(response(&value), &REGISTER, 1000 + 301), Err(Error::BadResponse));
+  }
+
+  #[test]
+  fn replay_test() {
+    let mut server = server();
+    let mut client = client();
+    let mut buf = [0u8; 512];
+
+    let n = server.challenge(Algorithm::Md5, false, 1000, &mut buf).unwrap();
+    let challenge = buf[..n].to_vec();
+    let c = match parse::challenge(&challenge).unwrap().1 {
+      Challenge::Digest(d) => d,
+      c => panic!("{:?}", c)
+    };
+
+    let n = client.authorize(c, &REGISTER, &mut buf).unwrap();
+    let first = buf[..n].to_vec();
+    let n = client.authorize(c, &REGISTER, &mut buf).unwrap();
+    let second = buf[..n].to_vec();
+
+    assert!(server.verify(response(&first), &REGISTER, 1000).is_ok());
+    assert_eq!(server.verify(response(&first), &REGISTER, 1001), Err(Error::Replay));
+    assert!(server.verify(response(&second), &REGISTER, 1002).is_ok());
+    assert_eq!(server.verify(response(&first), &REGISTER, 1003), Err(Error::Replay));
+
+    // the nonce is stale from then on and its count gets dropped
+    assert_eq!(server.verify(response(&second), &REGISTER, 2000), Err(Error::Stale));
+    let value = answer(&server, &mut client, Algorithm::Md5, 2000, &REGISTER);
+    assert!(server.verify(response(&value), &REGISTER, 2000).is_ok());
+    assert_eq!(server.counts.len(), 1);
+  }
+
+  #[test]
+  fn authentication_info_test() {
+    let mut server = server();
+    let mut client = client().with_integrity(true);
+    let request = Request { method: Method::Invite, uri: b"sip:bob@biloxi.com", body: b"v=0\r\n" };
+    let value = answer(&server, &mut client, Algorithm::Sha256, 1000, &request);
+
+    let raw = [b"Authorization: ".as_ref(), &value, b"\r\n\r\n"].concat();
+    let (_, hs) = headers(&raw).unwrap();
+    let authorized = server.authenticate(hs, HeaderName::Authorization, &request, 1000).unwrap();
+    assert_eq!(authorized.credentials.qop, Some(Qop::AuthInt));
+    assert_eq!(server.authenticate(hs, HeaderName::ProxyAuthorization, &request, 1000), Err(Error::NoCredentials));
+
+    let mut buf = [0u8; 256];
+    let n = authorized.authentication_info(b"", &mut buf).unwrap();
+    let info = parse::authentication_info(&buf[..n]).unwrap().1;
+    assert_eq!(info.qop, Some(Qop::AuthInt));
+    assert_eq!(info.nc, Some(1));
+    assert_eq!(info.cnonce, authorized.credentials.cnonce);
+    assert_eq!(info.rspauth.map(<[u8]>::len), Some(64));
+  }
+
+  #[test]
+  fn store_test() {
+    let expected = ha1(Algorithm::Md5, b"bob", REALM, b"zanzibar").unwrap();
+    let by_hash = move |username: &[u8], _: &[u8], hash: Hash| {
+      if username == b"bob" && hash == Hash::Md5 { Some(expected.clone()) } else { None }
+    };
+    let mut server = DigestServer::new(REALM, b"key", by_hash).with_lifetime(10);
+    let mut client = client();
+    let mut buf = [0u8; 512];
+
+    let n = server.challenge(Algorithm::Md5, true, 1000, &mut buf).unwrap();
+    let value = buf[..n].to_vec();
+    let c = match parse::challenge(&value).unwrap().1 {
+      Challenge::Digest(d) => d,
+      c => panic!("{:?}", c)
+    };
+    assert!(c.stale);
+    assert_eq!(c.nonce, server.nonce(Algorithm::Md5, 1000).as_slice());
+    let n = client.authorize(c, &REGISTER, &mut buf).unwrap();
+    let value = buf[..n].to_vec();
+    assert!(server.verify(response(&value), &REGISTER, 1010).is_ok());
+    assert_eq!(server.verify(response(&value), &REGISTER, 1011), Err(Error::Stale));
+
+    assert_eq!(server.challenge(Algorithm::Other(b"x"), false, 0, &mut buf), Err(Error::UnsupportedAlgorithm));
+  }
+}
@@ -1,4 +1,4 @@
-
 #[macro_use] extern crate nom;
 
-pub mod msg;
\ No newline at end of file
+pub mod auth;
+pub mod msg;
@@ -1,3 +1,10 @@
+use super::ch::{alpha, unreserved, escaped, is_unreserved};
+use super::host::{hostport, Host};
+use super::{map_byte, whole};
+use crate::msg::{Binary, Byte};
+use nom::IResult;
+use nom::Err::Error;
+use nom::error::ErrorKind;
 
 // absoluteURI      =  scheme ":" ( hier-part / opaque-part )
 // hier-part        =  ( net-path / abs-path ) [ "?" query ]
@@ -17,4 +24,537 @@
 // srvr             =  [ [ userinfo "@" ] hostport ]
 // reg-name         =  1*( unreserved / escaped / "$" / ","
 //                    / ";" / ":" / "@" / "&" / "=" / "+" )
-// query            =  *uric
\ No newline at end of file
+// query            =  *uric
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct AbsoluteUri<'a> {
+  pub scheme: &'a Binary,
+  pub part: Part<'a>
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Part<'a> {
+  // "//authority/path?query" or "/path?query", authority is None
+  // only for the latter
+  Hier {
+    authority: Option<Authority<'a>>,
+    path: Option<PathSegments<'a>>,
+    query: Option<&'a Binary>
+  },
+  // everything after "scheme:" when it doesn't start with a slash,
+  // e.g. "+1-201-555-0123" of "tel:+1-201-555-0123"
+  Opaque(&'a Binary)
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Authority<'a> {
+  Server(Server<'a>),
+  RegName(&'a Binary),
+  // the empty srvr, like in "file:///etc"
+  Empty
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Server<'a> {
+  pub userinfo: Option<&'a Binary>,
+  pub host: Host<'a>,
+  pub port: Option<u16>
+}
+
+// path-segments without the leading "/"
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct PathSegments<'a>(&'a Binary);
+
+impl<'a> PathSegments<'a> {
+  pub fn as_bytes(&self) -> &'a Binary {
+    self.0
+  }
+
+  // every segment with its ";" params, still escaped
+  pub fn iter(&self) -> std::slice::Split<'a, u8, fn(&u8) -> bool> {
+    fn is_slash(b: &u8) -> bool { *b == b'/' }
+    self.0.split(is_slash as fn(&u8) -> bool)
+  }
+}
+
+// scheme           =  ALPHA *( ALPHA / DIGIT / "+" / "-" / "." )
+named!(#[inline],
+  pub scheme,
+  recognize!(tuple!(
+    alpha,
+    many_times!(call!(map_byte, |i| i.is_ascii_alphanumeric() || i == b'+' || i == b'-' || i == b'.'))
+  ))
+);
+
+// reserved    =  ";" / "/" / "?" / ":" / "@" / "&" / "=" / "+"
+//                / "$" / ","
+named!(#[inline],
+  pub reserved<u8>,
+  one_of_byte!(b';' | b'/' | b'?' | b':' | b'@' | b'&' | b'=' | b'+' | b'$' | b',')
+);
+
+#[inline]
+pub fn is_reserved(b: Byte) -> bool {
+  matches!(b, b';' | b'/' | b'?' | b':' | b'@' | b'&' | b'=' | b'+' | b'$' | b',')
+}
+
+// uric             =  reserved / unreserved / escaped
+named!(#[inline],
+  pub uric<u8>,
+  alt!(reserved | unreserved | escaped)
+);
+
+// uric which don't need escaping
+#[inline]
+pub fn is_uric_char(b: Byte) -> bool {
+  is_unreserved(b) || is_reserved(b)
+}
+
+// uric-no-slash    =  unreserved / escaped / ";" / "?" / ":" / "@"
+//                    / "&" / "=" / "+" / "$" / ","
+named!(#[inline],
+  pub uric_no_slash<u8>,
+  alt!(
+    unreserved | escaped |
+    one_of_byte!(b';' | b'?' | b':' | b'@' | b'&' | b'=' | b'+' | b'$' | b',')
+  )
+);
+
+// pchar            =  unreserved / escaped /
+//                    ":" / "@" / "&" / "=" / "+" / "$" / ","
+named!(#[inline],
+  pub pchar<u8>,
+  alt!(
+    unreserved | escaped |
+    one_of_byte!(b':' | b'@' | b'&' | b'=' | b'+' | b'$' | b',')
+  )
+);
+
+// path-segments characters which don't need escaping
+#[inline]
+pub fn is_path_char(b: Byte) -> bool {
+  is_unreserved(b) || matches!(b, b':' | b'@' | b'&' | b'=' | b'+' | b'$' | b',' | b';' | b'/')
+}
+
+// opaque-part      =  uric-no-slash *uric
+named!(#[inline],
+  pub opaque_part,
+  recognize!(tuple!(uric_no_slash, many_times!(uric)))
+);
+
+// query            =  *uric
+named!(#[inline],
+  pub query,
+  many_times!(uric)
+);
+
+// path-segments    =  segment *( "/" segment )
+// segment          =  *pchar *( ";" param )
+// param            =  *pchar
+named!(#[inline],
+  pub path_segments<PathSegments>,
+  map!(many_times!(alt!(pchar | one_of_byte!(b';' | b'/'))), PathSegments)
+);
+
+// abs-path         =  "/" path-segments
+named!(#[inline],
+  pub abs_path<PathSegments>,
+  preceded!(byte!(b'/'), path_segments)
+);
+
+// reg-name         =  1*( unreserved / escaped / "$" / ","
+//                    / ";" / ":" / "@" / "&" / "=" / "+" )
+named!(#[inline],
+  pub reg_name,
+  at_least_one!(alt!(
+    unreserved | escaped |
+    one_of_byte!(b'$' | b',' | b';' | b':' | b'@' | b'&' | b'=' | b'+')
+  ))
+);
+
+// reg-name characters which don't need escaping
+#[inline]
+pub fn is_reg_name_char(b: Byte) -> bool {
+  is_unreserved(b) || matches!(b, b'$' | b',' | b';' | b':' | b'@' | b'&' | b'=' | b'+')
+}
+
+// userinfo         =  *( unreserved | escaped |
+//                        ";" | ":" | "&" | "=" | "+" | "$" | "," )
+// (as defined by RFC 2396, without the trailing "@" of the SIP one)
+named!(#[inline],
+  pub server_userinfo,
+  many_times!(alt!(
+    unreserved | escaped |
+    one_of_byte!(b';' | b':' | b'&' | b'=' | b'+' | b'$' | b',')
+  ))
+);
+
+// server userinfo characters which don't need escaping
+#[inline]
+pub fn is_server_userinfo_char(b: Byte) -> bool {
+  is_unreserved(b) || matches!(b, b';' | b':' | b'&' | b'=' | b'+' | b'$' | b',')
+}
+
+// srvr             =  [ [ userinfo "@" ] hostport ]
+// (the empty srvr is handled by authority)
+#[inline]
+pub fn srvr(input: &Binary) -> IResult<&Binary, Server<'_>> {
+  let (rest, userinfo) = match terminated!(input, server_userinfo, byte!(b'@')) {
+    Ok((r, u)) => (r, Some(u)),
+    Err(_) => (input, None)
+  };
+
+  let (rest, (host, port)) = hostport(rest)?;
+  Ok((rest, Server { userinfo, host, port }))
+}
+
+// authority        =  srvr / reg-name
+//
+// Both alternatives share most of the characters, so the extent of the
+// authority is found first and then either of them must span it whole.
+#[inline]
+pub fn authority(input: &Binary) -> IResult<&Binary, Authority<'_>> {
+  let (rest, extent): (&Binary, &Binary) = many_times!(input, alt!(
+    unreserved | escaped |
+    one_of_byte!(b'$' | b',' | b';' | b':' | b'@' | b'&' | b'=' | b'+' | b'[' | b']')
+  ))?;
+
+  if extent.is_empty() {
+    return Ok((rest, Authority::Empty));
+  }
+
+  if let Some(server) = whole(srvr(extent)) {
+    Ok((rest, Authority::Server(server)))
+  } else if let Some(name) = whole(reg_name(extent)) {
+    Ok((rest, Authority::RegName(name)))
+  } else {
+    Err(Error((input, ErrorKind::Verify)))
+  }
+}
+
+// net-path         =  "//" authority [ abs-path ]
+#[inline]
+pub fn net_path(input: &Binary)
+  -> IResult<&Binary, (Authority<'_>, Option<PathSegments<'_>>)> {
+  let (rest, _) = tag!(input, "//")?;
+  let (rest, authority) = authority(rest)?;
+  match abs_path(rest) {
+    Ok((r, path)) => Ok((r, (authority, Some(path)))),
+    Err(_) => Ok((rest, (authority, None)))
+  }
+}
+
+// hier-part        =  ( net-path / abs-path ) [ "?" query ]
+//
+// "//" always starts a net-path, though it would make
+// an abs-path with an empty first segment as well
+#[inline]
+pub fn hier_part(input: &Binary) -> IResult<&Binary, Part<'_>> {
+  let (rest, (authority, path)) = if input.starts_with(b"//") {
+    map!(input, net_path, |(a, p)| (Some(a), p))?
+  } else {
+    map!(input, abs_path, |p| (None, Some(p)))?
+  };
+
+  let (rest, query) = match preceded!(rest, byte!(b'?'), query) {
+    Ok((r, q)) => (r, Some(q)),
+    Err(_) => (rest, None)
+  };
+
+  Ok((rest, Part::Hier { authority, path, query }))
+}
+
+// absoluteURI      =  scheme ":" ( hier-part / opaque-part )
+named!(#[inline],
+  pub absolute_uri<AbsoluteUri>,
+  do_parse!(
+    scheme: scheme >>
+    byte!(b':') >>
+    part: alt!(hier_part | opaque_part => { Part::Opaque }) >>
+    ( AbsoluteUri { scheme, part } )
+  )
+);
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::abnf::absolute_uri::{
+    AbsoluteUri, Part, Authority,
+    is_uric_char, is_path_char, is_reg_name_char, is_server_userinfo_char
+  };
+  use crate::msg::abnf::host::assemble::hostport;
+  use crate::msg::output::{Output, AssembleResult, AssembleError};
+
+  pub fn authority(a: Authority, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    match a {
+      Authority::Server(s) => {
+        if let Some(u) = s.userinfo {
+          out.put_component(u, is_server_userinfo_char)?;
+          out.put_byte(b'@')?;
+        }
+        out.with(|b| hostport(s.host, s.port, b))?;
+      },
+      Authority::RegName(name) => out.put_component(name, is_reg_name_char)?,
+      Authority::Empty => ()
+    }
+    out.finish()
+  }
+
+  // absoluteURI      =  scheme ":" ( hier-part / opaque-part )
+  pub fn absolute_uri(u: AbsoluteUri, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.put(u.scheme)?;
+    out.put_byte(b':')?;
+    match u.part {
+      Part::Opaque(opaque) => {
+        // opaque-part mustn't start with a slash
+        if opaque.starts_with(b"/") {
+          return Err(AssembleError::NotEscaped { written: out.written() });
+        }
+        out.put_component(opaque, is_uric_char)?;
+      },
+      Part::Hier { authority: a, path, query } => {
+        if let Some(a) = a {
+          out.put(b"//")?;
+          out.with(|b| authority(a, b))?;
+        }
+        if let Some(path) = path {
+          out.put_byte(b'/')?;
+          out.put_component(path.as_bytes(), is_path_char)?;
+        }
+        if let Some(q) = query {
+          out.put_byte(b'?')?;
+          out.put_component(q, is_uric_char)?;
+        }
+      }
+    }
+    out.finish()
+  }
+}
+
+pub mod owned {
+  use crate::msg::Binary;
+  use crate::msg::abnf::host::owned::Host;
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub struct AbsoluteUri {
+    pub scheme: Vec<u8>,
+    pub part: Part
+  }
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub enum Part {
+    Hier {
+      authority: Option<Authority>,
+      path: Option<PathSegments>,
+      query: Option<Vec<u8>>
+    },
+    Opaque(Vec<u8>)
+  }
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub enum Authority {
+    Server(Server),
+    RegName(Vec<u8>),
+    Empty
+  }
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub struct Server {
+    pub userinfo: Option<Vec<u8>>,
+    pub host: Host,
+    pub port: Option<u16>
+  }
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub struct PathSegments(Vec<u8>);
+
+  impl PathSegments {
+    pub fn as_bytes(&self) -> &Binary {
+      &self.0
+    }
+  }
+
+  impl AbsoluteUri {
+    pub fn as_borrowed(&self) -> super::AbsoluteUri<'_> {
+      super::AbsoluteUri {
+        scheme: &self.scheme,
+        part: match &self.part {
+          Part::Hier { authority, path, query } => super::Part::Hier {
+            authority: authority.as_ref().map(|a| match a {
+              Authority::Server(s) => super::Authority::Server(super::Server {
+                userinfo: s.userinfo.as_deref(),
+                host: s.host.as_borrowed(),
+                port: s.port
+              }),
+              Authority::RegName(n) => super::Authority::RegName(n),
+              Authority::Empty => super::Authority::Empty
+            }),
+            path: path.as_ref().map(|p| super::PathSegments(&p.0)),
+            query: query.as_deref()
+          },
+          Part::Opaque(o) => super::Part::Opaque(o)
+        }
+      }
+    }
+  }
+
+  impl<'a> super::AbsoluteUri<'a> {
+    pub fn into_owned(self) -> AbsoluteUri {
+      AbsoluteUri {
+        scheme: self.scheme.to_vec(),
+        part: match self.part {
+          super::Part::Hier { authority, path, query } => Part::Hier {
+            authority: authority.map(|a| match a {
+              super::Authority::Server(s) => Authority::Server(Server {
+                userinfo: s.userinfo.map(<[u8]>::to_vec),
+                host: s.host.into_owned(),
+                port: s.port
+              }),
+              super::Authority::RegName(n) => Authority::RegName(n.to_vec()),
+              super::Authority::Empty => Authority::Empty
+            }),
+            path: path.map(|p| PathSegments(p.0.to_vec())),
+            query: query.map(<[u8]>::to_vec)
+          },
+          super::Part::Opaque(o) => Part::Opaque(o.to_vec())
+        }
+      }
+    }
+  }
+
+  impl<'a> From<super::AbsoluteUri<'a>> for AbsoluteUri {
+    fn from(u: super::AbsoluteUri<'a>) -> AbsoluteUri {
+      u.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a AbsoluteUri> for super::AbsoluteUri<'a> {
+    fn from(u: &'a AbsoluteUri) -> super::AbsoluteUri<'a> {
+      u.as_borrowed()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{absolute_uri, scheme, assemble, AbsoluteUri, Part, Authority, Server};
+  use crate::msg::abnf::host::Host;
+  use crate::msg::output::AssembleError;
+  use std::net::Ipv4Addr;
+
+  fn parse(input: &str) -> (&[u8], AbsoluteUri<'_>) {
+    absolute_uri(input.as_bytes()).unwrap()
+  }
+
+  #[test]
+  fn scheme_test() {
+    assert_eq!(scheme("tel:".as_bytes()), Ok((":".as_bytes(), "tel".as_bytes())));
+    assert_eq!(scheme("x-a.b+c:".as_bytes()), Ok((":".as_bytes(), "x-a.b+c".as_bytes())));
+    assert!(scheme("1tel:".as_bytes()).is_err());
+  }
+
+  #[test]
+  fn opaque_test() {
+    let (rest, uri) = parse("tel:+1-201-555-0123;phone-context=example.com SIP/2.0");
+    assert_eq!(rest, " SIP/2.0".as_bytes());
+    assert_eq!(uri.scheme, "tel".as_bytes());
+    assert_eq!(uri.part, Part::Opaque("+1-201-555-0123;phone-context=example.com".as_bytes()));
+
+    let (_, uri) = parse("urn:service:sos ");
+    assert_eq!(uri.scheme, "urn".as_bytes());
+    assert_eq!(uri.part, Part::Opaque("service:sos".as_bytes()));
+
+    let (_, uri) = parse("mailto:alice%40home@example.com?subject=hi ");
+    assert_eq!(uri.part, Part::Opaque("alice%40home@example.com?subject=hi".as_bytes()));
+
+    let (_, uri) = parse("im:alice@example.com>");
+    assert_eq!(uri.part, Part::Opaque("alice@example.com".as_bytes()));
+  }
+
+  #[test]
+  fn hier_test() {
+    let (rest, uri) = parse("http://user:pw@192.0.2.1:8080/a/b;p=1/c?x=1&y=2 ");
+    assert_eq!(rest, " ".as_bytes());
+    assert_eq!(uri.scheme, "http".as_bytes());
+    match uri.part {
+      Part::Hier { authority, path, query } => {
+        assert_eq!(authority, Some(Authority::Server(Server {
+          userinfo: Some("user:pw".as_bytes()),
+          host: Host::Ipv4(Ipv4Addr::new(192, 0, 2, 1)),
+          port: Some(8080)
+        })));
+        let path = path.unwrap();
+        assert_eq!(path.iter().collect::<Vec<_>>(), vec!["a".as_bytes(), "b;p=1".as_bytes(), "c".as_bytes()]);
+        assert_eq!(query, Some("x=1&y=2".as_bytes()));
+      },
+      other => panic!("unexpected {:?}", other)
+    }
+
+    let (_, uri) = parse("pres://%7Ealice$/ ");
+    match uri.part {
+      Part::Hier { authority, path, query } => {
+        assert_eq!(authority, Some(Authority::RegName("%7Ealice$".as_bytes())));
+        assert_eq!(path.unwrap().as_bytes(), "".as_bytes());
+        assert_eq!(query, None);
+      },
+      other => panic!("unexpected {:?}", other)
+    }
+
+    let (_, uri) = parse("file:///etc/hosts ");
+    match uri.part {
+      Part::Hier { authority, path, .. } => {
+        assert_eq!(authority, Some(Authority::Empty));
+        assert_eq!(path.unwrap().iter().collect::<Vec<_>>(), vec!["etc".as_bytes(), "hosts".as_bytes()]);
+      },
+      other => panic!("unexpected {:?}", other)
+    }
+
+    let (_, uri) = parse("x:/only/path?q ");
+    match uri.part {
+      Part::Hier { authority, path, query } => {
+        assert_eq!(authority, None);
+        assert_eq!(path.unwrap().as_bytes(), "only/path".as_bytes());
+        assert_eq!(query, Some("q".as_bytes()));
+      },
+      other => panic!("unexpected {:?}", other)
+    }
+  }
+
+  #[test]
+  fn absolute_uri_fail_test() {
+    assert!(absolute_uri("tel: 123".as_bytes()).is_err());
+    assert!(absolute_uri("tel123".as_bytes()).is_err());
+    assert!(absolute_uri("http://[::1 ".as_bytes()).is_err());
+  }
+
+  #[test]
+  fn absolute_uri_assemble_test() {
+    let mut buf = [0u8; 128];
+    for uri in &[
+      "tel:+1-201-555-0123;phone-context=example.com",
+      "urn:service:sos",
+      "http://user:pw@192.0.2.1:8080/a/b;p=1/c?x=1&y=2",
+      "pres://%7Ealice$/",
+      "file:///etc/hosts",
+      "news://",
+      "x:/only/path?q"
+    ] {
+      let (_, parsed) = parse(uri);
+      let n = assemble::absolute_uri(parsed, &mut buf).unwrap();
+      assert_eq!(&buf[..n], uri.as_bytes());
+    }
+
+    let n = assemble::absolute_uri(AbsoluteUri {
+      scheme: b"im",
+      part: Part::Opaque(b"%2Falice%20smith@example.com")
+    }, &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"im:%2Falice%20smith@example.com".as_ref());
+
+    // opaque-part which isn't in its escaped form
+    for opaque in &[&b"/alice@example.com"[..], b"alice smith@example.com", b"100%"] {
+      let uri = AbsoluteUri { scheme: b"im", part: Part::Opaque(opaque) };
+      assert_eq!(assemble::absolute_uri(uri, &mut buf), Err(AssembleError::NotEscaped { written: 3 }));
+    }
+  }
+}
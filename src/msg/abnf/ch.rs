@@ -18,9 +18,19 @@ named!(#[inline],
   one_of_byte!(b'-' | b'_' | b'.' | b'!' | b'~' | b'*' | b'\'' | b'(' | b')')
 );
 
+#[inline]
+pub fn is_mark(i: Byte) -> bool {
+  matches!(i, b'-' | b'_' | b'.' | b'!' | b'~' | b'*' | b'\'' | b'(' | b')')
+}
+
 // unreserved  =  alphanum / mark
 named!(#[inline], pub unreserved<u8>, alt!(alphanum | mark));
 
+#[inline]
+pub fn is_unreserved(i: Byte) -> bool {
+  i.is_ascii_alphanumeric() || is_mark(i)
+}
+
 // escaped     =  "%" HEXDIG HEXDIG
 named!(#[inline],
   pub escaped<u8>,
@@ -28,20 +38,129 @@ named!(#[inline],
 );
 
 // user-unreserved  =  "&" / "=" / "+" / "$" / "," / ";" / "?" / "/"
-named!(#[inline], pub user_unreserved<u8>, call!(map_byte, |i| match i {
-  b'&' | b'=' | b'+' | b'$' | b',' | b';' | b'?' | b'/' => true,
-  _ => false
-}));
+named!(#[inline], pub user_unreserved<u8>, call!(map_byte, is_user_unreserved));
+
+#[inline]
+pub fn is_user_unreserved(i: Byte) -> bool {
+  matches!(i, b'&' | b'=' | b'+' | b'$' | b',' | b';' | b'?' | b'/')
+}
+
+// SP = %x20
+named!(#[inline], pub sp<u8>, byte!(b' '));
+
+// CRLF = CR LF
+named!(#[inline], pub crlf, tag!(b"\r\n"));
+
+#[inline]
+pub fn is_wsp(i: Byte) -> bool {
+  i == b' ' || i == b'\t'
+}
+
+// LWS  =  [*WSP CRLF] 1*WSP ; linear whitespace
+// Header values are always followed by CRLF, so the end of input is
+// taken as the end of whitespace rather than a reason to wait for more.
+#[inline]
+pub fn lws(input: &Binary) -> IResult<&Binary, &Binary> {
+  let wsp = |from: usize| input[from..].iter().take_while(|i| is_wsp(**i)).count() + from;
 
+  let before = wsp(0);
+  let folded = if input[before..].starts_with(b"\r\n") { before + 2 } else { before };
+  let end = wsp(folded);
+  if end == folded && (folded != before || before == 0) {
+    return Err(Error((input, ErrorKind::Space)));
+  }
+  Ok((&input[end..], &input[..end]))
+}
+
+// SWS  =  [LWS] ; sep whitespace
+#[inline]
+pub fn sws(input: &Binary) -> IResult<&Binary, &Binary> {
+  match lws(input) {
+    Ok(r) => Ok(r),
+    Err(_) => Ok((input, &input[..0]))
+  }
+}
+
+// HCOLON  =  *( SP / HTAB ) ":" SWS
+#[inline]
+pub fn hcolon(input: &Binary) -> IResult<&Binary, &Binary> {
+  let wsp = input.iter().take_while(|i| is_wsp(**i)).count();
+  let (rest, _) = map_byte(&input[wsp..], |i| i == b':')?;
+  let (rest, _) = sws(rest)?;
+  Ok((rest, &input[..input.len() - rest.len()]))
+}
+
+// SLASH   =  SWS "/" SWS ; slash
+// EQUAL   =  SWS "=" SWS ; equal
+// COMMA   =  SWS "," SWS ; comma
+// SEMI    =  SWS ";" SWS ; semicolon
+// COLON   =  SWS ":" SWS ; colon
+// Separators only appear inside of header values, a missing separator
+// is a mismatch even at the end of input.
+#[inline]
+fn separator(input: &Binary, sep: Byte) -> IResult<&Binary, &Binary> {
+  let (rest, _) = sws(input)?;
+  match rest.first() {
+    Some(i) if *i == sep => {
+      let (rest, _) = sws(&rest[1..])?;
+      Ok((rest, &input[..input.len() - rest.len()]))
+    },
+    _ => Err(Error((input, ErrorKind::Char)))
+  }
+}
+
+#[inline]
+pub fn slash(input: &Binary) -> IResult<&Binary, &Binary> {
+  separator(input, b'/')
+}
+
+#[inline]
+pub fn equal(input: &Binary) -> IResult<&Binary, &Binary> {
+  separator(input, b'=')
+}
+
+#[inline]
+pub fn comma(input: &Binary) -> IResult<&Binary, &Binary> {
+  separator(input, b',')
+}
+
+#[inline]
+pub fn semi(input: &Binary) -> IResult<&Binary, &Binary> {
+  separator(input, b';')
+}
+
+#[inline]
+pub fn colon(input: &Binary) -> IResult<&Binary, &Binary> {
+  separator(input, b':')
+}
 
 // token       =  1*(alphanum / "-" / "." / "!" / "%" / "*"
 //                   / "_" / "+" / "`" / "'" / "~" )
-named!(#[inline], pub token, take_while1!(|i: Byte| {
-  i.is_ascii_alphanumeric() || match i {
-    b'-' | b'.' | b'!' | b'%' | b'*' | b'_' | b'+' | b'`' | b'\'' | b'~' => true,
-    _ => false
+named!(#[inline], pub token, take_while1!(is_token_char));
+
+// token inside of a header value, which is already cut out of the
+// message, so the end of input ends the token
+#[inline]
+pub fn value_token(input: &Binary) -> IResult<&Binary, &Binary> {
+  let len = input.iter().take_while(|i| is_token_char(**i)).count();
+  if len == 0 {
+    return Err(Error((input, ErrorKind::TakeWhile1)));
   }
-}));
+  Ok((&input[len..], &input[..len]))
+}
+
+#[inline]
+pub fn is_token_char(i: Byte) -> bool {
+  i.is_ascii_alphanumeric() || matches!(i,
+    b'-' | b'.' | b'!' | b'%' | b'*' | b'_' | b'+' | b'`' | b'\'' | b'~'
+  )
+}
+
+// Whole input is a token
+#[inline]
+pub fn is_token(input: &Binary) -> bool {
+  !input.is_empty() && input.iter().all(|i| is_token_char(*i))
+}
 
 // word     =  1*(alphanum / "-" / "." / "!" / "%" / "*" /
 //             "_" / "+" / "`" / "'" / "~" /
@@ -50,13 +169,12 @@ named!(#[inline], pub token, take_while1!(|i: Byte| {
 //             "/" / "[" / "]" / "?" /
 //             "{" / "}" )
 named!(#[inline], pub word, take_while1!(|i: Byte| {
-  i.is_ascii_alphanumeric() || match i {
+  i.is_ascii_alphanumeric() || matches!(i,
     b'-' | b'.' | b'!' | b'%' | b'*' | b'_' | b'+' |
     b'`' | b'\'' | b'~' | b'(' | b')' | b'<' | b'>' |
     b':' | b'\\' | b'"' | b'/' | b'[' | b']' | b'?' |
-    b'{' | b'}' => true,
-    _ => false
-  }
+    b'{' | b'}'
+  )
 }));
 
 static UTF8_CHAR_WIDTH: [u8; 256] = [
@@ -80,7 +198,7 @@ static UTF8_CHAR_WIDTH: [u8; 256] = [
 
 #[inline]
 pub fn utf8_char_width(b: Byte) -> usize {
-  return UTF8_CHAR_WIDTH[b as usize] as usize;
+  UTF8_CHAR_WIDTH[b as usize] as usize
 }
 
 #[inline]
@@ -88,7 +206,7 @@ pub fn utf8(input: &Binary) -> IResult<&Binary, char> {
   let (rest, first_byte) = one_byte(input)?;
   match utf8_char_width(first_byte) {
     1 => {
-      Ok((&rest, first_byte as char))
+      Ok((rest, first_byte as char))
     }
     len@ 2..=4 => {
       if input.len() < len {
@@ -110,11 +228,41 @@ pub fn utf8(input: &Binary) -> IResult<&Binary, char> {
 #[cfg(test)]
 mod tests {
 
-  use super::token;
+  use super::{token, value_token};
   use super::utf8;
+  use super::{lws, sws, hcolon, semi, comma};
   use nom::Err::{Incomplete, Error};
   use nom::Needed;
-  use nom::error::ErrorKind::TakeWhile1;
+  use nom::error::ErrorKind::{TakeWhile1, Space, Char};
+
+  #[test]
+  fn lws_test() {
+    assert_eq!(lws(b" \t x"), Ok((&b"x"[..], &b" \t "[..])));
+    assert_eq!(lws(b" \r\n\tx"), Ok((&b"x"[..], &b" \r\n\t"[..])));
+    assert_eq!(lws(b"\r\n x"), Ok((&b"x"[..], &b"\r\n "[..])));
+    assert_eq!(lws(b" "), Ok((&b""[..], &b" "[..])));
+    assert_eq!(lws(b" \r\nx"), Err(Error((&b" \r\nx"[..], Space))));
+    assert_eq!(lws(b"\r\nx"), Err(Error((&b"\r\nx"[..], Space))));
+    assert_eq!(lws(b"x"), Err(Error((&b"x"[..], Space))));
+
+    assert_eq!(sws(b"x"), Ok((&b"x"[..], &b""[..])));
+    assert_eq!(sws(b" \r\nx"), Ok((&b" \r\nx"[..], &b""[..])));
+    assert_eq!(sws(b"\r\n\tx"), Ok((&b"x"[..], &b"\r\n\t"[..])));
+
+    assert_eq!(hcolon(b" \t: x"), Ok((&b"x"[..], &b" \t: "[..])));
+    assert_eq!(hcolon(b":\r\n x"), Ok((&b"x"[..], &b":\r\n "[..])));
+    assert_eq!(hcolon(b":x"), Ok((&b"x"[..], &b":"[..])));
+    assert!(hcolon(b" x").is_err());
+  }
+
+  #[test]
+  fn separator_test() {
+    assert_eq!(semi(b";x"), Ok((&b"x"[..], &b";"[..])));
+    assert_eq!(semi(b" ;\r\n x"), Ok((&b"x"[..], &b" ;\r\n "[..])));
+    assert_eq!(comma(b" , "), Ok((&b""[..], &b" , "[..])));
+    assert_eq!(comma(b" ;"), Err(Error((&b" ;"[..], Char))));
+    assert_eq!(comma(b" "), Err(Error((&b" "[..], Char))));
+  }
 
   #[test]
   fn token_parse_test() {
@@ -122,6 +270,10 @@ mod tests {
     assert_eq!(token("hello world".as_bytes()), Ok((" world".as_bytes(), "hello".as_bytes())));
     assert_eq!(token("😀".as_bytes()), Err(Error(("😀".as_bytes(), TakeWhile1))));
     assert_eq!(token("-!%~😅".as_bytes()), Ok(("😅".as_bytes(), "-!%~".as_bytes())));
+
+    assert_eq!(value_token(b"one123"), Ok((&b""[..], &b"one123"[..])));
+    assert_eq!(value_token(b"a=b"), Ok((&b"=b"[..], &b"a"[..])));
+    assert_eq!(value_token(b""), Err(Error((&b""[..], TakeWhile1))));
   }
 
   #[test]
@@ -8,7 +8,7 @@ use nom::error::ErrorKind::Digit;
 named!(#[inline],
   pub single<u8>,
   map!(
-    call!(map_byte, |c| c >= b'0' && c <= b'9'),
+    call!(map_byte, |c| c.is_ascii_digit()),
     |c| c - b'0'
   )
 );
@@ -78,13 +78,31 @@ pub fn h16(input: &Binary) -> IResult<&Binary, u16> {
   Ok((input, acc as u16))
 }
 
+// 1*DIGIT of a header value which has to fit into `max`. Leading zeros
+// are fine, a value above `max` is an error rather than being wrapped.
+#[inline]
+pub fn decimal(input: &Binary, max: u64) -> IResult<&Binary, u64> {
+  let len = input.iter().take_while(|i| i.is_ascii_digit()).count();
+  if len == 0 {
+    return Err(Error((input, Digit)));
+  }
+
+  let value = input[..len].iter()
+    .try_fold(0u64, |acc, d| acc.checked_mul(10)?.checked_add((d - b'0') as u64))
+    .filter(|v| *v <= max);
+  match value {
+    Some(v) => Ok((&input[len..], v)),
+    None => Err(Error((input, Digit)))
+  }
+}
+
 #[cfg(test)]
 mod tests {
 
-  use super::{single, h16, dec_u8};
+  use super::{single, h16, dec_u8, decimal};
   use nom::Err::{Incomplete, Error};
   use nom::Needed;
-  use nom::error::ErrorKind::Verify;
+  use nom::error::ErrorKind::{Verify, Digit};
 
   #[test]
   fn single_digit_test() {
@@ -112,6 +130,17 @@ mod tests {
     assert!(dec_u8("k".as_bytes()).is_err());
   }
 
+  #[test]
+  fn decimal_parse_test() {
+    assert_eq!(decimal(b"70", 255), Ok((&b""[..], 70)));
+    assert_eq!(decimal(b"0070 x", 255), Ok((&b" x"[..], 70)));
+    assert_eq!(decimal(b"18446744073709551615", u64::MAX), Ok((&b""[..], u64::MAX)));
+    assert_eq!(decimal(b"256", 255), Err(Error((&b"256"[..], Digit))));
+    assert_eq!(decimal(b"99999999999999999999999", u64::MAX), Err(Error((&b"99999999999999999999999"[..], Digit))));
+    assert_eq!(decimal(b"", 255), Err(Error((&b""[..], Digit))));
+    assert_eq!(decimal(b"-1", 255), Err(Error((&b"-1"[..], Digit))));
+  }
+
   #[test]
   fn h16_parse_test() {
     assert_eq!(h16("0".as_bytes()), Ok(("".as_bytes(), 0)));
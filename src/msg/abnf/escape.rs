@@ -0,0 +1,137 @@
+use super::super::{Binary, Byte};
+use super::userinfo::{is_user_char, is_password_char};
+use super::uri_params::is_param_char;
+use super::uri_headers::is_hnv_char;
+use std::borrow::Cow;
+
+// Parsers hand out components as they were written, i.e. with escapes,
+// and assemblers take them in the same form. Decoding gives the
+// application bytes, encoding turns application bytes into a component
+// which is valid in the given part of a SIP URI.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Context {
+  // user-unreserved / unreserved
+  User,
+  // "&" / "=" / "+" / "$" / "," / unreserved
+  Password,
+  // param-unreserved / unreserved, both for pname and pvalue
+  Param,
+  // hnv-unreserved / unreserved, both for hname and hvalue
+  Header
+}
+
+impl Context {
+  // bytes which are written as is, everything else is escaped
+  #[inline]
+  pub fn allows(self, b: Byte) -> bool {
+    match self {
+      Context::User => is_user_char(b),
+      Context::Password => is_password_char(b),
+      Context::Param => is_param_char(b),
+      Context::Header => is_hnv_char(b)
+    }
+  }
+}
+
+pub(crate) static HEX: &Binary = b"0123456789ABCDEF";
+
+#[inline]
+fn hex(b: Byte) -> Option<Byte> {
+  (b as char).to_digit(16).map(|d| d as Byte)
+}
+
+// Value of the "%HH" triplet starting at `i` if there is one
+#[inline]
+pub(crate) fn escape_at(data: &Binary, i: usize) -> Option<Byte> {
+  match data.get(i..i + 3) {
+    Some([b'%', h, l]) => hex(*h).and_then(|h| hex(*l).map(|l| h * 16 + l)),
+    _ => None
+  }
+}
+
+// Replaces every "%HH" with the byte it stands for. Input without
+// escapes is returned borrowed. A '%' which does not start a well-formed
+// escape is kept as is, so decoding never fails.
+pub fn decode(input: &Binary) -> Cow<'_, Binary> {
+  let first = match (0..input.len()).find(|i| escape_at(input, *i).is_some()) {
+    Some(first) => first,
+    None => return Cow::Borrowed(input)
+  };
+
+  let mut out = Vec::with_capacity(input.len());
+  out.extend_from_slice(&input[..first]);
+  let mut i = first;
+  while i < input.len() {
+    match escape_at(input, i) {
+      Some(d) => {
+        out.push(d);
+        i += 3;
+      },
+      None => {
+        out.push(input[i]);
+        i += 1;
+      }
+    }
+  }
+  Cow::Owned(out)
+}
+
+// Escapes every byte the context does not allow, '%' included, so the
+// result decodes back to `input`. Input which needs no escaping is
+// returned borrowed.
+pub fn encode(input: &Binary, context: Context) -> Cow<'_, Binary> {
+  if input.iter().all(|b| context.allows(*b)) {
+    return Cow::Borrowed(input);
+  }
+
+  let mut out = Vec::with_capacity(input.len() + 8);
+  for &b in input {
+    if context.allows(b) {
+      out.push(b);
+    } else {
+      out.extend_from_slice(&[b'%', HEX[(b >> 4) as usize], HEX[(b & 0xf) as usize]]);
+    }
+  }
+  Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{decode, encode, escape_at, Context};
+  use super::super::userinfo::user;
+  use super::super::uri_params::pvalue;
+  use std::borrow::Cow;
+
+  #[test]
+  fn decode_test() {
+    assert_eq!(escape_at(b"a%4", 1), None);
+    assert_eq!(escape_at(b"a%4f", 1), Some(0x4f));
+
+    assert!(matches!(decode(b"alice"), Cow::Borrowed(b"alice")));
+    assert!(matches!(decode(b"100%"), Cow::Borrowed(b"100%")));
+    assert_eq!(decode(b"%61lice").as_ref(), b"alice");
+    assert_eq!(decode(b"a%20b%3bc%3B").as_ref(), b"a b;c;");
+    assert_eq!(decode(b"%%41%4g%").as_ref(), b"%A%4g%");
+  }
+
+  #[test]
+  fn encode_test() {
+    assert!(matches!(encode(b"alice;x=1", Context::User), Cow::Borrowed(_)));
+    assert_eq!(encode(b"al ice@x%", Context::User).as_ref(), b"al%20ice%40x%25".as_ref());
+    assert_eq!(encode(b"pa:ss;", Context::Password).as_ref(), b"pa%3Ass%3B".as_ref());
+    assert_eq!(encode(b"a=b;c", Context::Param).as_ref(), b"a%3Db%3Bc".as_ref());
+    assert_eq!(encode(b"sip:bob@biloxi.com&x", Context::Header).as_ref(), b"sip:bob%40biloxi.com%26x".as_ref());
+  }
+
+  #[test]
+  fn round_trip_test() {
+    let raw: &[u8] = b"\x00 %;=?&@:/[]\xff~";
+    let encoded = encode(raw, Context::User);
+    assert_eq!(user(&encoded), Ok((&b""[..], encoded.as_ref())));
+    assert_eq!(decode(&encoded).as_ref(), raw);
+
+    let encoded = encode(raw, Context::Param);
+    assert_eq!(pvalue(&encoded), Ok((&b""[..], encoded.as_ref())));
+    assert_eq!(decode(&encoded).as_ref(), raw);
+  }
+}
@@ -2,6 +2,7 @@
 use super::{
   super::{Byte, Binary},
   map_byte,
+  whole,
   ch::{alpha, alphanum},
   digit::single,
   ip::{ipv4address, ipv6reference}
@@ -10,14 +11,12 @@ use super::{
 use nom::{IResult, error::ErrorKind::{
   Verify,
   Digit
-}, Err::Error, Needed};
+}, Err::Error};
 
 use std::net::{Ipv4Addr, Ipv6Addr};
-use nom::Err::Incomplete;
 
 named!(#[inline], take1, take!(1));
 
-
 #[inline]
 fn byte_or_byte_terminated_alphanum_with_dash<F, O>(f: F, input: &Binary) -> IResult<&Binary, &Binary>
   where F: Fn(&Binary) -> IResult<&Binary, O> {
@@ -62,88 +61,106 @@ named!(#[inline],
   call!(map_byte, |i| i == b'.')
 );
 
-// hostname         =  *( domainlabel "." ) toplabel [ "." ]
-#[inline]
-pub fn hostname<'a, 'b>(input: &'a Binary, domains: &'b mut [&'a Binary])
-  -> IResult<&'a Binary, (&'a Binary, usize)> {
-  let mut rest = input;
-  let mut last_top_rest: Option<&Binary> = None;
+// Hostname as it appears in the input, including the trailing dot if any
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Hostname<'a>(&'a Binary);
 
-  let mut last_top = input;
-  let mut num_domains = 0usize;
-  let mut commit_num_domains = num_domains;
+impl<'a> Hostname<'a> {
+  // Hostname out of application data, the whole input has to be a hostname
+  pub fn new(input: &'a Binary) -> Option<Hostname<'a>> {
+    whole(hostname(input))
+  }
 
-  while let Ok((_, _)) = peek!(rest, tuple!(domainlabel, dot)) {
-    if let Ok((r, o)) = toplabel(rest) {
-      if num_domains >= domains.len() {
-        return Err(Incomplete(Needed::Unknown));
-      }
-      domains[num_domains] = o;
-      num_domains += 1;
-      let (r, _) = dot(r)?;
-      rest = r;
-      commit_num_domains = num_domains;
-      last_top_rest = Some(rest);
-      last_top = o;
-    } else {
-      let (r, o) = domainlabel(rest)?;
-      let (r, _) = dot(r)?;
-      rest = r;
-      if num_domains >= domains.len() {
-        return Err(Incomplete(Needed::Unknown));
-      }
+  pub fn as_bytes(&self) -> &'a Binary {
+    self.0
+  }
+
+  // domain labels followed by the top label
+  pub fn labels(&self) -> Labels<'a> {
+    Labels { rest: self.0 }
+  }
 
-      domains[num_domains] = o;
-      num_domains += 1;
+  pub fn top_label(&self) -> &'a Binary {
+    self.labels().last().unwrap_or(self.0)
+  }
+}
+
+pub struct Labels<'a> {
+  rest: &'a Binary
+}
+
+impl<'a> Iterator for Labels<'a> {
+  type Item = &'a Binary;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.rest.is_empty() {
+      return None;
     }
+
+    let (label, rest) = match self.rest.iter().position(|b| *b == b'.') {
+      Some(dot) => (&self.rest[..dot], &self.rest[dot + 1..]),
+      None => (self.rest, &self.rest[self.rest.len()..])
+    };
+    self.rest = rest;
+    Some(label)
   }
+}
 
-  if let Ok((r, o)) = toplabel(rest) {
-    rest = r;
-    last_top = o;
-    commit_num_domains = num_domains;
-  } else {
-    match last_top_rest {
-      None => {
-        return Err(Error((input, Verify)));
+// hostname         =  *( domainlabel "." ) toplabel [ "." ]
+// The longest prefix which ends with a toplabel (and its dot) is taken,
+// e.g. "a.1" is the hostname "a." followed by "1".
+#[inline]
+pub fn hostname(input: &Binary) -> IResult<&Binary, Hostname<'_>> {
+  let mut rest = input;
+  let mut end: Option<&Binary> = None;
+
+  while let Ok((r, label)) = domainlabel(rest) {
+    let top = label[0].is_ascii_alphabetic();
+    if top {
+      end = Some(r);
+    }
+
+    match dot(r) {
+      Ok((r, _)) => {
+        if top {
+          end = Some(r);
+        }
+        rest = r;
       },
-      Some(r) => {
-        return Ok((r, (last_top, commit_num_domains - 1)));
-      }
+      Err(_) => break
     }
   }
 
-  if let Ok((r, _)) = dot(input) {
-    rest = r;
+  match end {
+    Some(r) => Ok((r, Hostname(&input[..input.len() - r.len()]))),
+    None => Err(Error((input, Verify)))
   }
-
-  Ok((rest, (last_top, commit_num_domains)))
 }
 
+// Hostname keeps the whole name, IP addresses are parsed
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Host<'a> {
-  Hostname(&'a Binary, usize),
+  Hostname(Hostname<'a>),
   Ipv4(Ipv4Addr),
   Ipv6(Ipv6Addr)
 }
 
 // host             =  hostname / IPv4address / IPv6reference
 #[inline]
-pub fn host<'a, 'b>(input: &'a Binary, domains: &'b mut [&'a Binary])
-  -> IResult<&'a Binary, Host<'a>> {
-  alt!(input,
-    call!(hostname, domains) => {
-      |(top, num)| Host::Hostname(top, num)
-    }
-    |
-    ipv4address => {
-      |v4| Host::Ipv4(v4)
-    }
-    |
-    ipv6reference => {
-      |v6| Host::Ipv6(v6)
-    }
-  )
+pub fn host(input: &Binary) -> IResult<&Binary, Host<'_>> {
+  match hostname(input) {
+    Ok((rest, name)) => Ok((rest, Host::Hostname(name))),
+    Err(Error(_)) => alt!(input,
+      ipv4address => {
+        Host::Ipv4
+      }
+      |
+      ipv6reference => {
+        Host::Ipv6
+      }
+    ),
+    Err(e) => Err(e)
+  }
 }
 
 // port             =  1*DIGIT
@@ -161,7 +178,7 @@ pub fn port(input: &Binary) -> IResult<&Binary, u16> {
     acc *= 10;
     acc += next as u64;
 
-    if acc > std::u16::MAX as u64 {
+    if acc > u16::MAX as u64 {
       return Err(Error((rest, Digit)));
     }
 
@@ -178,9 +195,8 @@ named!(#[inline],
 
 // hostport         =  host [ ":" port ]
 #[inline]
-pub fn hostport<'a, 'b>(input: &'a Binary, domains: &'b mut [&'a Binary])
-  -> IResult<&'a Binary, (Host<'a>, Option<u16>)> {
-  let (mut rest, h) = host(input, domains)?;
+pub fn hostport(input: &Binary) -> IResult<&Binary, (Host<'_>, Option<u16>)> {
+  let (mut rest, h) = host(input)?;
 
   let p = if let Ok((r, _)) = colon(rest) {
     let (r, p) = port(r)?;
@@ -193,12 +209,106 @@ pub fn hostport<'a, 'b>(input: &'a Binary, domains: &'b mut [&'a Binary])
   Ok((rest, (h, p)))
 }
 
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::abnf::host::Host;
+  use crate::msg::output::{Output, AssembleResult};
+
+  pub fn host(h: Host, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    match h {
+      Host::Hostname(name) => out.put(name.as_bytes())?,
+      Host::Ipv4(addr) => out.put_display(addr)?,
+      Host::Ipv6(addr) => {
+        out.put_byte(b'[')?;
+        out.put_display(addr)?;
+        out.put_byte(b']')?;
+      }
+    }
+    out.finish()
+  }
+
+  pub fn hostport(h: Host, port: Option<u16>, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.with(|b| host(h, b))?;
+    if let Some(p) = port {
+      out.put_byte(b':')?;
+      out.put_number(p as u64)?;
+    }
+    out.finish()
+  }
+}
+
+pub mod owned {
+  use std::net::{Ipv4Addr, Ipv6Addr};
+
+  use crate::msg::Binary;
+
+  #[derive(Clone, Debug, Eq, PartialEq, Hash)]
+  pub struct Hostname(Vec<u8>);
+
+  impl Hostname {
+    pub fn as_bytes(&self) -> &Binary {
+      &self.0
+    }
+
+    pub fn as_borrowed(&self) -> super::Hostname<'_> {
+      super::Hostname(&self.0)
+    }
+  }
+
+  impl<'a> super::Hostname<'a> {
+    pub fn into_owned(self) -> Hostname {
+      Hostname(self.0.to_vec())
+    }
+  }
+
+  #[derive(Clone, Debug, Eq, PartialEq, Hash)]
+  pub enum Host {
+    Hostname(Hostname),
+    Ipv4(Ipv4Addr),
+    Ipv6(Ipv6Addr)
+  }
+
+  impl Host {
+    pub fn as_borrowed(&self) -> super::Host<'_> {
+      match self {
+        Host::Hostname(h) => super::Host::Hostname(h.as_borrowed()),
+        Host::Ipv4(ip) => super::Host::Ipv4(*ip),
+        Host::Ipv6(ip) => super::Host::Ipv6(*ip)
+      }
+    }
+  }
+
+  impl<'a> super::Host<'a> {
+    pub fn into_owned(self) -> Host {
+      match self {
+        super::Host::Hostname(h) => Host::Hostname(h.into_owned()),
+        super::Host::Ipv4(ip) => Host::Ipv4(ip),
+        super::Host::Ipv6(ip) => Host::Ipv6(ip)
+      }
+    }
+  }
+
+  impl<'a> From<super::Host<'a>> for Host {
+    fn from(h: super::Host<'a>) -> Host {
+      h.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a Host> for super::Host<'a> {
+    fn from(h: &'a Host) -> super::Host<'a> {
+      h.as_borrowed()
+    }
+  }
+}
+
 #[cfg(test)]
 mod tests {
-  use super::{domainlabel, toplabel, hostname, host, Host, port, hostport};
+  use super::{domainlabel, toplabel, hostname, Hostname, host, Host, port, hostport, assemble};
+  use crate::msg::output::AssembleError;
   use nom::Err as Error;
   use nom::error::ErrorKind::{Verify, Digit, Complete};
-  use nom::Needed;
   use std::net::{Ipv6Addr, Ipv4Addr};
   use std::str::FromStr;
   macro_rules! parser_ok {
@@ -257,52 +367,56 @@ mod tests {
   }
 
   macro_rules! hostname_test {
-    ( $input:literal makes $top:literal top, $left:expr) => {
-      let mut buf = ["".as_bytes(); 10];
-      assert_eq!( hostname($input.as_bytes(), &mut buf), Ok(($left.as_bytes(), ($top.as_bytes(), 0))));
-    };
-    ( $input:literal makes $top:literal top and domains: $($domain:literal),+  : left $left:literal) => {
-      let mut buf = ["".as_bytes(); 100];
-      let domains = [$($domain),+];
-      assert_eq!(hostname($input.as_bytes(), &mut buf), Ok(($left.as_bytes(), ($top.as_bytes(), domains.len()))));
-      Iterator::zip(buf.into_iter(), domains.into_iter()).for_each(|(b, d)|{
-        assert_eq!(*b, d.as_bytes());
-      });
+    ( $input:literal makes $name:literal with labels: $($label:literal),+ : left $left:literal) => {
+      let (rest, name) = hostname($input.as_bytes()).unwrap();
+      assert_eq!(rest, $left.as_bytes());
+      assert_eq!(name.as_bytes(), $name.as_bytes());
+      assert_eq!(name.labels().collect::<Vec<_>>(), vec![$($label.as_bytes()),+]);
     };
     ( $input:literal fails $error:expr, $left:expr ) => {
-      let mut buf = ["".as_bytes(); 10];
-      assert_eq!( hostname($input.as_bytes(), &mut buf), Err(Error::Error(($left.as_bytes(), $error))));
+      assert_eq!( hostname($input.as_bytes()), Err(Error::Error(($left.as_bytes(), $error))));
     };
   }
 
   #[test]
   fn hostname_test() {
-    hostname_test!("a" makes "a" top, "");
-    hostname_test!("a." makes "a" top, "");
-    hostname_test!("a.a." makes "a" top and domains: "a": left "");
-    hostname_test!("a.a" makes "a" top and domains: "a": left "");
-    hostname_test!("1.a" makes "a" top and domains: "1": left "");
-    hostname_test!("1.a." makes "a" top and domains: "1": left "");
-    hostname_test!("a.1" makes "a" top, "1");
-    hostname_test!("a.1." makes "a" top, "1.");
-    hostname_test!("3.a.4.b-d.1." makes "b-d" top and domains: "3", "a", "4" : left "1.");
-    assert_eq!(hostname("3.a.4.b-d.1.".as_bytes(), &mut []), Err(Error::Incomplete(Needed::Unknown)));
+    hostname_test!("a" makes "a" with labels: "a" : left "");
+    hostname_test!("a." makes "a." with labels: "a" : left "");
+    hostname_test!("a.a." makes "a.a." with labels: "a", "a" : left "");
+    hostname_test!("a.a" makes "a.a" with labels: "a", "a" : left "");
+    hostname_test!("1.a" makes "1.a" with labels: "1", "a" : left "");
+    hostname_test!("1.a." makes "1.a." with labels: "1", "a" : left "");
+    hostname_test!("a.1" makes "a." with labels: "a" : left "1");
+    hostname_test!("a.1." makes "a." with labels: "a" : left "1.");
+    hostname_test!("3.a.4.b-d.1." makes "3.a.4.b-d." with labels: "3", "a", "4", "b-d" : left "1.");
     hostname_test!("4.4." fails Verify, "4.4.");
+    hostname_test!("" fails Verify, "");
+
+    hostname_test!("3450.ns-45.goo.gov a;lkds" makes "3450.ns-45.goo.gov" with labels: "3450", "ns-45", "goo", "gov" : left " a;lkds");
 
-    hostname_test!("3450.ns-45.goo.gov a;lkds" makes "gov" top and domains: "3450", "ns-45", "goo" : left " a;lkds");
+    // there is no limit on the number of labels
+    let long = "a.".repeat(1000);
+    let (rest, name) = hostname(long.as_bytes()).unwrap();
+    assert_eq!(rest, b"");
+    assert_eq!(name.labels().count(), 1000);
+    assert_eq!(name.top_label(), b"a");
+
+    assert_eq!(Hostname::new(b"atlanta.com").map(|h| h.top_label()), Some(&b"com"[..]));
+    assert_eq!(Hostname::new(b"atlanta.com:5060"), None);
+    assert_eq!(Hostname::new(b"192.0.2.4"), None);
   }
 
   macro_rules! host_test {
     ( $input:literal makes $expected:expr, $left:expr) => {
-      let mut buf = ["".as_bytes(); 10];
-      assert_eq!( host($input.as_bytes(), &mut buf), Ok(($left.as_bytes(), $expected)));
+      assert_eq!( host($input.as_bytes()), Ok(($left.as_bytes(), $expected)));
     };
   }
 
   #[test]
   fn host_test() {
     host_test!("127.0.0.1" makes Host::Ipv4(Ipv4Addr::from_str("127.0.0.1").unwrap()), "");
-    host_test!("ringcentral.com" makes Host::Hostname("com".as_bytes(), 1), "");
+    host_test!("ringcentral.com" makes Host::Hostname(Hostname::new(b"ringcentral.com").unwrap()), "");
+    host_test!("a.b.c.:5060" makes Host::Hostname(Hostname::new(b"a.b.c.").unwrap()), ":5060");
     host_test!("[fa:2001:db8::9:01]" makes Host::Ipv6(Ipv6Addr::from_str("fa:2001:db8::9:01").unwrap()), "");
   }
 
@@ -329,45 +443,39 @@ mod tests {
   }
 
   macro_rules! hostport_test {
-    ( $input:literal makes $host:literal,$domains:literal, $left:literal) => {
-      let mut buf = ["".as_bytes(); 10];
+    ( $input:literal makes $host:literal, $left:literal) => {
       assert_eq!(
-        hostport($input.as_bytes(), &mut buf),
-        Ok(($left.as_bytes(), (Host::Hostname($host.as_bytes(), $domains), None)))
+        hostport($input.as_bytes()),
+        Ok(($left.as_bytes(), (Host::Hostname(Hostname::new($host.as_bytes()).unwrap()), None)))
       );
     };
-    ( $input:literal makes $host:literal,$domains:literal : $port:literal, $left:expr) => {
-      let mut buf = ["".as_bytes(); 10];
+    ( $input:literal makes $host:literal : $port:literal, $left:expr) => {
       assert_eq!(
-        hostport($input.as_bytes(), &mut buf),
-        Ok(($left.as_bytes(), (Host::Hostname($host.as_bytes(), $domains), Some($port))))
+        hostport($input.as_bytes()),
+        Ok(($left.as_bytes(), (Host::Hostname(Hostname::new($host.as_bytes()).unwrap()), Some($port))))
       );
     };
     ( $input:literal makes v4 $host:literal, $left:literal) => {
-      let mut buf = ["".as_bytes(); 10];
       assert_eq!(
-        hostport($input.as_bytes(), &mut buf),
+        hostport($input.as_bytes()),
         Ok(($left.as_bytes(), (Host::Ipv4(Ipv4Addr::from_str($host).unwrap()), None)))
       );
     };
     ( $input:literal makes v4 $host:literal : $port:literal, $left:expr) => {
-      let mut buf = ["".as_bytes(); 10];
       assert_eq!(
-        hostport($input.as_bytes(), &mut buf),
+        hostport($input.as_bytes()),
         Ok(($left.as_bytes(), (Host::Ipv4(Ipv4Addr::from_str($host).unwrap()), Some($port))))
       );
     };
     ( $input:literal makes v6 $host:literal, $left:literal) => {
-      let mut buf = ["".as_bytes(); 10];
       assert_eq!(
-        hostport($input.as_bytes(), &mut buf),
+        hostport($input.as_bytes()),
         Ok(($left.as_bytes(), (Host::Ipv6(Ipv6Addr::from_str($host).unwrap()), None)))
       );
     };
     ( $input:literal makes v6 $host:literal : $port:literal, $left:expr) => {
-      let mut buf = ["".as_bytes(); 10];
       assert_eq!(
-        hostport($input.as_bytes(), &mut buf),
+        hostport($input.as_bytes()),
         Ok(($left.as_bytes(), (Host::Ipv6(Ipv6Addr::from_str($host).unwrap()), Some($port))))
       );
     };
@@ -379,9 +487,26 @@ mod tests {
     hostport_test!("127.0.0.1:2222" makes v4"127.0.0.1":2222, "");
     hostport_test!("[2001:db8::192.0.2.1]" makes v6"2001:db8::192.0.2.1", "");
     hostport_test!("[2001:db8::10]:5070 " makes v6"2001:db8::10":5070, " ");
-    hostport_test!("www.yandex.ru" makes "ru",2, "");
-    hostport_test!("yandex.ru:22" makes "ru",1:22, "");
+    hostport_test!("www.yandex.ru" makes "www.yandex.ru", "");
+    hostport_test!("yandex.ru:22" makes "yandex.ru":22, "");
   }
 
+  #[test]
+  fn hostport_assemble_test() {
+    let mut buf = [0u8; 64];
+
+    let n = assemble::hostport(Host::Hostname(Hostname::new(b"atlanta.com").unwrap()), Some(5060), &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"atlanta.com:5060");
+
+    let n = assemble::hostport(Host::Ipv4(Ipv4Addr::new(192, 0, 2, 4)), None, &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"192.0.2.4");
+
+    let n = assemble::hostport(Host::Ipv6(Ipv6Addr::from_str("2001:db8::10").unwrap()), Some(5070), &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"[2001:db8::10]:5070");
 
-}
\ No newline at end of file
+    assert_eq!(
+      assemble::hostport(Host::Hostname(Hostname::new(b"atlanta.com").unwrap()), Some(5060), &mut buf[..13]),
+      Err(AssembleError::NotEnoughOutSpace { written: 12 })
+    );
+  }
+}
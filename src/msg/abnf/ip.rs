@@ -44,7 +44,7 @@ named!(#[inline], pub ls32<Ls32>, alt!(
   }
   |
   ipv4address => {
-    |a| Ls32::V4(a)
+    Ls32::V4
   }
 ));
 
@@ -56,7 +56,7 @@ named!(#[inline], pub colon_and_ls32<Ls32>, preceded!(char!(':'), ls32));
 // IPv6address      =  hexpart [ ":" IPv4address ]
 
 // Following ABNF is taken from [RFC 5954](Section 4.1 - Resolution for Extra Colon in IPv4-Mapped IPv6 Address)
-named!(#[inline], pub ipv6address<Ipv6Addr>, alt!(
+named!(#[inline] #[allow(clippy::type_complexity)], pub ipv6address<Ipv6Addr>, alt!(
   // IPv6address   =                             6( h16 ":" ) ls32
   tuple!(h16, colon_and_h16, colon_and_h16, colon_and_h16, colon_and_h16, colon_and_h16, colon_and_ls32) => {
     |(h1, h2, h3, h4, h5, h6, ls): (u16, u16, u16, u16, u16, u16, Ls32)| {
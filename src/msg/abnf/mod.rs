@@ -15,6 +15,25 @@ pub(crate) fn map_byte<F>(input: &Binary, f: F) -> IResult<&Binary, Byte>
   )
 }
 
+// Result of a parser which was given an exactly delimited input,
+// anything not consumed by the parser makes it a mismatch
+#[inline]
+pub(crate) fn whole<O>(res: IResult<&Binary, O>) -> Option<O> {
+  match res {
+    Ok((&[], o)) => Some(o),
+    _ => None
+  }
+}
+
+// The caller's slice which a parser stores elements into is full and
+// `written` of them made it in. Nothing is wrong with the input, so this
+// is neither a parse error nor a request for more input: the caller needs
+// a bigger slice or the lazy list type instead.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct NotEnoughOutSpace {
+  pub written: usize
+}
+
 named!(#[inline],
   pub(crate) one_byte<Byte>,
   map!(take!(1), |i| unsafe { *i.get_unchecked(0) })
@@ -35,75 +54,29 @@ macro_rules! one_of_byte(
   )
 );
 
-// TODO: how to match bytes instead of str
-//       is it possible?
-//macro_rules! bytes (
-//  ($input:expr, $expected:expr) => ({
-//    let mut rest = $input;
-//    println!("trying to find: {}", std::str::from_utf8($expected).unwrap());
-//
-//    // TODO: why just iteration does not work???
-////    let ret = tag!($input, std::str::from_utf8($expected).unwrap());
-////    println!("ret is {:?}", ret);
-////    ret
-//    for b in $expected {
-//      match byte!(rest, *b) {
-//        Err(a) => {
-//          println!("error is {:?}", a);
-//          return Err(a);
-//        },
-//        Ok((r, _)) => {
-//          println!("matched with {}", *b);
-//          rest = r;
-//        }
-//      }
-////      let (r, _) = byte!(rest, *b)?;
-////      rest = r;
-//    }
-//    Ok((rest, ()))
-//  });
-//);
-
-pub(crate) trait Consumed {
-  fn consumed(&self) -> usize;
-}
-
-impl Consumed for &Binary {
-  fn consumed(&self) -> usize {
-    self.len()
-  }
-}
-
-impl Consumed for u8 {
-  fn consumed(&self) -> usize {
-    1
-  }
-}
-
+// Both repetition macros measure the recognized slice by what is left
+// of the input, so elements of different widths (e.g. a single byte and
+// an escaped "%HH" triplet) can be mixed freely.
 macro_rules! at_least_one (
   ($input:expr, $submac:ident!( $($args:tt)* )) => ({
 
-    let (mut rest, c) = $submac!($input, $($args)*)?;
-    let mut len = $crate::msg::abnf::Consumed::consumed(&c);
+    let (mut rest, _) = $submac!($input, $($args)*)?;
 
-    while let Ok((r, c)) =  $submac!(rest, $($args)*) {
+    while let Ok((r, _)) =  $submac!(rest, $($args)*) {
       rest = r;
-      len += $crate::msg::abnf::Consumed::consumed(&c);
     }
 
-    Ok((rest, &$input[..len]))
+    Ok((rest, &$input[..$input.len() - rest.len()]))
 
   });
   ($input:expr, $fn:expr) => ({
-    let (mut rest, c) = $fn($input)?;
-    let mut len = $crate::msg::abnf::Consumed::consumed(&c);
+    let (mut rest, _) = $fn($input)?;
 
-    while let Ok((r, c)) = $fn(rest) {
+    while let Ok((r, _)) = $fn(rest) {
       rest = r;
-      len += $crate::msg::abnf::Consumed::consumed(&c);
     }
 
-    Ok((rest, &$input[..len]))
+    Ok((rest, &$input[..$input.len() - rest.len()]))
   });
 );
 
@@ -111,26 +84,22 @@ macro_rules! many_times (
   ($input:expr, $submac:ident!( $($args:tt)* )) => ({
 
     let mut rest: &Binary = $input;
-    let mut len = 0usize;
 
-    while let Ok((r, c)) =  $submac!(rest, $($args)*) {
+    while let Ok((r, _)) =  $submac!(rest, $($args)*) {
       rest = r;
-      len += $crate::msg::abnf::Consumed::consumed(&c);
     }
 
-    let res: &Binary = &$input[..len];
+    let res: &Binary = &$input[..$input.len() - rest.len()];
     Ok((rest, res))
   });
   ($input:expr, $fn:expr) => ({
     let mut rest: &Binary = $input;
-    let mut len = 0usize;
 
-    while let Ok((r, c)) = $fn(rest) {
+    while let Ok((r, _)) = $fn(rest) {
       rest = r;
-      len += $crate::msg::abnf::Consumed::consumed(&c);
     }
 
-    let res: &Binary = &$input[..len];
+    let res: &Binary = &$input[..$input.len() - rest.len()];
     Ok((rest, res))
   });
 );
@@ -143,3 +112,4 @@ pub mod userinfo;
 pub mod uri_headers;
 pub mod uri_params;
 pub mod absolute_uri;
+pub mod escape;
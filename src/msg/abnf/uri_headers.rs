@@ -1,5 +1,7 @@
-use super::ch::{unreserved, escaped};
-use crate::msg::Binary;
+use super::ch::{unreserved, escaped, is_unreserved};
+use crate::msg::{Binary, Byte};
+use super::NotEnoughOutSpace;
+use nom::IResult;
 
 // hnv-unreserved  =  "[" / "]" / "/" / "?" / ":" / "+" / "$"
 named!(#[inline],
@@ -7,32 +9,22 @@ named!(#[inline],
   one_of_byte!(b'[' | b']' | b'/' | b'?' | b':' | b'+' | b'$')
 );
 
-struct Digits(usize);
-
-impl super::Consumed for Digits {
-  fn consumed(&self) -> usize {
-    self.0
-  }
+// hname and hvalue characters which don't need escaping
+#[inline]
+pub fn is_hnv_char(b: Byte) -> bool {
+  is_unreserved(b) || matches!(b, b'[' | b']' | b'/' | b'?' | b':' | b'+' | b'$')
 }
 
 // hname           =  1*( hnv-unreserved / unreserved / escaped )
 named!(#[inline],
   hname,
-  at_least_one!(alt!(
-    hnv_unreserved => { |_| Digits(1) } |
-    unreserved => { |_| Digits(1) } |
-    escaped => { |_| Digits(3) }
-  ))
+  at_least_one!(alt!(hnv_unreserved | unreserved | escaped))
 );
 
 // hvalue          =  *( hnv-unreserved / unreserved / escaped )
 named!(#[inline],
   hvalue,
-  many_times!(alt!(
-    hnv_unreserved => { |_| Digits(1) } |
-    unreserved => { |_| Digits(1) } |
-    escaped => { |_| Digits(3) }
-  ))
+  many_times!(alt!(hnv_unreserved | unreserved | escaped))
 );
 
 // header          =  hname "=" hvalue
@@ -41,17 +33,10 @@ named!(#[inline],
   tuple!(hname, preceded!(byte!(b'='), hvalue))
 );
 
-#[derive(Debug, Eq, PartialEq)]
-pub enum ParseError {
-  NotEnoughOutSpace {
-    written: usize
-  }
-}
-
 // headers         =  "?" header *( "&" header )
 #[inline]
-pub fn headers<'a, 'b>(input: &'a Binary, out: &'b mut [&'a Binary])
-  -> Result<(&'a Binary, usize), ParseError> {
+pub fn headers<'a>(input: &'a Binary, out: &mut [&'a Binary])
+  -> Result<(&'a Binary, usize), NotEnoughOutSpace> {
 
   let mut i = 0usize;
   let mut rest = input;
@@ -59,7 +44,7 @@ pub fn headers<'a, 'b>(input: &'a Binary, out: &'b mut [&'a Binary])
     rest = r;
     if let Ok((r, (hname, hvalue))) = header(rest) {
       if i + 1 >= out.len() {
-        return Err(ParseError::NotEnoughOutSpace { written: i });
+        return Err(NotEnoughOutSpace { written: i });
       }
 
       rest = r;
@@ -69,7 +54,7 @@ pub fn headers<'a, 'b>(input: &'a Binary, out: &'b mut [&'a Binary])
 
       while let Ok((r, (hname, hvalue))) = preceded!(rest, byte!(b'&'), header) {
         if i + 1 >= out.len() {
-          return Err(ParseError::NotEnoughOutSpace { written: i });
+          return Err(NotEnoughOutSpace { written: i });
         }
 
         rest = r;
@@ -82,12 +67,140 @@ pub fn headers<'a, 'b>(input: &'a Binary, out: &'b mut [&'a Binary])
   Ok((rest, i))
 }
 
+// headers which were already validated by the parser, kept as the
+// original slice without the leading "?"
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default)]
+pub struct UriHeaders<'a>(&'a Binary);
+
+impl<'a> UriHeaders<'a> {
+  pub fn as_bytes(&self) -> &'a Binary {
+    self.0
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  pub fn iter(&self) -> UriHeadersIter<'a> {
+    UriHeadersIter { rest: self.0 }
+  }
+}
+
+impl<'a> IntoIterator for UriHeaders<'a> {
+  type Item = (&'a Binary, &'a Binary);
+  type IntoIter = UriHeadersIter<'a>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+pub struct UriHeadersIter<'a> {
+  rest: &'a Binary
+}
+
+impl<'a> Iterator for UriHeadersIter<'a> {
+  type Item = (&'a Binary, &'a Binary);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let (r, h) = header(self.rest).ok()?;
+    self.rest = match byte!(r, b'&') {
+      Ok((r, _)) => r,
+      Err(_) => r
+    };
+    Some(h)
+  }
+}
+
+// headers         =  "?" header *( "&" header )
+#[inline]
+pub fn lazy_headers(input: &Binary) -> IResult<&Binary, UriHeaders<'_>> {
+  let (headers, _) = byte!(input, b'?')?;
+  let (mut rest, _) = header(headers)?;
+
+  while let Ok((r, _)) = preceded!(rest, byte!(b'&'), header) {
+    rest = r;
+  }
+
+  Ok((rest, UriHeaders(&headers[..headers.len() - rest.len()])))
+}
+
 
 
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::abnf::uri_headers::is_hnv_char;
+  use crate::msg::output::{Output, AssembleResult};
+
+  // header          =  hname "=" hvalue
+  pub fn header(name: &Binary, value: &Binary, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.put_component(name, is_hnv_char)?;
+    out.put_byte(b'=')?;
+    out.put_component(value, is_hnv_char)?;
+    out.finish()
+  }
+
+  // headers         =  "?" header *( "&" header )
+  // (nothing is written for no headers)
+  pub fn headers<'a, I>(hdrs: I, buf: &mut Binary) -> AssembleResult
+    where I: IntoIterator<Item = (&'a Binary, &'a Binary)> {
+    let mut out = Output::new(buf);
+    let mut delimiter = b'?';
+    for (name, value) in hdrs {
+      out.put_byte(delimiter)?;
+      out.with(|b| header(name, value, b))?;
+      delimiter = b'&';
+    }
+    out.finish()
+  }
+}
+
+pub mod owned {
+  use crate::msg::Binary;
+
+  #[derive(Clone, Debug, Eq, PartialEq, Hash, Default)]
+  pub struct UriHeaders(Vec<u8>);
+
+  impl UriHeaders {
+    pub fn as_bytes(&self) -> &Binary {
+      &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+      self.0.is_empty()
+    }
+
+    pub fn as_borrowed(&self) -> super::UriHeaders<'_> {
+      super::UriHeaders(&self.0)
+    }
+  }
+
+  impl<'a> super::UriHeaders<'a> {
+    pub fn into_owned(self) -> UriHeaders {
+      UriHeaders(self.0.to_vec())
+    }
+  }
+
+  impl<'a> From<super::UriHeaders<'a>> for UriHeaders {
+    fn from(h: super::UriHeaders<'a>) -> UriHeaders {
+      h.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a UriHeaders> for super::UriHeaders<'a> {
+    fn from(h: &'a UriHeaders) -> super::UriHeaders<'a> {
+      h.as_borrowed()
+    }
+  }
+}
+
 #[cfg(test)]
 mod tests {
-  use super::headers;
-  use crate::msg::abnf::uri_headers::ParseError;
+  use super::{headers, lazy_headers, assemble};
+  use crate::msg::abnf::escape::{encode, Context};
+  use crate::msg::abnf::NotEnoughOutSpace;
+  use crate::msg::output::AssembleError;
 
   #[test]
   fn headers_test() {
@@ -101,10 +214,41 @@ mod tests {
     assert_eq!(hdrs[4], "[%ff]".as_bytes());
     assert_eq!(hdrs[5], "_/.?".as_bytes());
 
-    assert_eq!(headers("?a=b".as_bytes(), &mut []), Err(ParseError::NotEnoughOutSpace { written: 0 }));
-    assert_eq!(headers("?a=b&c=d".as_bytes(), &mut hdrs[..1]), Err(ParseError::NotEnoughOutSpace { written: 0 }));
-    assert_eq!(headers("?a=b&c=d".as_bytes(), &mut hdrs[..2]), Err(ParseError::NotEnoughOutSpace { written: 2 }));
+    assert_eq!(headers("?a=b".as_bytes(), &mut []), Err(NotEnoughOutSpace { written: 0 }));
+    assert_eq!(headers("?a=b&c=d".as_bytes(), &mut hdrs[..1]), Err(NotEnoughOutSpace { written: 0 }));
+    assert_eq!(headers("?a=b&c=d".as_bytes(), &mut hdrs[..2]), Err(NotEnoughOutSpace { written: 2 }));
   }
 
+  #[test]
+  fn lazy_headers_test() {
+    let (rest, hdrs) = lazy_headers("?subject=project%20x&priority=urgent&".as_bytes()).unwrap();
+    assert_eq!(rest, "&".as_bytes());
+    assert_eq!(hdrs.as_bytes(), "subject=project%20x&priority=urgent".as_bytes());
+
+    let mut it = hdrs.iter();
+    assert_eq!(it.next(), Some(("subject".as_bytes(), "project%20x".as_bytes())));
+    assert_eq!(it.next(), Some(("priority".as_bytes(), "urgent".as_bytes())));
+    assert_eq!(it.next(), None);
+
+    assert!(lazy_headers("subject=x".as_bytes()).is_err());
+  }
 
-}
\ No newline at end of file
+  #[test]
+  fn headers_assemble_test() {
+    let mut buf = [0u8; 64];
+    let value = encode(b"project x", Context::Header);
+    let hdrs: [(&[u8], &[u8]); 2] = [(b"subject", &value), (b"to", b"alice%40atlanta.com")];
+    let n = assemble::headers(hdrs.iter().copied(), &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"?subject=project%20x&to=alice%40atlanta.com".as_ref());
+
+    let (rest, parsed) = lazy_headers(&buf[..n]).unwrap();
+    assert!(rest.is_empty());
+    let mut again = [0u8; 64];
+    let m = assemble::headers(parsed, &mut again).unwrap();
+    assert_eq!(&buf[..n], &again[..m]);
+
+    assert_eq!(assemble::headers(Vec::new(), &mut buf), Ok(0));
+    let raw: [(&[u8], &[u8]); 1] = [(b"to", b"alice@atlanta.com")];
+    assert_eq!(assemble::headers(raw.iter().copied(), &mut buf), Err(AssembleError::NotEscaped { written: 4 }));
+  }
+}
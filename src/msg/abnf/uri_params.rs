@@ -1,11 +1,11 @@
-use super::ch::{unreserved, escaped, token};
+use super::ch::{unreserved, escaped, token, is_token, is_unreserved};
 use super::digit::dec_u8;
 use super::super::method::{parse::method, Method};
-use super::super::Binary;
+use super::super::{Binary, Byte};
 use super::host::host;
-use nom::{IResult, Needed};
+use super::{whole, NotEnoughOutSpace};
+use nom::IResult;
 use crate::msg::abnf::host::Host;
-use nom::Err::Incomplete;
 
 // param-unreserved =  "[" / "]" / "/" / ":" / "&" / "+" / "$"
 named!(#[inline],
@@ -19,6 +19,12 @@ named!(#[inline],
   alt!(param_unreserved | unreserved | escaped)
 );
 
+// paramchar which don't need escaping
+#[inline]
+pub fn is_param_char(b: Byte) -> bool {
+  is_unreserved(b) || matches!(b, b'[' | b']' | b'/' | b':' | b'&' | b'+' | b'$')
+}
+
 // pname            =  1*paramchar
 named!(#[inline],
   pub pname,
@@ -32,24 +38,35 @@ named!(#[inline],
 );
 
 // other-param      =  pname [ "=" pvalue ]
-named!(#[inline],
-  pub other_param<(&Binary, Option<&Binary>)>,
-  tuple!(pname, opt!(preceded!(byte!(b'='), pvalue)))
-);
+//
+// (not opt! since it propagates Incomplete, and a parameter
+// without value may well be the last thing in the input)
+#[inline]
+pub fn other_param(input: &Binary) -> IResult<&Binary, (&Binary, Option<&Binary>)> {
+  let (rest, name) = pname(input)?;
+  match preceded!(rest, byte!(b'='), pvalue) {
+    Ok((r, value)) => Ok((r, (name, Some(value)))),
+    Err(_) => Ok((rest, (name, None)))
+  }
+}
 
 // maddr-param      =  "maddr=" host
 #[inline]
-pub fn maddr_param<'a, 'b>(input: &'a Binary, domains: &'b mut [&'a Binary])
-  -> IResult<&'a Binary, Host<'a>> {
+pub fn maddr_param(input: &Binary) -> IResult<&Binary, Host<'_>> {
   let (rest, _) = tag!(input, "maddr=")?;
-  host(rest, domains)
+  host(rest)
 }
 
-// ttl-param        =  "ttl=" ttl
 // ttl               =  1*3DIGIT ; 0 to 255
+named!(#[inline],
+  pub ttl<u8>,
+  call!(dec_u8)
+);
+
+// ttl-param        =  "ttl=" ttl
 named!(#[inline],
   pub ttl_param<u8>,
-  preceded!(tag!("ttl="), dec_u8)
+  preceded!(tag!("ttl="), ttl)
 );
 
 // method-param     =  "method=" Method
@@ -65,6 +82,26 @@ pub enum User<'a> {
   Other(&'a Binary)
 }
 
+impl<'a> User<'a> {
+  pub fn as_bytes(&self) -> &'a Binary {
+    match self {
+      User::Phone => b"phone",
+      User::Ip => b"ip",
+      User::Other(t) => t
+    }
+  }
+
+  pub fn from_token(t: &'a Binary) -> User<'a> {
+    if t.eq_ignore_ascii_case(b"phone") {
+      User::Phone
+    } else if t.eq_ignore_ascii_case(b"ip") {
+      User::Ip
+    } else {
+      User::Other(t)
+    }
+  }
+}
+
 // user-param       =  "user=" ( "phone" / "ip" / other-user)
 // other-user       =  token
 named!(#[inline],
@@ -72,7 +109,7 @@ named!(#[inline],
   preceded!(tag!("user="), alt!(
     tag!("phone") => { |_| User::Phone } |
     tag!("ip") => { |_| User::Ip } |
-    token => { |t| User::Other(t) }
+    token => { User::Other }
   ))
 );
 
@@ -85,6 +122,32 @@ pub enum Transport<'a> {
   Other(&'a Binary)
 }
 
+impl<'a> Transport<'a> {
+  pub fn as_bytes(&self) -> &'a Binary {
+    match self {
+      Transport::UDP => b"udp",
+      Transport::TCP => b"tcp",
+      Transport::SCTP => b"sctp",
+      Transport::TLS => b"tls",
+      Transport::Other(t) => t
+    }
+  }
+
+  pub fn from_token(t: &'a Binary) -> Transport<'a> {
+    if t.eq_ignore_ascii_case(b"udp") {
+      Transport::UDP
+    } else if t.eq_ignore_ascii_case(b"tcp") {
+      Transport::TCP
+    } else if t.eq_ignore_ascii_case(b"sctp") {
+      Transport::SCTP
+    } else if t.eq_ignore_ascii_case(b"tls") {
+      Transport::TLS
+    } else {
+      Transport::Other(t)
+    }
+  }
+}
+
 // transport-param  =  "transport="
 //                    ( "udp" / "tcp" / "sctp" / "tls"
 //                    / other-transport)
@@ -96,7 +159,7 @@ named!(#[inline],
     tag!("tcp") => { |_| Transport::TCP } |
     tag!("sctp") => { |_| Transport::SCTP } |
     tag!("tls") => { |_| Transport::TLS } |
-    token => { |t| Transport::Other(t) }
+    token => { Transport::Other }
   ))
 );
 
@@ -114,36 +177,51 @@ pub enum UriParam <'a> {
 // lr-param         =  "lr"
 // uri-parameter    =  transport-param / user-param / method-param
 //                    / ttl-param / maddr-param / lr-param / other-param
+//
+// Every uri-parameter is an other-param as well, so the parameter extent
+// is taken from other-param first. A well-known parameter is recognized
+// only when its value spans this whole extent, otherwise ";lrx" would be
+// taken for "lr" followed by garbage.
 #[inline]
-pub fn uri_parameter<'a, 'b>(input: &'a Binary, domains: &'b mut [&'a Binary])
-  -> IResult<&'a Binary, UriParam<'a>> {
-  alt!(input,
-    tag!("lr") => { |_| UriParam::Lr } |
-    transport_param => { |t| UriParam::Transport(t) } |
-    user_param => { |u| UriParam::User(u) } |
-    method_param => { |m| UriParam::Method(m) } |
-    ttl_param => { |t| UriParam::Ttl(t) } |
-    call!(maddr_param, domains) => { |h| UriParam::Maddr(h) } |
-    other_param => { |(h, v)| UriParam::Other(h, v) }
-  )
+pub fn uri_parameter(input: &Binary) -> IResult<&Binary, UriParam<'_>> {
+  let (rest, (name, value)) = other_param(input)?;
+  let param = well_known_parameter(name, value)
+    .unwrap_or(UriParam::Other(name, value));
+  Ok((rest, param))
+}
+
+fn well_known_parameter<'a>(name: &'a Binary, value: Option<&'a Binary>)
+  -> Option<UriParam<'a>> {
+  let is = |n: &[u8]| name.eq_ignore_ascii_case(n);
+
+  match value {
+    None if is(b"lr") => Some(UriParam::Lr),
+    Some(v) if is(b"transport") && is_token(v) => Some(UriParam::Transport(Transport::from_token(v))),
+    Some(v) if is(b"user") && is_token(v) => Some(UriParam::User(User::from_token(v))),
+    Some(v) if is(b"method") && is_token(v) => Some(UriParam::Method(
+      Method::from_token(v)
+    )),
+    Some(v) if is(b"ttl") => whole(ttl(v)).map(UriParam::Ttl),
+    Some(v) if is(b"maddr") => whole(host(v)).map(UriParam::Maddr),
+    _ => None
+  }
 }
 
 // uri-parameters   =  *( ";" uri-parameter)
 #[inline]
-pub fn uri_parameters<'a, 'b, 'c>(
+pub fn uri_parameters<'a>(
   input: &'a Binary,
-  params: &'b mut [UriParam<'a>],
-  domains: &'c mut [&'a Binary]
+  params: &mut [UriParam<'a>]
 )
-  -> IResult<&'a Binary, usize> {
+  -> Result<(&'a Binary, usize), NotEnoughOutSpace> {
 
   let mut rest = input;
   let mut i = 0usize;
 
   while let Ok((r, _)) = byte!(rest, b';') {
-    if let Ok((r, v)) = uri_parameter(r, domains) {
+    if let Ok((r, v)) = uri_parameter(r) {
       if i >= params.len() {
-        return Err(Incomplete(Needed::Unknown));
+        return Err(NotEnoughOutSpace { written: i });
       }
       params[i] = v;
       i += 1;
@@ -156,38 +234,363 @@ pub fn uri_parameters<'a, 'b, 'c>(
   Ok((rest, i))
 }
 
+// uri-parameters which were already validated by the parser and are kept
+// as the original slice. Parameters are parsed again on every iteration,
+// so holding them costs nothing but a reference.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default)]
+pub struct UriParameters<'a>(&'a Binary);
+
+impl<'a> UriParameters<'a> {
+  pub fn as_bytes(&self) -> &'a Binary {
+    self.0
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  pub fn iter(&self) -> UriParametersIter<'a> {
+    UriParametersIter { rest: self.0 }
+  }
+}
+
+impl<'a> IntoIterator for UriParameters<'a> {
+  type Item = UriParam<'a>;
+  type IntoIter = UriParametersIter<'a>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+pub struct UriParametersIter<'a> {
+  rest: &'a Binary
+}
+
+impl<'a> Iterator for UriParametersIter<'a> {
+  type Item = UriParam<'a>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let (r, _) = byte!(self.rest, b';').ok()?;
+    let (r, param) = uri_parameter(r).ok()?;
+    self.rest = r;
+    Some(param)
+  }
+}
+
+// uri-parameters   =  *( ";" uri-parameter)
+#[inline]
+pub fn lazy_uri_parameters(input: &Binary) -> IResult<&Binary, UriParameters<'_>> {
+  let mut it = UriParametersIter { rest: input };
+  while it.next().is_some() {}
+
+  let rest = it.rest;
+  Ok((rest, UriParameters(&input[..input.len() - rest.len()])))
+}
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::abnf::host::assemble::host;
+  use crate::msg::abnf::uri_params::{UriParam, is_param_char};
+  use crate::msg::output::{Output, AssembleResult};
+
+  // uri-parameter without the leading ";"
+  pub fn uri_parameter(p: UriParam, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    match p {
+      UriParam::Transport(t) => {
+        out.put(b"transport=")?;
+        out.put_component(t.as_bytes(), is_param_char)?;
+      },
+      UriParam::User(u) => {
+        out.put(b"user=")?;
+        out.put_component(u.as_bytes(), is_param_char)?;
+      },
+      UriParam::Method(m) => {
+        out.put(b"method=")?;
+        out.put_component(m.as_bytes(), is_param_char)?;
+      },
+      UriParam::Ttl(ttl) => {
+        out.put(b"ttl=")?;
+        out.put_number(ttl as u64)?;
+      },
+      UriParam::Maddr(h) => {
+        out.put(b"maddr=")?;
+        out.with(|b| host(h, b))?;
+      },
+      UriParam::Lr => out.put(b"lr")?,
+      UriParam::Other(name, value) => {
+        out.put_component(name, is_param_char)?;
+        if let Some(v) = value {
+          out.put_byte(b'=')?;
+          out.put_component(v, is_param_char)?;
+        }
+      }
+    }
+    out.finish()
+  }
+
+  // uri-parameters   =  *( ";" uri-parameter)
+  pub fn uri_parameters<'a, I>(params: I, buf: &mut Binary) -> AssembleResult
+    where I: IntoIterator<Item = UriParam<'a>> {
+    let mut out = Output::new(buf);
+    for p in params {
+      out.put_byte(b';')?;
+      out.with(|b| uri_parameter(p, b))?;
+    }
+    out.finish()
+  }
+}
+
+pub mod owned {
+  use crate::msg::Binary;
+  use crate::msg::method::owned::Method;
+  use crate::msg::abnf::host::owned::Host;
+
+  #[derive(Clone, Debug, Eq, PartialEq, Hash)]
+  pub enum User {
+    Phone,
+    Ip,
+    Other(Vec<u8>)
+  }
+
+  impl User {
+    pub fn as_borrowed(&self) -> super::User<'_> {
+      match self {
+        User::Phone => super::User::Phone,
+        User::Ip => super::User::Ip,
+        User::Other(t) => super::User::Other(t)
+      }
+    }
+  }
+
+  impl<'a> super::User<'a> {
+    pub fn into_owned(self) -> User {
+      match self {
+        super::User::Phone => User::Phone,
+        super::User::Ip => User::Ip,
+        super::User::Other(t) => User::Other(t.to_vec())
+      }
+    }
+  }
+
+  #[derive(Clone, Debug, Eq, PartialEq, Hash)]
+  pub enum Transport {
+    UDP,
+    TCP,
+    SCTP,
+    TLS,
+    Other(Vec<u8>)
+  }
+
+  impl Transport {
+    pub fn as_borrowed(&self) -> super::Transport<'_> {
+      match self {
+        Transport::UDP => super::Transport::UDP,
+        Transport::TCP => super::Transport::TCP,
+        Transport::SCTP => super::Transport::SCTP,
+        Transport::TLS => super::Transport::TLS,
+        Transport::Other(t) => super::Transport::Other(t)
+      }
+    }
+  }
+
+  impl<'a> super::Transport<'a> {
+    pub fn into_owned(self) -> Transport {
+      match self {
+        super::Transport::UDP => Transport::UDP,
+        super::Transport::TCP => Transport::TCP,
+        super::Transport::SCTP => Transport::SCTP,
+        super::Transport::TLS => Transport::TLS,
+        super::Transport::Other(t) => Transport::Other(t.to_vec())
+      }
+    }
+  }
+
+  #[derive(Clone, Debug, Eq, PartialEq, Hash)]
+  pub enum UriParam {
+    Transport(Transport),
+    User(User),
+    Method(Method),
+    Ttl(u8),
+    Maddr(Host),
+    Lr,
+    Other(Vec<u8>, Option<Vec<u8>>)
+  }
+
+  impl UriParam {
+    pub fn as_borrowed(&self) -> super::UriParam<'_> {
+      match self {
+        UriParam::Transport(t) => super::UriParam::Transport(t.as_borrowed()),
+        UriParam::User(u) => super::UriParam::User(u.as_borrowed()),
+        UriParam::Method(m) => super::UriParam::Method(m.as_borrowed()),
+        UriParam::Ttl(ttl) => super::UriParam::Ttl(*ttl),
+        UriParam::Maddr(h) => super::UriParam::Maddr(h.as_borrowed()),
+        UriParam::Lr => super::UriParam::Lr,
+        UriParam::Other(n, v) => super::UriParam::Other(n, v.as_deref())
+      }
+    }
+  }
+
+  impl<'a> super::UriParam<'a> {
+    pub fn into_owned(self) -> UriParam {
+      match self {
+        super::UriParam::Transport(t) => UriParam::Transport(t.into_owned()),
+        super::UriParam::User(u) => UriParam::User(u.into_owned()),
+        super::UriParam::Method(m) => UriParam::Method(m.into_owned()),
+        super::UriParam::Ttl(ttl) => UriParam::Ttl(ttl),
+        super::UriParam::Maddr(h) => UriParam::Maddr(h.into_owned()),
+        super::UriParam::Lr => UriParam::Lr,
+        super::UriParam::Other(n, v) => UriParam::Other(n.to_vec(), v.map(<[u8]>::to_vec))
+      }
+    }
+  }
+
+  // Validated uri-parameters, same as the borrowed ones it is only the
+  // bytes, so converting either way is a single copy or nothing at all
+  #[derive(Clone, Debug, Eq, PartialEq, Hash, Default)]
+  pub struct UriParameters(Vec<u8>);
+
+  impl UriParameters {
+    pub fn as_bytes(&self) -> &Binary {
+      &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+      self.0.is_empty()
+    }
+
+    pub fn as_borrowed(&self) -> super::UriParameters<'_> {
+      super::UriParameters(&self.0)
+    }
+  }
+
+  impl<'a> super::UriParameters<'a> {
+    pub fn into_owned(self) -> UriParameters {
+      UriParameters(self.0.to_vec())
+    }
+  }
+
+  impl<'a> From<super::UriParameters<'a>> for UriParameters {
+    fn from(p: super::UriParameters<'a>) -> UriParameters {
+      p.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a UriParameters> for super::UriParameters<'a> {
+    fn from(p: &'a UriParameters) -> super::UriParameters<'a> {
+      p.as_borrowed()
+    }
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::{
     uri_parameters,
+    lazy_uri_parameters,
+    assemble,
     UriParam,
     Transport
   };
+  use crate::msg::output::AssembleError;
   use super::super::super::method::Method;
-  use crate::msg::abnf::host::Host;
+  use crate::msg::abnf::host::{Host, Hostname};
   use crate::msg::abnf::uri_params::User;
+  use crate::msg::abnf::NotEnoughOutSpace;
 
   #[test]
   fn uri_parameters_test() {
-    let mut domains = ["".as_bytes(); 100];
     let mut params = [UriParam::Lr; 100];
 
     assert_eq!(uri_parameters(
       ";transport=udp;method=INVITE;ttl=212;maddr=alib.ru;lr;user=phone;ooo=999;q asdf"
       .as_bytes(),
-      &mut params,
-      &mut domains
+      &mut params
     ), Ok((" asdf".as_bytes(), 8)));
 
     assert_eq!(params[0], UriParam::Transport(Transport::UDP));
     assert_eq!(params[1], UriParam::Method(Method::Invite));
     assert_eq!(params[2], UriParam::Ttl(212));
-    assert_eq!(params[3], UriParam::Maddr(Host::Hostname("ru".as_bytes(), 1)));
-    assert_eq!(domains[0], "alib".as_bytes());
+    assert_eq!(params[3], UriParam::Maddr(Host::Hostname(Hostname::new(b"alib.ru").unwrap())));
     assert_eq!(params[4], UriParam::Lr);
     assert_eq!(params[5], UriParam::User(User::Phone));
     assert_eq!(params[6], UriParam::Other("ooo".as_bytes(), Some("999".as_bytes())));
     assert_eq!(params[7], UriParam::Other("q".as_bytes(), None));
+
+    assert_eq!(uri_parameters(";lr;ttl=1;q".as_bytes(), &mut params[..2]), Err(NotEnoughOutSpace { written: 2 }));
+    assert_eq!(uri_parameters(";lr".as_bytes(), &mut []), Err(NotEnoughOutSpace { written: 0 }));
   }
 
-}
\ No newline at end of file
+  #[test]
+  fn uri_parameter_boundary_test() {
+    let mut params = [UriParam::Lr; 10];
+
+    assert_eq!(uri_parameters(
+      ";lrx;ttl=2555;transport=tcp;maddr=a.b.c;method=%20;user=ipx;method=INVITEX;maddr=1.1.1.1x"
+      .as_bytes(),
+      &mut params
+    ), Ok(("".as_bytes(), 8)));
+
+    assert_eq!(params[0], UriParam::Other("lrx".as_bytes(), None));
+    assert_eq!(params[1], UriParam::Other("ttl".as_bytes(), Some("2555".as_bytes())));
+    assert_eq!(params[2], UriParam::Transport(Transport::TCP));
+    assert_eq!(params[3], UriParam::Maddr(Host::Hostname(Hostname::new(b"a.b.c").unwrap())));
+    assert_eq!(params[4], UriParam::Method(Method::Token("%20".as_bytes())));
+    assert_eq!(params[5], UriParam::User(User::Other("ipx".as_bytes())));
+    assert_eq!(params[6], UriParam::Method(Method::Token("INVITEX".as_bytes())));
+    assert_eq!(params[7], UriParam::Other("maddr".as_bytes(), Some("1.1.1.1x".as_bytes())));
+  }
+
+  #[test]
+  fn lazy_uri_parameters_test() {
+    let (rest, params) = lazy_uri_parameters(";lr;ttl=1;x=y?h=v".as_bytes()).unwrap();
+    assert_eq!(rest, "?h=v".as_bytes());
+    assert_eq!(params.as_bytes(), ";lr;ttl=1;x=y".as_bytes());
+
+    let mut it = params.iter();
+    assert_eq!(it.next(), Some(UriParam::Lr));
+    assert_eq!(it.next(), Some(UriParam::Ttl(1)));
+    assert_eq!(it.next(), Some(UriParam::Other("x".as_bytes(), Some("y".as_bytes()))));
+    assert_eq!(it.next(), None);
+
+    let (rest, params) = lazy_uri_parameters(" ;lr".as_bytes()).unwrap();
+    assert_eq!(rest, " ;lr".as_bytes());
+    assert!(params.is_empty());
+  }
+
+  #[test]
+  fn uri_parameters_assemble_test() {
+    let mut buf = [0u8; 128];
+    let params = [
+      UriParam::Transport(Transport::TCP),
+      UriParam::User(User::Phone),
+      UriParam::Method(Method::Invite),
+      UriParam::Ttl(16),
+      UriParam::Maddr(Host::Hostname(Hostname::new(b"alib.ru").unwrap())),
+      UriParam::Lr,
+      UriParam::Other(b"x%20y", Some(b"[%41]")),
+      UriParam::Other(b"q", None)
+    ];
+    let n = assemble::uri_parameters(params.iter().copied(), &mut buf).unwrap();
+    assert_eq!(
+      &buf[..n],
+      b";transport=tcp;user=phone;method=INVITE;ttl=16;maddr=alib.ru;lr;x%20y=[%41];q".as_ref()
+    );
+
+    let (rest, parsed) = lazy_uri_parameters(&buf[..n]).unwrap();
+    assert!(rest.is_empty());
+    let mut again = [0u8; 128];
+    let m = assemble::uri_parameters(parsed, &mut again).unwrap();
+    assert_eq!(&buf[..n], &again[..m]);
+
+    assert_eq!(
+      assemble::uri_parameters(params.iter().copied(), &mut buf[..20]),
+      Err(AssembleError::NotEnoughOutSpace { written: 20 })
+    );
+    assert_eq!(
+      assemble::uri_parameters([UriParam::Lr, UriParam::Other(b"x y", None)].iter().copied(), &mut buf),
+      Err(AssembleError::NotEscaped { written: 4 })
+    );
+  }
+}
@@ -1,14 +1,16 @@
 use super::ch::escaped;
 use super::ch::unreserved;
 use super::ch::user_unreserved;
+use super::ch::{is_unreserved, is_user_unreserved};
 use super::digit::single;
-use super::host::hostname;
+use super::host::{hostname, Hostname};
 use super::map_byte;
 use crate::msg::abnf::ch::utf8;
-use crate::msg::Binary;
+use crate::msg::{Binary, Byte};
 use nom::error::ErrorKind;
 use nom::Err::Error;
 use nom::IResult;
+use std::borrow::Cow;
 
 // userinfo         =  ( user / telephone-subscriber ) [ ":" password ] "@"
 named!(#[inline],
@@ -29,6 +31,18 @@ named!(#[inline],
   at_least_one!(alt!(unreserved | escaped | user_unreserved))
 );
 
+// characters allowed in user without escaping
+#[inline]
+pub fn is_user_char(b: Byte) -> bool {
+    is_unreserved(b) || is_user_unreserved(b)
+}
+
+// characters allowed in password without escaping
+#[inline]
+pub fn is_password_char(b: Byte) -> bool {
+    is_unreserved(b) || matches!(b, b'&' | b'=' | b'+' | b'$' | b',')
+}
+
 // The BNF for telephone-subscriber can be found in RFC 2806 [9].  Note,
 // however, that any characters allowed there that are not allowed in
 // the user part of the SIP URI MUST be escaped.
@@ -101,7 +115,7 @@ named!(#[inline],
 
 // area-specifier        = ";" phone-context-tag "=" phone-context-ident
 #[inline]
-pub fn area_specifier(input: &Binary) -> IResult<&Binary, PhoneContextIdent> {
+pub fn area_specifier(input: &Binary) -> IResult<&Binary, PhoneContextIdent<'_>> {
     let (r, _) = byte!(input, b';')?;
     let (r, _) = phone_context_tag(r)?;
     phone_context_ident(r)
@@ -121,9 +135,9 @@ pub enum PhoneContextIdent<'a> {
 named!(#[inline],
   pub phone_context_ident<PhoneContextIdent>,
   alt!(
-    network_prefix => { |p| PhoneContextIdent::NetworkPrefix(p) }
+    network_prefix => { PhoneContextIdent::NetworkPrefix }
     |
-    private_prefix => { |p| PhoneContextIdent::PrivatePrefix(p) }
+    private_prefix => { PhoneContextIdent::PrivatePrefix }
   )
 );
 
@@ -136,9 +150,9 @@ pub enum NetworkPrefix {
 named!(#[inline],
   pub network_prefix<NetworkPrefix>,
   alt!(
-    global_network_prefix => { |b| NetworkPrefix::Global(b) }
+    global_network_prefix => { NetworkPrefix::Global }
     |
-    local_network_prefix => { |b| NetworkPrefix::Local(b) }
+    local_network_prefix => { NetworkPrefix::Local }
   )
 );
 
@@ -178,14 +192,11 @@ named!(#[inline],
 //
 // service-provider      = ";" provider-tag "=" provider-hostname
 #[inline]
-pub fn service_provider<'a, 'b>(
-    input: &'a Binary,
-    domains: &'b mut [&'a Binary],
-) -> IResult<&'a Binary, (&'a Binary, usize)> {
+pub fn service_provider(input: &Binary) -> IResult<&Binary, Hostname<'_>> {
     let (rest, _) = byte!(input, b';')?;
     let (rest, _) = provider_tag(rest)?;
     let (rest, _) = byte!(rest, b'=')?;
-    provider_hostname(rest, domains)
+    provider_hostname(rest)
 }
 
 // provider-tag          = "tsp"
@@ -195,11 +206,8 @@ named!(#[inline],
 );
 // provider-hostname     = domain ; <domain> is defined in [RFC1035]
 #[inline]
-pub fn provider_hostname<'a, 'b>(
-    input: &'a Binary,
-    domains: &'b mut [&'a Binary],
-) -> IResult<&'a Binary, (&'a Binary, usize)> {
-    hostname(input, domains)
+pub fn provider_hostname(input: &Binary) -> IResult<&Binary, Hostname<'_>> {
+    hostname(input)
 }
 
 // ; See section 2.5.10
@@ -212,7 +220,7 @@ pub enum FutureExtensionValue<'a> {
 #[inline]
 pub fn future_extension(
     input: &Binary,
-) -> IResult<&Binary, (&Binary, Option<FutureExtensionValue>)> {
+) -> IResult<&Binary, (&Binary, Option<FutureExtensionValue<'_>>)> {
     let (rest, _) = byte!(input, b';')?;
     let (rest, token) = at_least_one!(rest, token_char)?;
 
@@ -223,7 +231,7 @@ pub fn future_extension(
           }
           |
           quoted_string => {
-            |s| FutureExtensionValue::Quoted(s)
+            FutureExtensionValue::Quoted
           }
         )?;
         Ok((rest, (token, Some(val))))
@@ -237,11 +245,10 @@ pub fn future_extension(
 //                          / %x41-5A / %x5E-7A / %x7C / %x7E)
 named!(#[inline],
   pub token_char<u8>,
-  call!(map_byte, |b| match b {
+  call!(map_byte, |b| matches!(b,
     0x21 | 0x23..=0x27 | 0x2a..=0x2b | 0x2d..=0x2e | 0x30..=0x39 |
-    0x41..=0x5a | 0x5e..=0x7a | 0x7c | 0x7e => true,
-    _ => false
-  })
+    0x41..=0x5a | 0x5e..=0x7a | 0x7c | 0x7e
+  ))
 );
 
 // ; Characters in URLs must follow escaping rules
@@ -254,20 +261,19 @@ named!(#[inline],
 //                        ; See sections 1.2 and 2.5.11
 #[inline]
 pub fn quoted_string(input: &Binary) -> IResult<&Binary, &Binary> {
-    let mut counter = 0usize;
     let (mut rest, _) = byte!(input, 0x22)?;
 
     loop {
         let (r, ch) = take!(rest, 1)?;
         match ch[0] {
+            // the escaped character is skipped whole, so an escaped
+            // DQUOTE doesn't end the string
             b'\\' => {
-                let (r, ch) = utf8(rest)?;
+                let (r, _) = utf8(r)?;
                 rest = r;
-                counter += ch.len_utf8();
             }
             0x20..=0x21 | 0x23..=0x7e | 0x80..=0xff => {
                 rest = r;
-                counter += 1;
             }
             0x22 => {
                 rest = r;
@@ -279,7 +285,43 @@ pub fn quoted_string(input: &Binary) -> IResult<&Binary, &Binary> {
         }
     }
 
-    Ok((rest, &input[1..counter + 1]))
+    // without the surrounding quotes, escapes are kept as they are
+    Ok((rest, &input[1..input.len() - rest.len() - 1]))
+}
+
+// Content of a quoted-string with every quoted-pair replaced by the
+// character it escapes. Content without any is returned borrowed.
+pub fn unquote(content: &Binary) -> Cow<'_, Binary> {
+    if !content.contains(&b'\\') {
+        return Cow::Borrowed(content);
+    }
+    let mut out = Vec::with_capacity(content.len());
+    let mut escaped = false;
+    for &b in content {
+        if b == b'\\' && !escaped {
+            escaped = true;
+        } else {
+            out.push(b);
+            escaped = false;
+        }
+    }
+    Cow::Owned(out)
+}
+
+// Content of a quoted-string for the text, the inverse of unquote.
+// DQUOTE and backslash are the only characters which need a quoted-pair.
+pub fn escape_quoted(text: &Binary) -> Cow<'_, Binary> {
+    if !text.iter().any(|b| *b == b'"' || *b == b'\\') {
+        return Cow::Borrowed(text);
+    }
+    let mut out = Vec::with_capacity(text.len() + 2);
+    for &b in text {
+        if b == b'"' || b == b'\\' {
+            out.push(b'\\');
+        }
+        out.push(b);
+    }
+    Cow::Owned(out)
 }
 
 // phonedigit            = DIGIT / visual-separator
@@ -324,14 +366,83 @@ named!(#[inline],
   one_of_byte!(b'*' | b'#' | b'A' | b'B' | b'C' | b'D')
 );
 
+pub mod assemble {
+    use super::{is_password_char, is_user_char};
+    use crate::msg::output::{AssembleResult, Output};
+    use crate::msg::Binary;
+
+    pub fn user(u: &Binary, buf: &mut Binary) -> AssembleResult {
+        let mut out = Output::new(buf);
+        out.put_component(u, is_user_char)?;
+        out.finish()
+    }
+
+    pub fn password(p: &Binary, buf: &mut Binary) -> AssembleResult {
+        let mut out = Output::new(buf);
+        out.put_component(p, is_password_char)?;
+        out.finish()
+    }
+
+    // userinfo         =  ( user / telephone-subscriber ) [ ":" password ] "@"
+    pub fn userinfo(u: &Binary, p: Option<&Binary>, buf: &mut Binary) -> AssembleResult {
+        let mut out = Output::new(buf);
+        out.with(|b| user(u, b))?;
+        if let Some(p) = p {
+            out.put_byte(b':')?;
+            out.with(|b| password(p, b))?;
+        }
+        out.put_byte(b'@')?;
+        out.finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
+    use super::assemble;
     use super::dtmf_digit;
-    use super::DTMF;
+    use crate::msg::abnf::escape::{encode, Context};
+    use crate::msg::output::AssembleError;
+    use super::{quoted_string, unquote, escape_quoted};
+    use super::userinfo;
 
     #[test]
     fn dtmf_digit_test() {
-        assert_eq!(dtmf_digit("*".as_bytes()), Ok(("".as_bytes(), DTMF{ digit: b'*'})));
+        assert_eq!(dtmf_digit("*".as_bytes()), Ok(("".as_bytes(), b'*')));
+    }
+
+    #[test]
+    fn quoted_string_test() {
+        assert_eq!(quoted_string(b"\"abc\";x"), Ok((&b";x"[..], &b"abc"[..])));
+        assert_eq!(quoted_string(b"\"\""), Ok((&b""[..], &b""[..])));
+        assert_eq!(quoted_string(b"\"a\\\"b\\\\\" c"), Ok((&b" c"[..], &b"a\\\"b\\\\"[..])));
+        assert_eq!(quoted_string("\"\\ü\"".as_bytes()), Ok((&b""[..], "\\ü".as_bytes())));
+        assert!(quoted_string(b"\"abc").is_err());
+        assert!(quoted_string(b"abc\"").is_err());
+
+        assert_eq!(unquote(b"a\\\"b\\\\"), b"a\"b\\".to_vec());
+        assert!(matches!(unquote(b"abc"), std::borrow::Cow::Borrowed(_)));
+        assert_eq!(escape_quoted(b"a\"b\\"), b"a\\\"b\\\\".to_vec());
+        assert_eq!(unquote(&escape_quoted(b"\\\"")), b"\\\"".to_vec());
+    }
+
+    #[test]
+    fn userinfo_assemble_test() {
+        let mut buf = [0u8; 64];
+        let user = encode(b"al ice;x", Context::User);
+        let password = encode(b"p@ss:A", Context::Password);
+        let n = assemble::userinfo(&user, Some(&password), &mut buf).unwrap();
+        assert_eq!(&buf[..n], b"al%20ice;x:p%40ss%3AA@".as_ref());
+        assert_eq!(
+            userinfo(&buf[..n]),
+            Ok(("".as_bytes(), ("al%20ice;x".as_bytes(), Some("p%40ss%3AA".as_bytes()))))
+        );
+        assert_eq!(
+            assemble::userinfo(b"alice", Some(b"p@ss"), &mut buf),
+            Err(AssembleError::NotEscaped { written: 6 })
+        );
+
+        let n = assemble::userinfo(b"+1-212-555-1212", None, &mut buf).unwrap();
+        assert_eq!(&buf[..n], b"+1-212-555-1212@");
     }
 }
@@ -0,0 +1,277 @@
+use super::Binary;
+use super::header::{Header, HeaderName};
+use super::header::assemble::header;
+use super::header::numeric::assemble::content_length;
+use super::method::Method;
+use super::output::{Output, AssembleError};
+use super::start_line::{StartLine, RequestLine, StatusLine};
+use super::start_line::assemble::start_line;
+use super::status::StatusCode;
+use super::uri::Uri;
+use super::version::{Version, DEFAULT_VERSION};
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum BuildError {
+  // one of the headers RFC 3261 8.1.1 and 8.2.6.2 require
+  MissingHeader(HeaderName<'static>),
+  Assemble(AssembleError)
+}
+
+impl From<AssembleError> for BuildError {
+  fn from(e: AssembleError) -> BuildError {
+    BuildError::Assemble(e)
+  }
+}
+
+const REQUEST_HEADERS: &[HeaderName<'static>] = &[
+  HeaderName::Via, HeaderName::To, HeaderName::From,
+  HeaderName::CallId, HeaderName::CSeq, HeaderName::MaxForwards
+];
+
+const RESPONSE_HEADERS: &[HeaderName<'static>] = &[
+  HeaderName::Via, HeaderName::To, HeaderName::From,
+  HeaderName::CallId, HeaderName::CSeq
+];
+
+// Request or response out of values which were assembled or parsed
+// before. Via headers go first and Route headers right after them, each
+// in the order they were added, then the rest in the order they were
+// added. Content-Length is always written last, taken from the body.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct MessageBuilder<'a> {
+  line: StartLine<'a>,
+  via: Vec<&'a Binary>,
+  route: Vec<&'a Binary>,
+  headers: Vec<Header<'a>>,
+  to_tag: Option<Vec<u8>>,
+  body: &'a Binary
+}
+
+impl<'a> MessageBuilder<'a> {
+  fn new(line: StartLine<'a>) -> MessageBuilder<'a> {
+    MessageBuilder {
+      line,
+      via: Vec::new(),
+      route: Vec::new(),
+      headers: Vec::new(),
+      to_tag: None,
+      body: b""
+    }
+  }
+
+  pub fn request(method: Method<'a>, uri: Uri<'a>) -> MessageBuilder<'a> {
+    MessageBuilder::new(StartLine::Request(RequestLine { method, uri, version: DEFAULT_VERSION }))
+  }
+
+  // Reason-Phrase is the one registered for the code
+  pub fn response(code: StatusCode) -> MessageBuilder<'a> {
+    let reason = code.reason_phrase().as_bytes();
+    MessageBuilder::new(StartLine::Status(StatusLine { version: DEFAULT_VERSION, code, reason }))
+  }
+
+  pub fn with_reason(mut self, reason: &'a Binary) -> MessageBuilder<'a> {
+    if let StartLine::Status(ref mut line) = self.line {
+      line.reason = reason;
+    }
+    self
+  }
+
+  pub fn with_version(mut self, version: Version) -> MessageBuilder<'a> {
+    match self.line {
+      StartLine::Request(ref mut line) => line.version = version,
+      StartLine::Status(ref mut line) => line.version = version
+    }
+    self
+  }
+
+  // Via below the ones added before, the first one is the topmost
+  pub fn with_via(mut self, value: &'a Binary) -> MessageBuilder<'a> {
+    self.via.push(value);
+    self
+  }
+
+  // Route below the ones added before, the first one is the next hop
+  pub fn with_route(mut self, value: &'a Binary) -> MessageBuilder<'a> {
+    self.route.push(value);
+    self
+  }
+
+  // Via and Route go to their place, Content-Length is dropped since it
+  // comes from the body
+  pub fn with_header(mut self, name: HeaderName<'a>, value: &'a Binary) -> MessageBuilder<'a> {
+    if name == HeaderName::Via {
+      self.via.push(value);
+    } else if name == HeaderName::Route {
+      self.route.push(value);
+    } else if name != HeaderName::ContentLength {
+      self.headers.push(Header { name, value });
+    }
+    self
+  }
+
+  // tag-param appended to the To value when it is written, for the tag
+  // a UAS adds to a request's To
+  pub fn with_to_tag(mut self, tag: &Binary) -> MessageBuilder<'a> {
+    self.to_tag = Some(tag.to_vec());
+    self
+  }
+
+  pub fn with_body(mut self, body: &'a Binary) -> MessageBuilder<'a> {
+    self.body = body;
+    self
+  }
+
+  fn check(&self) -> Result<(), BuildError> {
+    let required = match self.line {
+      StartLine::Request(_) => REQUEST_HEADERS,
+      StartLine::Status(_) => RESPONSE_HEADERS
+    };
+    let missing = required.iter().find(|name| match name {
+      HeaderName::Via => self.via.is_empty(),
+      name => !self.headers.iter().any(|h| h.name == **name)
+    });
+    match missing {
+      Some(name) => Err(BuildError::MissingHeader(*name)),
+      None => Ok(())
+    }
+  }
+
+  pub fn build(&self, buf: &mut Binary) -> Result<usize, BuildError> {
+    self.check()?;
+    let mut out = Output::new(buf);
+    out.with(|b| start_line(self.line, b))?;
+    let via = self.via.iter().map(|value| Header { name: HeaderName::Via, value });
+    let route = self.route.iter().map(|value| Header { name: HeaderName::Route, value });
+    for h in via.chain(route).chain(self.headers.iter().copied()) {
+      match self.to_tag {
+        Some(ref tag) if h.name == HeaderName::To => {
+          out.put(h.name.as_bytes())?;
+          out.put(b": ")?;
+          out.put(h.value)?;
+          out.put(b";tag=")?;
+          out.put(tag)?;
+          out.put(b"\r\n")?;
+        },
+        _ => out.with(|b| header(h, b))?
+      }
+    }
+    out.put(HeaderName::ContentLength.as_bytes())?;
+    out.put(b": ")?;
+    out.with(|b| content_length(self.body.len(), b))?;
+    out.put(b"\r\n\r\n")?;
+    out.put(self.body)?;
+    Ok(out.finish()?)
+  }
+
+  pub fn to_vec(&self) -> Result<Vec<u8>, BuildError> {
+    self.check()?;
+    let guess = self.via.iter().chain(self.route.iter()).map(|v| v.len() + 8).sum::<usize>()
+      + self.headers.iter().map(|h| h.name.as_bytes().len() + h.value.len() + 4).sum::<usize>()
+      + self.to_tag.as_ref().map_or(0, |t| t.len() + 5)
+      + self.body.len() + 128;
+    let mut buf = vec![0u8; guess];
+    loop {
+      match self.build(&mut buf) {
+        Ok(n) => {
+          buf.truncate(n);
+          return Ok(buf);
+        },
+        Err(BuildError::Assemble(AssembleError::NotEnoughOutSpace { .. })) => {
+          let len = buf.len() * 2;
+          buf.resize(len, 0);
+        },
+        Err(e) => return Err(e)
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{MessageBuilder, BuildError};
+  use crate::msg::header::HeaderName;
+  use crate::msg::method::Method;
+  use crate::msg::output::AssembleError;
+  use crate::msg::parse::message;
+  use crate::msg::status::StatusCode;
+  use crate::msg::uri::parse::uri;
+
+  const INVITE: &[u8] = b"INVITE sip:bob@biloxi.com SIP/2.0\r\n\
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bKnashds8\r\n\
+Max-Forwards: 70\r\n\
+To: Bob <sip:bob@biloxi.com>\r\n\
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+Call-ID: a84b4c76e66710\r\n\
+CSeq: 314159 INVITE\r\n\
+Contact: <sip:alice@pc33.atlanta.com>\r\n\
+Content-Type: application/sdp\r\n\
+Content-Length: 4\r\n\
+\r\n\
+v=0\n";
+
+  fn invite(u: &[u8]) -> MessageBuilder<'_> {
+    let (_, u) = uri(u).unwrap();
+    MessageBuilder::request(Method::Invite, u)
+      .with_header(HeaderName::MaxForwards, b"70")
+      .with_header(HeaderName::To, b"Bob <sip:bob@biloxi.com>")
+      .with_header(HeaderName::From, b"Alice <sip:alice@atlanta.com>;tag=1928301774")
+      .with_header(HeaderName::CallId, b"a84b4c76e66710")
+      .with_header(HeaderName::CSeq, b"314159 INVITE")
+      .with_header(HeaderName::Contact, b"<sip:alice@pc33.atlanta.com>")
+      .with_header(HeaderName::Other(b"content-length"), b"99")
+      .with_header(HeaderName::Via, b"SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bKnashds8")
+      .with_header(HeaderName::ContentType, b"application/sdp")
+  }
+
+  #[test]
+  fn request_test() {
+    let builder = invite(b"sip:bob@biloxi.com").with_body(b"v=0\n");
+    assert_eq!(builder.to_vec().unwrap(), INVITE);
+
+    let mut buf = [0u8; 512];
+    let n = builder.build(&mut buf).unwrap();
+    assert_eq!(&buf[..n], INVITE);
+    assert!(matches!(
+      builder.build(&mut buf[..100]),
+      Err(BuildError::Assemble(AssembleError::NotEnoughOutSpace { .. }))
+    ));
+
+    let built = invite(b"sip:bob@biloxi.com")
+      .with_route(b"<sip:p1.example.com;lr>")
+      .with_header(HeaderName::Route, b"<sip:p2.example.com;lr>")
+      .with_via(b"SIP/2.0/UDP second.example.com;branch=z9hG4bK2")
+      .to_vec()
+      .unwrap();
+    let (_, m) = message(&built).unwrap();
+    let names: Vec<_> = m.headers().iter().map(|h| h.name).take(5).collect();
+    assert_eq!(names, [HeaderName::Via, HeaderName::Via, HeaderName::Route, HeaderName::Route, HeaderName::MaxForwards]);
+    assert_eq!(m.headers().iter().nth(1).unwrap().value, b"SIP/2.0/UDP second.example.com;branch=z9hG4bK2");
+    assert_eq!(m.headers().iter().nth(3).unwrap().value, b"<sip:p2.example.com;lr>");
+    assert_eq!(m.body(), b"");
+  }
+
+  #[test]
+  fn response_test() {
+    let built = MessageBuilder::response(StatusCode::RINGING)
+      .with_via(b"SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bKnashds8")
+      .with_header(HeaderName::To, b"Bob <sip:bob@biloxi.com>;tag=a6c85cf")
+      .with_header(HeaderName::From, b"Alice <sip:alice@atlanta.com>;tag=1928301774")
+      .with_header(HeaderName::CallId, b"a84b4c76e66710")
+      .with_header(HeaderName::CSeq, b"314159 INVITE")
+      .to_vec()
+      .unwrap();
+    assert!(built.starts_with(b"SIP/2.0 180 Ringing\r\nVia: "));
+    assert!(built.ends_with(b"CSeq: 314159 INVITE\r\nContent-Length: 0\r\n\r\n"));
+
+    let builder = MessageBuilder::response(StatusCode::new(499).unwrap()).with_reason(b"Odd");
+    assert_eq!(builder.to_vec(), Err(BuildError::MissingHeader(HeaderName::Via)));
+    let builder = builder.with_via(b"SIP/2.0/TCP h").with_header(HeaderName::To, b"<sip:a@b>");
+    assert_eq!(builder.to_vec(), Err(BuildError::MissingHeader(HeaderName::From)));
+
+    let (_, u) = uri(b"sip:bob@biloxi.com").unwrap();
+    let builder = MessageBuilder::request(Method::Options, u);
+    assert_eq!(builder.build(&mut [0u8; 512]), Err(BuildError::MissingHeader(HeaderName::Via)));
+    let builder = invite(b"sip:bob@biloxi.com");
+    assert!(builder.to_vec().is_ok());
+  }
+}
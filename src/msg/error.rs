@@ -0,0 +1,138 @@
+use super::Binary;
+use nom::Err;
+use nom::error::ErrorKind;
+use std::fmt;
+
+// Parse failure for the peer's eyes: the grammar rule which didn't
+// match, named as in the RFC ABNF, and the byte offset into the message
+// where it happened. An incomplete one might have parsed with more
+// input, which on a stream means waiting rather than answering 400.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub struct ParseError {
+  pub rule: &'static str,
+  pub offset: usize,
+  pub incomplete: bool
+}
+
+impl ParseError {
+  pub fn new(rule: &'static str, offset: usize) -> ParseError {
+    ParseError { rule, offset, incomplete: false }
+  }
+
+  // Error of a nom parser run over a slice of `base`. The offset counts
+  // from the start of `base`, so a header value parser given a slice of
+  // the message reports offsets into the message. Incomplete input is
+  // reported at the end of `base`.
+  pub fn from_nom(rule: &'static str, base: &Binary, err: Err<(&Binary, ErrorKind)>) -> ParseError {
+    match err {
+      Err::Error((at, _)) | Err::Failure((at, _)) => ParseError::new(rule, offset_in(base, at)),
+      Err::Incomplete(_) => ParseError { rule, offset: base.len(), incomplete: true }
+    }
+  }
+
+  // Error at `at`, a slice of `base`
+  pub fn at(rule: &'static str, base: &Binary, at: &Binary) -> ParseError {
+    ParseError::new(rule, offset_in(base, at))
+  }
+
+  pub fn needs_more(&self) -> bool {
+    self.incomplete
+  }
+}
+
+// Offset of a slice which points into `base`, its end when it doesn't
+fn offset_in(base: &Binary, at: &Binary) -> usize {
+  let start = base.as_ptr() as usize;
+  let pos = at.as_ptr() as usize;
+  if pos >= start && pos <= start + base.len() {
+    pos - start
+  } else {
+    base.len()
+  }
+}
+
+// Result of a parser which names the rule that failed
+pub type ParseResult<'a, O> = Result<(&'a Binary, O), ParseError>;
+
+// Result of a nom parser with the error named by the rule
+pub fn rule<'a, O>(rule: &'static str, base: &Binary, res: nom::IResult<&'a Binary, O>)
+  -> ParseResult<'a, O> {
+  res.map_err(|e| ParseError::from_nom(rule, base, e))
+}
+
+// "Malformed hostport at byte 12", which is fine for a Reason-Phrase and
+// for the warn-text of Warning
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let what = if self.incomplete { "Incomplete" } else { "Malformed" };
+    write!(f, "{} {} at byte {}", what, self.rule, self.offset)
+  }
+}
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::output::{Output, AssembleResult};
+  use super::ParseError;
+
+  // warn-code for anything which has no code of its own
+  pub const MISCELLANEOUS_WARNING: u16 = 399;
+
+  // Reason-Phrase of a 400 (Bad Request) answering the message
+  pub fn reason_phrase(e: ParseError, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.put_display(e)?;
+    out.finish()
+  }
+
+  // warning-value  =  warn-code SP warn-agent SP warn-text
+  // warn-agent     =  hostport / pseudonym
+  // warn-text      =  quoted-string
+  pub fn warning(e: ParseError, agent: &Binary, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.put_number(MISCELLANEOUS_WARNING as u64)?;
+    out.put_byte(b' ')?;
+    out.put(agent)?;
+    out.put(b" \"")?;
+    out.put_display(e)?;
+    out.put_byte(b'"')?;
+    out.finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{ParseError, rule, assemble};
+  use crate::msg::abnf::digit::decimal;
+  use nom::{Err::{Error, Incomplete}, Needed, error::ErrorKind::Digit};
+
+  #[test]
+  fn parse_error_test() {
+    let message = b"CSeq: x INVITE\r\n";
+    let value = &message[6..14];
+    assert_eq!(rule("CSeq", message, decimal(value, 10)), Err(ParseError::new("CSeq", 6)));
+    assert_eq!(rule("CSeq", message, decimal(b"1", 10)), Ok((&b""[..], 1)));
+
+    let e = ParseError::from_nom("message-body", message, Incomplete(Needed::Size(2)));
+    assert!(e.needs_more());
+    assert_eq!(e.offset, message.len());
+    assert_eq!(e.to_string(), "Incomplete message-body at byte 16");
+
+    // a slice which isn't part of the base
+    let e = ParseError::from_nom("hostport", message, Error((&b"x"[..], Digit)));
+    assert_eq!((e.offset, e.needs_more()), (message.len(), false));
+
+    let e = ParseError::at("CSeq", message, value);
+    assert_eq!(e, ParseError::new("CSeq", 6));
+  }
+
+  #[test]
+  fn parse_error_assemble_test() {
+    let mut buf = [0u8; 64];
+    let e = ParseError::new("hostport", 12);
+    let n = assemble::reason_phrase(e, &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"Malformed hostport at byte 12");
+    let n = assemble::warning(e, b"proxy.example.com:5060", &mut buf).unwrap();
+    assert_eq!(&buf[..n], &b"399 proxy.example.com:5060 \"Malformed hostport at byte 12\""[..]);
+    assert!(assemble::warning(e, b"proxy.example.com:5060", &mut buf[..40]).is_err());
+  }
+}
@@ -0,0 +1,323 @@
+use super::Binary;
+use super::error::{ParseError, rule};
+use super::header::parse::headers;
+use super::message::parse::checked_content_length;
+use super::start_line::parse::checked_start_line;
+use super::version::parse::version;
+
+// What comes out of a stream, RFC 3261 18.3 and RFC 5626 3.5.1
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Frame<'a> {
+  // whole message including the body, ready for msg::parse::message
+  Message(&'a Binary),
+  // CRLFCRLF keep-alive, to be answered with a single CRLF
+  Ping,
+  // CRLF answering a ping
+  Pong
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Error {
+  // stream transports need Content-Length to find the end of the body
+  MissingContentLength,
+  // the message is or is going to be larger than the limit
+  TooLarge,
+  // start line, a header or Content-Length doesn't parse
+  Malformed(ParseError)
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+enum State {
+  // nothing of the next frame is known, it may be a keep-alive
+  Start,
+  // header section doesn't end before `scanned`
+  Head { scanned: usize },
+  // message takes `len` bytes
+  Body { len: usize },
+  Failed(Error)
+}
+
+// Sans-IO framer of a stream connection. Bytes read from the socket are
+// pushed in as they come, poll() then yields frames until it needs more.
+// The buffer is only searched for the end of the header section where
+// it wasn't searched before, the header section is parsed once and the
+// body is not looked at at all. Once an error is returned the stream
+// can't be synchronized again and the connection should be closed.
+#[derive(Debug)]
+pub struct Framer {
+  buf: Vec<u8>,
+  // bytes of the frame returned last, dropped on the next call
+  consumed: usize,
+  max_size: usize,
+  state: State
+}
+
+impl Default for Framer {
+  fn default() -> Framer {
+    Framer::new()
+  }
+}
+
+impl Framer {
+  pub const DEFAULT_MAX_SIZE: usize = 65536;
+
+  pub fn new() -> Framer {
+    Framer {
+      buf: Vec::new(),
+      consumed: 0,
+      max_size: Self::DEFAULT_MAX_SIZE,
+      state: State::Start
+    }
+  }
+
+  // Largest message accepted, header section and body together
+  pub fn with_max_size(mut self, max_size: usize) -> Framer {
+    self.max_size = max_size;
+    self
+  }
+
+  // bytes received but not returned in a frame yet
+  pub fn buffered(&self) -> &Binary {
+    &self.buf[self.consumed..]
+  }
+
+  pub fn push(&mut self, data: &Binary) {
+    self.compact();
+    self.buf.extend_from_slice(data);
+  }
+
+  fn compact(&mut self) {
+    if self.consumed > 0 {
+      self.buf.drain(..self.consumed);
+      self.consumed = 0;
+    }
+  }
+
+  fn fail(&mut self, e: Error) -> Result<Option<Frame<'_>>, Error> {
+    self.state = State::Failed(e);
+    Err(e)
+  }
+
+  // Next frame, None when more bytes are needed. CR and LF in front of
+  // a frame are held back until they either make a CRLFCRLF ping or can
+  // no longer become one, only then a CRLF is a pong. So a pong is
+  // reported with the first byte which follows it.
+  pub fn poll(&mut self) -> Result<Option<Frame<'_>>, Error> {
+    self.compact();
+    loop {
+      match self.state {
+        State::Failed(e) => return Err(e),
+        State::Start => {
+          let (frame, len) = match keep_alive(&self.buf) {
+            KeepAlive::Undecided => return Ok(None),
+            KeepAlive::Ping => (Frame::Ping, 4),
+            KeepAlive::Pong => (Frame::Pong, 2),
+            KeepAlive::None => {
+              self.state = State::Head { scanned: 0 };
+              continue;
+            }
+          };
+          self.consumed = len;
+          return Ok(Some(frame));
+        },
+        State::Head { scanned } => {
+          let from = scanned.saturating_sub(3);
+          let end = match self.buf[from..].windows(4).position(|w| w == b"\r\n\r\n") {
+            Some(p) => from + p + 4,
+            None => {
+              if self.buf.len() > self.max_size {
+                return self.fail(Error::TooLarge);
+              }
+              self.state = State::Head { scanned: self.buf.len() };
+              return Ok(None);
+            }
+          };
+          if end > self.max_size {
+            return self.fail(Error::TooLarge);
+          }
+          match self.body_length(end) {
+            Ok(len) if end + len > self.max_size => return self.fail(Error::TooLarge),
+            Ok(len) => self.state = State::Body { len: end + len },
+            Err(e) => return self.fail(e)
+          }
+        },
+        State::Body { len } => {
+          if self.buf.len() < len {
+            return Ok(None);
+          }
+          self.state = State::Start;
+          self.consumed = len;
+          return Ok(Some(Frame::Message(&self.buf[..len])));
+        }
+      }
+    }
+  }
+
+  // Content-Length of a message whose header section ends at `end`
+  fn body_length(&self, end: usize) -> Result<usize, Error> {
+    let head = &self.buf[..end];
+    let parsed = checked_start_line(head, version)
+      .and_then(|(rest, _)| rule("message-header", head, headers(rest)))
+      .and_then(|(_, headers)| checked_content_length(head, headers));
+    match parsed {
+      Ok(Some(len)) => Ok(len),
+      Ok(None) => Err(Error::MissingContentLength),
+      Err(e) => Err(Error::Malformed(e))
+    }
+  }
+}
+
+enum KeepAlive {
+  Ping,
+  Pong,
+  // a prefix of CRLFCRLF, which may still turn out either way
+  Undecided,
+  None
+}
+
+fn keep_alive(buf: &Binary) -> KeepAlive {
+  const PING: &Binary = b"\r\n\r\n";
+  if buf.starts_with(PING) {
+    KeepAlive::Ping
+  } else if PING.starts_with(buf) {
+    KeepAlive::Undecided
+  } else if buf.starts_with(b"\r\n") {
+    KeepAlive::Pong
+  } else {
+    KeepAlive::None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Framer, Frame, Error};
+  use crate::msg::error::ParseError;
+  use crate::msg::parse::message;
+
+  const OPTIONS: &[u8] = b"OPTIONS sip:carol@chicago.com SIP/2.0\r\n\
+Via: SIP/2.0/TCP pc33.atlanta.com;branch=z9hG4bKhjhs8ass877\r\n\
+Call-ID: a84b4c76e66710\r\n\
+CSeq: 63104 OPTIONS\r\n\
+l: 5\r\n\
+\r\n\
+hello";
+
+  #[test]
+  fn framer_test() {
+    let mut framer = Framer::new();
+    assert_eq!(framer.poll(), Ok(None));
+
+    // a message in pieces, then two at once with keep-alives around
+    for chunk in OPTIONS.chunks(7) {
+      assert_eq!(framer.poll(), Ok(None));
+      framer.push(chunk);
+    }
+    assert_eq!(framer.poll(), Ok(Some(Frame::Message(OPTIONS))));
+    assert_eq!(framer.poll(), Ok(None));
+
+    let mut stream = b"\r\n\r\n".to_vec();
+    stream.extend_from_slice(OPTIONS);
+    stream.extend_from_slice(b"\r\n");
+    stream.extend_from_slice(OPTIONS);
+    framer.push(&stream[..stream.len() - 1]);
+    assert_eq!(framer.poll(), Ok(Some(Frame::Ping)));
+    match framer.poll() {
+      Ok(Some(Frame::Message(m))) => assert_eq!(message(m).unwrap().1.body(), b"hello"),
+      other => panic!("message expected, got {:?}", other)
+    }
+    assert_eq!(framer.poll(), Ok(Some(Frame::Pong)));
+    assert_eq!(framer.poll(), Ok(None));
+    assert_eq!(framer.buffered(), &OPTIONS[..OPTIONS.len() - 1]);
+    framer.push(b"o\r\n\r");
+    assert_eq!(framer.poll(), Ok(Some(Frame::Message(OPTIONS))));
+    assert_eq!(framer.poll(), Ok(None));
+    framer.push(b"\n");
+    assert_eq!(framer.poll(), Ok(Some(Frame::Ping)));
+    assert_eq!(framer.poll(), Ok(None));
+    assert!(framer.buffered().is_empty());
+  }
+
+  // every frame the framer yields for `stream` pushed in `size` pieces
+  fn frames(stream: &[u8], size: usize) -> Vec<Vec<u8>> {
+    let mut framer = Framer::new();
+    let mut out = Vec::new();
+    for chunk in stream.chunks(size) {
+      framer.push(chunk);
+      while let Some(frame) = framer.poll().unwrap() {
+        out.push(match frame {
+          Frame::Message(m) => m.to_vec(),
+          Frame::Ping => b"ping".to_vec(),
+          Frame::Pong => b"pong".to_vec()
+        });
+      }
+    }
+    out
+  }
+
+  #[test]
+  fn keep_alive_test() {
+    // ping split anywhere is still one ping
+    let mut framer = Framer::new();
+    framer.push(b"\r");
+    assert_eq!(framer.poll(), Ok(None));
+    framer.push(b"\n\r\n");
+    assert_eq!(framer.poll(), Ok(Some(Frame::Ping)));
+    assert_eq!(framer.poll(), Ok(None));
+
+    let mut framer = Framer::new();
+    framer.push(b"\r\n");
+    assert_eq!(framer.poll(), Ok(None));
+    framer.push(b"\r\n");
+    assert_eq!(framer.poll(), Ok(Some(Frame::Ping)));
+
+    // a pong is known once the byte after it can't make a ping
+    let mut framer = Framer::new();
+    framer.push(b"\r\n\r");
+    assert_eq!(framer.poll(), Ok(None));
+    framer.push(b"O");
+    assert_eq!(framer.poll(), Ok(Some(Frame::Pong)));
+    assert_eq!(framer.buffered(), b"\rO");
+
+    let mut stream = b"\r\n\r\n".to_vec();
+    stream.extend_from_slice(OPTIONS);
+    stream.extend_from_slice(b"\r\n");
+    stream.extend_from_slice(OPTIONS);
+    stream.extend_from_slice(b"\r\n\r\n\r\n");
+    stream.extend_from_slice(OPTIONS);
+    let expected = vec![
+      b"ping".to_vec(), OPTIONS.to_vec(), b"pong".to_vec(), OPTIONS.to_vec(),
+      b"ping".to_vec(), b"pong".to_vec(), OPTIONS.to_vec()
+    ];
+    for size in 1..6 {
+      assert_eq!(frames(&stream, size), expected);
+    }
+  }
+
+  #[test]
+  fn framer_error_test() {
+    let mut framer = Framer::new();
+    framer.push(b"SIP/2.0 200 OK\r\nCSeq: 1 OPTIONS\r\n\r\n");
+    assert_eq!(framer.poll(), Err(Error::MissingContentLength));
+    framer.push(b"\r\n\r\n");
+    assert_eq!(framer.poll(), Err(Error::MissingContentLength));
+
+    let mut framer = Framer::new();
+    framer.push(b"SIP/2.0 200 OK\r\nl: x\r\n\r\n");
+    assert_eq!(framer.poll(), Err(Error::Malformed(ParseError::new("Content-Length", 19))));
+
+    let mut framer = Framer::new();
+    framer.push(b"GARBAGE\r\n\r\n");
+    assert_eq!(framer.poll(), Err(Error::Malformed(ParseError::new("Method", 7))));
+
+    let mut framer = Framer::new().with_max_size(OPTIONS.len() - 1);
+    framer.push(OPTIONS);
+    assert_eq!(framer.poll(), Err(Error::TooLarge));
+
+    // header section which never ends
+    let mut framer = Framer::new().with_max_size(64);
+    framer.push(&OPTIONS[..60]);
+    assert_eq!(framer.poll(), Ok(None));
+    framer.push(&OPTIONS[60..70]);
+    assert_eq!(framer.poll(), Err(Error::TooLarge));
+  }
+}
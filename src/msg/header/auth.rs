@@ -0,0 +1,980 @@
+use crate::msg::Binary;
+use crate::msg::abnf::whole;
+use crate::msg::abnf::userinfo::unquote;
+use crate::msg::header::{Headers, HeaderName};
+use std::borrow::Cow;
+
+// auth-param        =  auth-param-name EQUAL
+//                      ( token / quoted-string )
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum AuthValue<'a> {
+  Token(&'a Binary),
+  // content of the quoted-string, quoted-pairs are kept as they are
+  Quoted(&'a Binary)
+}
+
+impl<'a> AuthValue<'a> {
+  pub fn as_bytes(&self) -> &'a Binary {
+    match self {
+      AuthValue::Token(value) => value,
+      AuthValue::Quoted(value) => value
+    }
+  }
+
+  pub fn unescaped(&self) -> Cow<'a, Binary> {
+    match *self {
+      AuthValue::Token(value) => Cow::Borrowed(value),
+      AuthValue::Quoted(value) => unquote(value)
+    }
+  }
+}
+
+// auth-param *(COMMA auth-param) which was already validated by the
+// parser, kept as the original slice
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub struct AuthParams<'a>(&'a Binary);
+
+impl<'a> AuthParams<'a> {
+  pub fn as_bytes(&self) -> &'a Binary {
+    self.0
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  pub fn iter(&self) -> AuthParamsIter<'a> {
+    AuthParamsIter { rest: self.0, first: true }
+  }
+
+  // Value of the first parameter with the name, names are
+  // case-insensitive
+  pub fn get(&self, name: &Binary) -> Option<AuthValue<'a>> {
+    self.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v)
+  }
+}
+
+impl<'a> IntoIterator for AuthParams<'a> {
+  type Item = (&'a Binary, AuthValue<'a>);
+  type IntoIter = AuthParamsIter<'a>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+pub struct AuthParamsIter<'a> {
+  rest: &'a Binary,
+  first: bool
+}
+
+impl<'a> Iterator for AuthParamsIter<'a> {
+  type Item = (&'a Binary, AuthValue<'a>);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let rest = if self.first {
+      self.rest
+    } else {
+      parse::comma(self.rest).ok()?.0
+    };
+    let (r, param) = parse::auth_param(rest).ok()?;
+    self.first = false;
+    self.rest = r;
+    Some(param)
+  }
+}
+
+// algorithm  =  "algorithm" EQUAL ( "MD5" / "MD5-sess" / token )
+// RFC 8760 adds SHA-256 and SHA-512-256 together with their session
+// variants. Names are case-insensitive.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Algorithm<'a> {
+  Md5,
+  Md5Sess,
+  Sha256,
+  Sha256Sess,
+  Sha512_256,
+  Sha512_256Sess,
+  Other(&'a Binary)
+}
+
+impl<'a> Algorithm<'a> {
+  pub fn from_bytes(name: &'a Binary) -> Algorithm<'a> {
+    let known = [
+      Algorithm::Md5, Algorithm::Md5Sess,
+      Algorithm::Sha256, Algorithm::Sha256Sess,
+      Algorithm::Sha512_256, Algorithm::Sha512_256Sess
+    ];
+    known.iter()
+      .find(|a| a.as_bytes().eq_ignore_ascii_case(name))
+      .copied()
+      .unwrap_or(Algorithm::Other(name))
+  }
+
+  pub fn as_bytes(&self) -> &'a Binary {
+    match self {
+      Algorithm::Md5 => b"MD5",
+      Algorithm::Md5Sess => b"MD5-sess",
+      Algorithm::Sha256 => b"SHA-256",
+      Algorithm::Sha256Sess => b"SHA-256-sess",
+      Algorithm::Sha512_256 => b"SHA-512-256",
+      Algorithm::Sha512_256Sess => b"SHA-512-256-sess",
+      Algorithm::Other(name) => name
+    }
+  }
+
+  // A1 of the -sess variants includes the nonce and cnonce
+  pub fn is_session(&self) -> bool {
+    matches!(self, Algorithm::Md5Sess | Algorithm::Sha256Sess | Algorithm::Sha512_256Sess)
+  }
+}
+
+// qop-value  =  "auth" / "auth-int" / token
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Qop<'a> {
+  Auth,
+  AuthInt,
+  Other(&'a Binary)
+}
+
+impl<'a> Qop<'a> {
+  pub fn from_bytes(value: &'a Binary) -> Qop<'a> {
+    if value.eq_ignore_ascii_case(b"auth") {
+      Qop::Auth
+    } else if value.eq_ignore_ascii_case(b"auth-int") {
+      Qop::AuthInt
+    } else {
+      Qop::Other(value)
+    }
+  }
+
+  pub fn as_bytes(&self) -> &'a Binary {
+    match self {
+      Qop::Auth => b"auth",
+      Qop::AuthInt => b"auth-int",
+      Qop::Other(value) => value
+    }
+  }
+}
+
+// qop-options  =  "qop" EQUAL LDQUOT qop-value
+//                 *("," qop-value) RDQUOT
+// Content between the quotes, qop values are parsed again on iteration.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct QopOptions<'a>(&'a Binary);
+
+impl<'a> QopOptions<'a> {
+  pub fn new(input: &'a Binary) -> Option<QopOptions<'a>> {
+    whole(parse::qop_options(input))
+  }
+
+  pub fn as_bytes(&self) -> &'a Binary {
+    self.0
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = Qop<'a>> {
+    self.0
+      .split(|b| *b == b',')
+      .map(|v| {
+        let start = v.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(v.len());
+        let end = v.iter().rposition(|b| !b.is_ascii_whitespace()).map_or(start, |i| i + 1);
+        Qop::from_bytes(&v[start..end])
+      })
+  }
+
+  pub fn contains(&self, qop: Qop) -> bool {
+    self.iter().any(|q| q == qop)
+  }
+}
+
+// challenge  =  ("Digest" LWS digest-cln *(COMMA digest-cln))
+// digest-cln =  realm / domain / nonce / opaque / stale / algorithm
+//               / qop-options / auth-param
+// Quoted values are kept as their content with quoted-pairs, quoting
+// of the known parameters isn't enforced since implementations get it
+// wrong both ways. `params` is every parameter of the header.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct DigestChallenge<'a> {
+  pub realm: &'a Binary,
+  pub domain: Option<&'a Binary>,
+  pub nonce: &'a Binary,
+  pub opaque: Option<&'a Binary>,
+  pub stale: bool,
+  pub algorithm: Option<Algorithm<'a>>,
+  pub qop: Option<QopOptions<'a>>,
+  pub params: AuthParams<'a>
+}
+
+impl<'a> DigestChallenge<'a> {
+  pub fn new(realm: &'a Binary, nonce: &'a Binary) -> DigestChallenge<'a> {
+    DigestChallenge {
+      realm,
+      domain: None,
+      nonce,
+      opaque: None,
+      stale: false,
+      algorithm: None,
+      qop: None,
+      params: AuthParams::default()
+    }
+  }
+}
+
+// WWW-Authenticate  =  "WWW-Authenticate" HCOLON challenge
+// Proxy-Authenticate  =  "Proxy-Authenticate" HCOLON challenge
+// other-challenge   =  auth-scheme LWS auth-param *(COMMA auth-param)
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Challenge<'a> {
+  Digest(DigestChallenge<'a>),
+  Other { scheme: &'a Binary, params: AuthParams<'a> }
+}
+
+impl<'a> Challenge<'a> {
+  pub fn scheme(&self) -> &'a Binary {
+    match self {
+      Challenge::Digest(_) => b"Digest",
+      Challenge::Other { scheme, .. } => scheme
+    }
+  }
+}
+
+// digest-response  =  dig-resp *(COMMA dig-resp)
+// dig-resp         =  username / realm / nonce / digest-uri
+//                     / dresponse / algorithm / cnonce
+//                     / opaque / message-qop
+//                     / nonce-count / auth-param
+// Values the same way as in DigestChallenge, `uri` is the
+// digest-uri-value as it was sent.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct DigestResponse<'a> {
+  pub username: &'a Binary,
+  pub realm: &'a Binary,
+  pub nonce: &'a Binary,
+  pub uri: &'a Binary,
+  pub response: &'a Binary,
+  pub algorithm: Option<Algorithm<'a>>,
+  pub cnonce: Option<&'a Binary>,
+  pub opaque: Option<&'a Binary>,
+  pub qop: Option<Qop<'a>>,
+  pub nc: Option<u32>,
+  pub params: AuthParams<'a>
+}
+
+// Authorization  =  "Authorization" HCOLON credentials
+// Proxy-Authorization  =  "Proxy-Authorization" HCOLON credentials
+// credentials    =  ("Digest" LWS digest-response)
+//                   / other-response
+// other-response =  auth-scheme LWS auth-param *(COMMA auth-param)
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Credentials<'a> {
+  Digest(DigestResponse<'a>),
+  Other { scheme: &'a Binary, params: AuthParams<'a> }
+}
+
+impl<'a> Credentials<'a> {
+  pub fn scheme(&self) -> &'a Binary {
+    match self {
+      Credentials::Digest(_) => b"Digest",
+      Credentials::Other { scheme, .. } => scheme
+    }
+  }
+}
+
+// ainfo  =  nextnonce / message-qop / response-auth / cnonce
+//           / nonce-count
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub struct AuthenticationInfo<'a> {
+  pub nextnonce: Option<&'a Binary>,
+  pub qop: Option<Qop<'a>>,
+  pub rspauth: Option<&'a Binary>,
+  pub cnonce: Option<&'a Binary>,
+  pub nc: Option<u32>,
+  pub params: AuthParams<'a>
+}
+
+// Challenges of the WWW-Authenticate or Proxy-Authenticate headers of
+// a response. Headers which don't parse are skipped, so a client goes
+// on with the challenges it understands.
+pub fn challenges<'a, 'n>(headers: Headers<'a>, name: HeaderName<'n>) -> impl Iterator<Item = Challenge<'a>> + 'n
+  where 'a: 'n {
+  headers.get_all(name).filter_map(|h| whole(parse::challenge(h.value)))
+}
+
+// Credentials of the Authorization or Proxy-Authorization headers of
+// a request, malformed ones are skipped
+pub fn credentials<'a, 'n>(headers: Headers<'a>, name: HeaderName<'n>) -> impl Iterator<Item = Credentials<'a>> + 'n
+  where 'a: 'n {
+  headers.get_all(name).filter_map(|h| whole(parse::credentials(h.value)))
+}
+
+pub mod parse {
+  use crate::msg::Binary;
+  use crate::msg::abnf::whole;
+  use crate::msg::abnf::ch::{lws, equal, value_token};
+  use crate::msg::abnf::userinfo::quoted_string;
+  use super::{
+    AuthValue, AuthParams, Algorithm, Qop, QopOptions,
+    DigestChallenge, Challenge, DigestResponse, Credentials, AuthenticationInfo
+  };
+  use nom::IResult;
+  use nom::Err::Error;
+  use nom::error::ErrorKind::Verify;
+
+  pub use crate::msg::abnf::ch::comma;
+
+  // auth-param        =  auth-param-name EQUAL
+  //                      ( token / quoted-string )
+  // auth-param-name   =  token
+  #[inline]
+  pub fn auth_param(input: &Binary) -> IResult<&Binary, (&Binary, AuthValue<'_>)> {
+    let (rest, name) = value_token(input)?;
+    let (rest, _) = equal(rest)?;
+    if rest.first() == Some(&b'"') {
+      let (rest, value) = quoted_string(rest)?;
+      Ok((rest, (name, AuthValue::Quoted(value))))
+    } else {
+      let (rest, value) = value_token(rest)?;
+      Ok((rest, (name, AuthValue::Token(value))))
+    }
+  }
+
+  // auth-param *(COMMA auth-param)
+  #[inline]
+  pub fn auth_params(input: &Binary) -> IResult<&Binary, AuthParams<'_>> {
+    let (mut rest, _) = auth_param(input)?;
+    while let Ok((r, _)) = comma(rest) {
+      let (r, _) = auth_param(r)?;
+      rest = r;
+    }
+    Ok((rest, AuthParams(&input[..input.len() - rest.len()])))
+  }
+
+  // qop-value *("," qop-value), whitespace around the commas is taken
+  #[inline]
+  pub fn qop_options(input: &Binary) -> IResult<&Binary, QopOptions<'_>> {
+    let (mut rest, _) = value_token(input)?;
+    while let Ok((r, _)) = comma(rest) {
+      let (r, _) = value_token(r)?;
+      rest = r;
+    }
+    Ok((rest, QopOptions(&input[..input.len() - rest.len()])))
+  }
+
+  // nc-value  =  8LHEX
+  // Upper case digits are taken as well.
+  #[inline]
+  pub fn nc_value(input: &Binary) -> IResult<&Binary, u32> {
+    let len = input.iter().take(8).take_while(|b| b.is_ascii_hexdigit()).count();
+    if len != 8 {
+      return Err(Error((input, Verify)));
+    }
+    let value = input[..8].iter().fold(0u32, |n, b| (n << 4) | (*b as char).to_digit(16).unwrap_or(0));
+    Ok((&input[8..], value))
+  }
+
+  // scheme LWS auth-param *(COMMA auth-param)
+  #[inline]
+  fn scheme_params(input: &Binary) -> IResult<&Binary, (&Binary, AuthParams<'_>)> {
+    let (rest, scheme) = value_token(input)?;
+    let (rest, _) = lws(rest)?;
+    let (rest, params) = auth_params(rest)?;
+    Ok((rest, (scheme, params)))
+  }
+
+  #[inline]
+  fn text<'a>(params: AuthParams<'a>, name: &Binary) -> Option<&'a Binary> {
+    params.get(name).map(|v| v.as_bytes())
+  }
+
+  fn stale(params: AuthParams) -> Option<bool> {
+    match text(params, b"stale") {
+      None => Some(false),
+      Some(v) if v.eq_ignore_ascii_case(b"true") => Some(true),
+      Some(v) if v.eq_ignore_ascii_case(b"false") => Some(false),
+      Some(_) => None
+    }
+  }
+
+  fn nc(params: AuthParams) -> Option<Option<u32>> {
+    match text(params, b"nc") {
+      None => Some(None),
+      Some(v) => whole(nc_value(v)).map(Some)
+    }
+  }
+
+  // response-digest  =  LDQUOT *LHEX RDQUOT
+  fn hex(value: &Binary) -> Option<&Binary> {
+    if value.iter().all(u8::is_ascii_hexdigit) { Some(value) } else { None }
+  }
+
+  fn digest_challenge(params: AuthParams<'_>) -> Option<DigestChallenge<'_>> {
+    let qop = match text(params, b"qop") {
+      None => None,
+      Some(v) => Some(QopOptions::new(v)?)
+    };
+    Some(DigestChallenge {
+      realm: text(params, b"realm")?,
+      domain: text(params, b"domain"),
+      nonce: text(params, b"nonce")?,
+      opaque: text(params, b"opaque"),
+      stale: stale(params)?,
+      algorithm: text(params, b"algorithm").map(Algorithm::from_bytes),
+      qop,
+      params
+    })
+  }
+
+  fn digest_response(params: AuthParams<'_>) -> Option<DigestResponse<'_>> {
+    Some(DigestResponse {
+      username: text(params, b"username")?,
+      realm: text(params, b"realm")?,
+      nonce: text(params, b"nonce")?,
+      uri: text(params, b"uri")?,
+      response: hex(text(params, b"response")?)?,
+      algorithm: text(params, b"algorithm").map(Algorithm::from_bytes),
+      cnonce: text(params, b"cnonce"),
+      opaque: text(params, b"opaque"),
+      qop: text(params, b"qop").map(Qop::from_bytes),
+      nc: nc(params)?,
+      params
+    })
+  }
+
+  // challenge  =  ("Digest" LWS digest-cln *(COMMA digest-cln))
+  //               / other-challenge
+  // Digest requires realm and nonce, other schemes are kept as they are.
+  #[inline]
+  pub fn challenge(input: &Binary) -> IResult<&Binary, Challenge<'_>> {
+    let (rest, (scheme, params)) = scheme_params(input)?;
+    if !scheme.eq_ignore_ascii_case(b"Digest") {
+      return Ok((rest, Challenge::Other { scheme, params }));
+    }
+    match digest_challenge(params) {
+      Some(c) => Ok((rest, Challenge::Digest(c))),
+      None => Err(Error((input, Verify)))
+    }
+  }
+
+  // credentials  =  ("Digest" LWS digest-response) / other-response
+  // Digest requires username, realm, nonce, uri and response.
+  #[inline]
+  pub fn credentials(input: &Binary) -> IResult<&Binary, Credentials<'_>> {
+    let (rest, (scheme, params)) = scheme_params(input)?;
+    if !scheme.eq_ignore_ascii_case(b"Digest") {
+      return Ok((rest, Credentials::Other { scheme, params }));
+    }
+    match digest_response(params) {
+      Some(c) => Ok((rest, Credentials::Digest(c))),
+      None => Err(Error((input, Verify)))
+    }
+  }
+
+  // Authentication-Info  =  "Authentication-Info" HCOLON ainfo
+  //                         *(COMMA ainfo)
+  #[inline]
+  pub fn authentication_info(input: &Binary) -> IResult<&Binary, AuthenticationInfo<'_>> {
+    let (rest, params) = auth_params(input)?;
+    let info = nc(params).and_then(|nc| Some(AuthenticationInfo {
+      nextnonce: text(params, b"nextnonce"),
+      qop: text(params, b"qop").map(Qop::from_bytes),
+      rspauth: match text(params, b"rspauth") {
+        Some(v) => Some(hex(v)?),
+        None => None
+      },
+      cnonce: text(params, b"cnonce"),
+      nc,
+      params
+    }));
+    match info {
+      Some(info) => Ok((rest, info)),
+      None => Err(Error((input, Verify)))
+    }
+  }
+}
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::output::{Output, AssembleError, AssembleResult};
+  use super::{AuthValue, AuthParams, Challenge, Credentials, AuthenticationInfo};
+
+  const CHALLENGE_PARAMS: [&[u8]; 7] = [b"realm", b"domain", b"nonce", b"opaque", b"stale", b"algorithm", b"qop"];
+  const RESPONSE_PARAMS: [&[u8]; 10] = [
+    b"username", b"realm", b"nonce", b"uri", b"response", b"algorithm", b"cnonce", b"opaque", b"qop", b"nc"
+  ];
+  const INFO_PARAMS: [&[u8]; 5] = [b"nextnonce", b"qop", b"rspauth", b"cnonce", b"nc"];
+
+  // Writes auth-params separated by COMMA
+  struct Params<'o, 'b> {
+    out: &'o mut Output<'b>,
+    first: bool
+  }
+
+  impl<'o, 'b> Params<'o, 'b> {
+    fn new(out: &'o mut Output<'b>) -> Params<'o, 'b> {
+      Params { out, first: true }
+    }
+
+    fn name(&mut self, name: &Binary) -> Result<(), AssembleError> {
+      if !self.first {
+        self.out.put(b", ")?;
+      }
+      self.first = false;
+      self.out.put(name)?;
+      self.out.put_byte(b'=')
+    }
+
+    fn token(&mut self, name: &Binary, value: &Binary) -> Result<(), AssembleError> {
+      self.name(name)?;
+      self.out.put(value)
+    }
+
+    // value is the content of the quoted-string with its quoted-pairs
+    fn quoted(&mut self, name: &Binary, value: &Binary) -> Result<(), AssembleError> {
+      self.name(name)?;
+      self.out.put_byte(b'"')?;
+      self.out.put(value)?;
+      self.out.put_byte(b'"')
+    }
+
+    fn nc(&mut self, nc: u32) -> Result<(), AssembleError> {
+      self.name(b"nc")?;
+      self.out.put_display(format_args!("{:08x}", nc))
+    }
+
+    // parameters other than the typed ones, as they were parsed
+    fn extensions(&mut self, params: AuthParams, known: &[&[u8]]) -> Result<(), AssembleError> {
+      for (name, value) in params {
+        if known.iter().any(|k| k.eq_ignore_ascii_case(name)) {
+          continue;
+        }
+        match value {
+          AuthValue::Token(v) => self.token(name, v)?,
+          AuthValue::Quoted(v) => self.quoted(name, v)?
+        }
+      }
+      Ok(())
+    }
+  }
+
+  pub fn auth_params(params: AuthParams, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    Params::new(&mut out).extensions(params, &[])?;
+    out.finish()
+  }
+
+  pub fn challenge(c: Challenge, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.put(c.scheme())?;
+    out.put_byte(b' ')?;
+    let mut p = Params::new(&mut out);
+    match c {
+      Challenge::Digest(d) => {
+        p.quoted(b"realm", d.realm)?;
+        if let Some(domain) = d.domain {
+          p.quoted(b"domain", domain)?;
+        }
+        p.quoted(b"nonce", d.nonce)?;
+        if let Some(opaque) = d.opaque {
+          p.quoted(b"opaque", opaque)?;
+        }
+        if d.stale {
+          p.token(b"stale", b"true")?;
+        }
+        if let Some(algorithm) = d.algorithm {
+          p.token(b"algorithm", algorithm.as_bytes())?;
+        }
+        if let Some(qop) = d.qop {
+          p.quoted(b"qop", qop.as_bytes())?;
+        }
+        p.extensions(d.params, &CHALLENGE_PARAMS)?;
+      },
+      Challenge::Other { params, .. } => p.extensions(params, &[])?
+    }
+    out.finish()
+  }
+
+  pub fn credentials(c: Credentials, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.put(c.scheme())?;
+    out.put_byte(b' ')?;
+    let mut p = Params::new(&mut out);
+    match c {
+      Credentials::Digest(d) => {
+        p.quoted(b"username", d.username)?;
+        p.quoted(b"realm", d.realm)?;
+        p.quoted(b"nonce", d.nonce)?;
+        p.quoted(b"uri", d.uri)?;
+        p.quoted(b"response", d.response)?;
+        if let Some(algorithm) = d.algorithm {
+          p.token(b"algorithm", algorithm.as_bytes())?;
+        }
+        if let Some(cnonce) = d.cnonce {
+          p.quoted(b"cnonce", cnonce)?;
+        }
+        if let Some(opaque) = d.opaque {
+          p.quoted(b"opaque", opaque)?;
+        }
+        if let Some(qop) = d.qop {
+          p.token(b"qop", qop.as_bytes())?;
+        }
+        if let Some(nc) = d.nc {
+          p.nc(nc)?;
+        }
+        p.extensions(d.params, &RESPONSE_PARAMS)?;
+      },
+      Credentials::Other { params, .. } => p.extensions(params, &[])?
+    }
+    out.finish()
+  }
+
+  pub fn authentication_info(info: AuthenticationInfo, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    let mut p = Params::new(&mut out);
+    if let Some(nextnonce) = info.nextnonce {
+      p.quoted(b"nextnonce", nextnonce)?;
+    }
+    if let Some(qop) = info.qop {
+      p.token(b"qop", qop.as_bytes())?;
+    }
+    if let Some(rspauth) = info.rspauth {
+      p.quoted(b"rspauth", rspauth)?;
+    }
+    if let Some(cnonce) = info.cnonce {
+      p.quoted(b"cnonce", cnonce)?;
+    }
+    if let Some(nc) = info.nc {
+      p.nc(nc)?;
+    }
+    p.extensions(info.params, &INFO_PARAMS)?;
+    out.finish()
+  }
+}
+
+// Challenges outlive the response they came with, the client answers
+// every following request out of them
+pub mod owned {
+  use crate::msg::Binary;
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash, Default)]
+  pub struct AuthParams(Vec<u8>);
+
+  impl AuthParams {
+    pub fn as_bytes(&self) -> &Binary {
+      &self.0
+    }
+
+    pub fn as_borrowed(&self) -> super::AuthParams<'_> {
+      super::AuthParams(&self.0)
+    }
+  }
+
+  impl<'a> super::AuthParams<'a> {
+    pub fn into_owned(self) -> AuthParams {
+      AuthParams(self.0.to_vec())
+    }
+  }
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub enum Algorithm {
+    Md5,
+    Md5Sess,
+    Sha256,
+    Sha256Sess,
+    Sha512_256,
+    Sha512_256Sess,
+    Other(Vec<u8>)
+  }
+
+  impl Algorithm {
+    pub fn as_borrowed(&self) -> super::Algorithm<'_> {
+      match self {
+        Algorithm::Md5 => super::Algorithm::Md5,
+        Algorithm::Md5Sess => super::Algorithm::Md5Sess,
+        Algorithm::Sha256 => super::Algorithm::Sha256,
+        Algorithm::Sha256Sess => super::Algorithm::Sha256Sess,
+        Algorithm::Sha512_256 => super::Algorithm::Sha512_256,
+        Algorithm::Sha512_256Sess => super::Algorithm::Sha512_256Sess,
+        Algorithm::Other(name) => super::Algorithm::Other(name)
+      }
+    }
+  }
+
+  impl<'a> super::Algorithm<'a> {
+    pub fn into_owned(self) -> Algorithm {
+      match self {
+        super::Algorithm::Md5 => Algorithm::Md5,
+        super::Algorithm::Md5Sess => Algorithm::Md5Sess,
+        super::Algorithm::Sha256 => Algorithm::Sha256,
+        super::Algorithm::Sha256Sess => Algorithm::Sha256Sess,
+        super::Algorithm::Sha512_256 => Algorithm::Sha512_256,
+        super::Algorithm::Sha512_256Sess => Algorithm::Sha512_256Sess,
+        super::Algorithm::Other(name) => Algorithm::Other(name.to_vec())
+      }
+    }
+  }
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub struct QopOptions(Vec<u8>);
+
+  impl QopOptions {
+    pub fn as_borrowed(&self) -> super::QopOptions<'_> {
+      super::QopOptions(&self.0)
+    }
+  }
+
+  impl<'a> super::QopOptions<'a> {
+    pub fn into_owned(self) -> QopOptions {
+      QopOptions(self.0.to_vec())
+    }
+  }
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub struct DigestChallenge {
+    pub realm: Vec<u8>,
+    pub domain: Option<Vec<u8>>,
+    pub nonce: Vec<u8>,
+    pub opaque: Option<Vec<u8>>,
+    pub stale: bool,
+    pub algorithm: Option<Algorithm>,
+    pub qop: Option<QopOptions>,
+    pub params: AuthParams
+  }
+
+  impl DigestChallenge {
+    pub fn as_borrowed(&self) -> super::DigestChallenge<'_> {
+      super::DigestChallenge {
+        realm: &self.realm,
+        domain: self.domain.as_deref(),
+        nonce: &self.nonce,
+        opaque: self.opaque.as_deref(),
+        stale: self.stale,
+        algorithm: self.algorithm.as_ref().map(Algorithm::as_borrowed),
+        qop: self.qop.as_ref().map(QopOptions::as_borrowed),
+        params: self.params.as_borrowed()
+      }
+    }
+  }
+
+  impl<'a> super::DigestChallenge<'a> {
+    pub fn into_owned(self) -> DigestChallenge {
+      DigestChallenge {
+        realm: self.realm.to_vec(),
+        domain: self.domain.map(<[u8]>::to_vec),
+        nonce: self.nonce.to_vec(),
+        opaque: self.opaque.map(<[u8]>::to_vec),
+        stale: self.stale,
+        algorithm: self.algorithm.map(super::Algorithm::into_owned),
+        qop: self.qop.map(super::QopOptions::into_owned),
+        params: self.params.into_owned()
+      }
+    }
+  }
+
+  impl<'a> From<super::DigestChallenge<'a>> for DigestChallenge {
+    fn from(c: super::DigestChallenge<'a>) -> DigestChallenge {
+      c.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a DigestChallenge> for super::DigestChallenge<'a> {
+    fn from(c: &'a DigestChallenge) -> super::DigestChallenge<'a> {
+      c.as_borrowed()
+    }
+  }
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub enum Challenge {
+    Digest(DigestChallenge),
+    Other { scheme: Vec<u8>, params: AuthParams }
+  }
+
+  impl Challenge {
+    pub fn as_borrowed(&self) -> super::Challenge<'_> {
+      match self {
+        Challenge::Digest(c) => super::Challenge::Digest(c.as_borrowed()),
+        Challenge::Other { scheme, params } => super::Challenge::Other { scheme, params: params.as_borrowed() }
+      }
+    }
+  }
+
+  impl<'a> super::Challenge<'a> {
+    pub fn into_owned(self) -> Challenge {
+      match self {
+        super::Challenge::Digest(c) => Challenge::Digest(c.into_owned()),
+        super::Challenge::Other { scheme, params } => {
+          Challenge::Other { scheme: scheme.to_vec(), params: params.into_owned() }
+        }
+      }
+    }
+  }
+
+  impl<'a> From<super::Challenge<'a>> for Challenge {
+    fn from(c: super::Challenge<'a>) -> Challenge {
+      c.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a Challenge> for super::Challenge<'a> {
+    fn from(c: &'a Challenge) -> super::Challenge<'a> {
+      c.as_borrowed()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{
+    AuthValue, Algorithm, Qop, QopOptions, DigestChallenge, Challenge, Credentials,
+    AuthenticationInfo, challenges, parse, assemble
+  };
+  use crate::msg::header::HeaderName;
+  use crate::msg::header::parse::headers;
+
+  #[test]
+  fn auth_params_test() {
+    let (rest, params) = parse::auth_params(b"realm=\"a\\\"b\" ,\r\n charset=UTF-8 x").unwrap();
+    assert_eq!(rest, b" x");
+    assert_eq!(params.get(b"REALM"), Some(AuthValue::Quoted(b"a\\\"b")));
+    assert_eq!(params.get(b"realm").unwrap().unescaped().as_ref(), b"a\"b".as_ref());
+    assert_eq!(params.get(b"charset"), Some(AuthValue::Token(b"UTF-8")));
+    assert_eq!(params.iter().count(), 2);
+
+    assert!(parse::auth_params(b"realm").is_err());
+    assert!(parse::auth_params(b"realm=a,").is_err());
+
+    assert_eq!(parse::nc_value(b"0000002A"), Ok((&b""[..], 42)));
+    assert!(parse::nc_value(b"0000001").is_err());
+    assert_eq!(Algorithm::from_bytes(b"md5-SESS"), Algorithm::Md5Sess);
+    assert_eq!(Algorithm::from_bytes(b"SHA-512-256"), Algorithm::Sha512_256);
+    assert_eq!(Algorithm::from_bytes(b"AKAv1-MD5"), Algorithm::Other(b"AKAv1-MD5"));
+  }
+
+  #[test]
+  fn challenge_test() {
+    let value = b"Digest realm=\"atlanta.com\",\r\n domain=\"sip:ss1.carrier.com\", qop=\"auth, auth-int\",\
+nonce=\"f84f1cec41e6cbe5aea9c8e88d359\", opaque=\"\", stale=FALSE, algorithm=MD5, x=\"y\"";
+    let (rest, c) = parse::challenge(value).unwrap();
+    assert_eq!(rest, b"");
+    let d = match c {
+      Challenge::Digest(d) => d,
+      _ => panic!("{:?}", c)
+    };
+    assert_eq!(d.realm, b"atlanta.com");
+    assert_eq!(d.domain, Some(&b"sip:ss1.carrier.com"[..]));
+    assert_eq!(d.nonce, b"f84f1cec41e6cbe5aea9c8e88d359");
+    assert_eq!(d.opaque, Some(&b""[..]));
+    assert!(!d.stale);
+    assert_eq!(d.algorithm, Some(Algorithm::Md5));
+    let qop = d.qop.unwrap();
+    assert_eq!(qop.iter().collect::<Vec<_>>(), vec![Qop::Auth, Qop::AuthInt]);
+    assert!(qop.contains(Qop::AuthInt));
+
+    // quoting of the known parameters isn't enforced
+    let (_, c) = parse::challenge(b"Digest realm=x, nonce=\"1\", algorithm=\"SHA-256\", stale=\"true\", qop=auth").unwrap();
+    assert!(matches!(c, Challenge::Digest(d) if d.stale && d.algorithm == Some(Algorithm::Sha256)));
+
+    // Digest without nonce or with a broken known value
+    assert!(parse::challenge(b"Digest realm=\"a\"").is_err());
+    assert!(parse::challenge(b"Digest realm=\"a\", nonce=\"1\", stale=maybe").is_err());
+    assert!(parse::challenge(b"Digest realm=\"a\", nonce=\"1\", qop=\"auth,\"").is_err());
+
+    // other schemes are opaque
+    let (_, c) = parse::challenge(b"Bearer realm=\"b\", scope=\"x y\"").unwrap();
+    match c {
+      Challenge::Other { scheme, params } => {
+        assert_eq!(scheme, b"Bearer");
+        assert_eq!(params.get(b"scope"), Some(AuthValue::Quoted(b"x y")));
+      },
+      _ => panic!("{:?}", c)
+    }
+  }
+
+  #[test]
+  fn challenge_assemble_test() {
+    let mut buf = [0u8; 256];
+    let mut d = DigestChallenge::new(b"biloxi.com", b"ea9c8e88df84f1cec4341ae6cbe5a359");
+    d.qop = QopOptions::new(b"auth,auth-int");
+    d.algorithm = Some(Algorithm::Sha256);
+    d.stale = true;
+    let n = assemble::challenge(Challenge::Digest(d), &mut buf).unwrap();
+    assert_eq!(
+      &buf[..n],
+      b"Digest realm=\"biloxi.com\", nonce=\"ea9c8e88df84f1cec4341ae6cbe5a359\", stale=true, \
+algorithm=SHA-256, qop=\"auth,auth-int\"".as_ref()
+    );
+    let (_, parsed) = parse::challenge(&buf[..n]).unwrap();
+    assert!(matches!(parsed, Challenge::Digest(p) if p.nonce == d.nonce && p.stale && p.qop == d.qop));
+
+    // extension parameters survive, the known ones aren't repeated
+    let input = b"Digest nonce=\"1\", charset=UTF-8, realm=\"r\\\"\"".to_vec();
+    let (_, c) = parse::challenge(&input).unwrap();
+    let owned = c.into_owned();
+    let n = assemble::challenge(owned.as_borrowed(), &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"Digest realm=\"r\\\"\", nonce=\"1\", charset=UTF-8".as_ref());
+
+    let (_, c) = parse::challenge(b"Basic realm=\"WallyWorld\"").unwrap();
+    let n = assemble::challenge(c, &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"Basic realm=\"WallyWorld\"".as_ref());
+  }
+
+  #[test]
+  fn credentials_test() {
+    let value = b"Digest username=\"bob\", realm=\"biloxi.com\", nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", \
+uri=\"sip:bob@biloxi.com\", qop=auth, nc=00000001, cnonce=\"0a4f113b\", \
+response=\"6629fae49393a05397450978507c4ef1\", opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"";
+    let (rest, c) = parse::credentials(value).unwrap();
+    assert_eq!(rest, b"");
+    let d = match c {
+      Credentials::Digest(d) => d,
+      _ => panic!("{:?}", c)
+    };
+    assert_eq!(d.username, b"bob");
+    assert_eq!(d.uri, b"sip:bob@biloxi.com");
+    assert_eq!(d.qop, Some(Qop::Auth));
+    assert_eq!(d.nc, Some(1));
+    assert_eq!(d.cnonce, Some(&b"0a4f113b"[..]));
+    assert_eq!(d.algorithm, None);
+
+    let mut buf = [0u8; 512];
+    let n = assemble::credentials(c, &mut buf).unwrap();
+    assert_eq!(
+      &buf[..n],
+      b"Digest username=\"bob\", realm=\"biloxi.com\", nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", \
+uri=\"sip:bob@biloxi.com\", response=\"6629fae49393a05397450978507c4ef1\", cnonce=\"0a4f113b\", \
+opaque=\"5ccc069c403ebaf9f0171e9517f40e41\", qop=auth, nc=00000001".as_ref()
+    );
+    let (_, again) = parse::credentials(&buf[..n]).unwrap();
+    assert!(matches!(again, Credentials::Digest(a) if a.response == d.response && a.nc == d.nc && a.qop == d.qop));
+
+    assert!(parse::credentials(b"Digest username=\"bob\", realm=\"b\", nonce=\"1\", uri=\"sip:b\"").is_err());
+    assert!(parse::credentials(b"Digest username=\"bob\", realm=\"b\", nonce=\"1\", uri=\"sip:b\", response=\"xyz\"").is_err());
+    assert!(parse::credentials(b"Digest username=\"bob\", realm=\"b\", nonce=\"1\", uri=\"sip:b\", response=\"\", nc=1").is_err());
+    assert!(matches!(parse::credentials(b"Bearer token=abc"), Ok((_, Credentials::Other { .. }))));
+  }
+
+  #[test]
+  fn authentication_info_test() {
+    let (rest, info) = parse::authentication_info(b"nextnonce=\"47364c23432d2e131a5fb210812c\", rspauth=\"1a2B\", nc=00000002").unwrap();
+    assert_eq!(rest, b"");
+    assert_eq!(info.nextnonce, Some(&b"47364c23432d2e131a5fb210812c"[..]));
+    assert_eq!(info.rspauth, Some(&b"1a2B"[..]));
+    assert_eq!(info.nc, Some(2));
+    assert!(parse::authentication_info(b"rspauth=\"zz\"").is_err());
+
+    let mut buf = [0u8; 128];
+    let info = AuthenticationInfo { qop: Some(Qop::Auth), rspauth: Some(b"ab"), cnonce: Some(b"c"), nc: Some(255), ..Default::default() };
+    let n = assemble::authentication_info(info, &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"qop=auth, rspauth=\"ab\", cnonce=\"c\", nc=000000ff".as_ref());
+  }
+
+  #[test]
+  fn challenges_test() {
+    let (_, hs) = headers(b"WWW-Authenticate: Negotiate\r\n\
+WWW-Authenticate: Digest realm=\"a\", nonce=\"1\"\r\n\
+Proxy-Authenticate: Digest realm=\"p\", nonce=\"2\"\r\n\r\n").unwrap();
+    let www: Vec<_> = challenges(hs, HeaderName::WwwAuthenticate).collect();
+    assert_eq!(www.len(), 1);
+    assert!(matches!(www[0], Challenge::Digest(d) if d.realm == b"a"));
+    assert_eq!(challenges(hs, HeaderName::ProxyAuthenticate).count(), 1);
+  }
+}
@@ -0,0 +1,139 @@
+use crate::msg::Binary;
+use crate::msg::abnf::whole;
+
+// callid   =  word [ "@" word ]
+// Call-IDs are compared byte by byte, case matters.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub struct CallId<'a>(&'a Binary);
+
+impl<'a> CallId<'a> {
+  // Call-ID out of application data, e.g. generated with msg::id::word
+  pub fn new(input: &'a Binary) -> Option<CallId<'a>> {
+    whole(parse::call_id(input))
+  }
+
+  pub fn as_bytes(&self) -> &'a Binary {
+    self.0
+  }
+
+  // word before "@"
+  pub fn local(&self) -> &'a Binary {
+    self.0.split(|b| *b == b'@').next().unwrap_or(self.0)
+  }
+
+  // word after "@", usually a host name
+  pub fn host(&self) -> Option<&'a Binary> {
+    self.0.iter().position(|b| *b == b'@').map(|at| &self.0[at + 1..])
+  }
+}
+
+pub mod parse {
+  use crate::msg::Binary;
+  use crate::msg::abnf::ch::word;
+  use super::CallId;
+  use nom::IResult;
+  use nom::Err::Incomplete;
+
+  // word which reaches the end of the header value is reported as
+  // Incomplete by the streaming parser, here it is just the last word
+  #[inline]
+  fn value_word(input: &Binary) -> IResult<&Binary, &Binary> {
+    match word(input) {
+      Err(Incomplete(_)) if !input.is_empty() => Ok((&input[input.len()..], input)),
+      r => r
+    }
+  }
+
+  // Call-ID  =  ( "Call-ID" / "i" ) HCOLON callid
+  // callid   =  word [ "@" word ]
+  #[inline]
+  pub fn call_id(input: &Binary) -> IResult<&Binary, CallId<'_>> {
+    let (mut rest, _) = value_word(input)?;
+    if rest.first() == Some(&b'@') {
+      let (r, _) = value_word(&rest[1..])?;
+      rest = r;
+    }
+    Ok((rest, CallId(&input[..input.len() - rest.len()])))
+  }
+}
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::output::{Output, AssembleResult};
+  use super::CallId;
+
+  pub fn call_id(id: CallId, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.put(id.as_bytes())?;
+    out.finish()
+  }
+}
+
+pub mod owned {
+  use crate::msg::Binary;
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub struct CallId(Vec<u8>);
+
+  impl CallId {
+    pub fn as_bytes(&self) -> &Binary {
+      &self.0
+    }
+
+    pub fn as_borrowed(&self) -> super::CallId<'_> {
+      super::CallId(&self.0)
+    }
+  }
+
+  impl<'a> super::CallId<'a> {
+    pub fn into_owned(self) -> CallId {
+      CallId(self.0.to_vec())
+    }
+  }
+
+  impl<'a> From<super::CallId<'a>> for CallId {
+    fn from(id: super::CallId<'a>) -> CallId {
+      id.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a CallId> for super::CallId<'a> {
+    fn from(id: &'a CallId) -> super::CallId<'a> {
+      id.as_borrowed()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{CallId, parse, assemble};
+  use crate::msg::id;
+
+  #[test]
+  fn call_id_test() {
+    let (rest, id) = parse::call_id(b"f81d4fae-7dec-11d0-a765-00a0c91e6bf6@biloxi.com").unwrap();
+    assert_eq!(rest, b"");
+    assert_eq!(id.local(), b"f81d4fae-7dec-11d0-a765-00a0c91e6bf6");
+    assert_eq!(id.host(), Some(&b"biloxi.com"[..]));
+
+    let (rest, id) = parse::call_id(b"a84b4c76e66710 x").unwrap();
+    assert_eq!(rest, b" x");
+    assert_eq!(id.as_bytes(), b"a84b4c76e66710");
+    assert_eq!(id.host(), None);
+
+    assert!(CallId::new(b"a@b@c").is_none());
+    assert!(CallId::new(b"a@").is_none());
+    assert!(CallId::new(b"@b").is_none());
+    assert!(CallId::new(b"").is_none());
+    assert_ne!(CallId::new(b"ABC"), CallId::new(b"abc"));
+
+    let mut generated = [0u8; 16];
+    let n = id::word(b"seed", &mut generated);
+    assert!(CallId::new(&generated[..n]).is_some());
+
+    let mut buf = [0u8; 16];
+    let owned = CallId::new(b"x@y").unwrap().into_owned();
+    let n = assemble::call_id(owned.as_borrowed(), &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"x@y");
+  }
+}
@@ -0,0 +1,257 @@
+use crate::msg::Binary;
+use crate::msg::abnf::whole;
+use crate::msg::header::name_addr::NameAddr;
+use crate::msg::header::params::{GenericParams, QValue};
+use crate::msg::header::params::parse::{qvalue, delta_seconds};
+
+// contact-param  =  (name-addr / addr-spec) *(SEMI contact-params)
+// contact-params     =  c-p-q / c-p-expires
+//                       / contact-extension
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Contact<'a> {
+  pub address: NameAddr<'a>,
+  pub params: GenericParams<'a>
+}
+
+impl<'a> Contact<'a> {
+  // c-p-q              =  "q" EQUAL qvalue
+  pub fn q(&self) -> Option<QValue> {
+    whole(qvalue(self.params.get(b"q")??))
+  }
+
+  // c-p-expires        =  "expires" EQUAL delta-seconds
+  pub fn expires(&self) -> Option<u32> {
+    whole(delta_seconds(self.params.get(b"expires")??))
+  }
+}
+
+// Contact  =  ("Contact" / "m" ) HCOLON
+//             ( STAR / (contact-param *(COMMA contact-param)))
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum ContactValue<'a> {
+  Star,
+  Contacts(ContactList<'a>)
+}
+
+// Contacts of a single header, every contact-param is parsed again on
+// iteration
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct ContactList<'a>(&'a Binary);
+
+impl<'a> ContactList<'a> {
+  pub fn as_bytes(&self) -> &'a Binary {
+    self.0
+  }
+
+  pub fn iter(&self) -> ContactListIter<'a> {
+    ContactListIter { rest: self.0, first: true }
+  }
+}
+
+impl<'a> IntoIterator for ContactList<'a> {
+  type Item = Contact<'a>;
+  type IntoIter = ContactListIter<'a>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+pub struct ContactListIter<'a> {
+  rest: &'a Binary,
+  first: bool
+}
+
+impl<'a> Iterator for ContactListIter<'a> {
+  type Item = Contact<'a>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let rest = if self.first {
+      self.rest
+    } else {
+      parse::comma(self.rest).ok()?.0
+    };
+    let (r, contact) = parse::contact_param(rest).ok()?;
+    self.first = false;
+    self.rest = r;
+    Some(contact)
+  }
+}
+
+pub mod parse {
+  use crate::msg::Binary;
+  use crate::msg::abnf::ch::sws;
+  use crate::msg::header::name_addr::parse::address;
+  use crate::msg::header::params::parse::generic_params;
+  use super::{Contact, ContactValue, ContactList};
+  use nom::IResult;
+  use nom::Err::Error;
+  use nom::error::ErrorKind::Char;
+
+  pub(super) use crate::msg::abnf::ch::comma;
+
+  // contact-param  =  (name-addr / addr-spec) *(SEMI contact-params)
+  #[inline]
+  pub fn contact_param(input: &Binary) -> IResult<&Binary, Contact<'_>> {
+    let (rest, address) = address(input)?;
+    let (rest, params) = generic_params(rest)?;
+    Ok((rest, Contact { address, params }))
+  }
+
+  // STAR    =  SWS "*" SWS
+  #[inline]
+  fn star(input: &Binary) -> IResult<&Binary, &Binary> {
+    let (rest, _) = sws(input)?;
+    match rest.first() {
+      Some(b'*') => {
+        let (rest, _) = sws(&rest[1..])?;
+        Ok((rest, &input[..input.len() - rest.len()]))
+      },
+      _ => Err(Error((input, Char)))
+    }
+  }
+
+  // STAR / (contact-param *(COMMA contact-param))
+  #[inline]
+  pub fn contact(input: &Binary) -> IResult<&Binary, ContactValue<'_>> {
+    if let Ok((rest, _)) = star(input) {
+      return Ok((rest, ContactValue::Star));
+    }
+
+    let (mut rest, _) = contact_param(input)?;
+    while let Ok((r, _)) = comma(rest) {
+      let (r, _) = contact_param(r)?;
+      rest = r;
+    }
+    Ok((rest, ContactValue::Contacts(ContactList(&input[..input.len() - rest.len()]))))
+  }
+}
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::header::name_addr::assemble::name_addr;
+  use crate::msg::output::{Output, AssembleResult};
+  use super::{Contact, ContactValue};
+
+  pub fn contact_param(c: Contact, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.with(|b| name_addr(c.address, b))?;
+    out.put(c.params.as_bytes())?;
+    out.finish()
+  }
+
+  pub fn contact(v: ContactValue, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    match v {
+      ContactValue::Star => out.put_byte(b'*')?,
+      ContactValue::Contacts(list) => for (i, c) in list.iter().enumerate() {
+        if i > 0 {
+          out.put(b", ")?;
+        }
+        out.with(|b| contact_param(c, b))?;
+      }
+    }
+    out.finish()
+  }
+}
+
+pub mod owned {
+  use crate::msg::header::name_addr::owned::NameAddr;
+  use crate::msg::header::params::owned::GenericParams;
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub struct Contact {
+    pub address: NameAddr,
+    pub params: GenericParams
+  }
+
+  impl Contact {
+    pub fn as_borrowed(&self) -> super::Contact<'_> {
+      super::Contact {
+        address: self.address.as_borrowed(),
+        params: self.params.as_borrowed()
+      }
+    }
+  }
+
+  impl<'a> super::Contact<'a> {
+    pub fn into_owned(self) -> Contact {
+      Contact {
+        address: self.address.into_owned(),
+        params: self.params.into_owned()
+      }
+    }
+  }
+
+  impl<'a> From<super::Contact<'a>> for Contact {
+    fn from(c: super::Contact<'a>) -> Contact {
+      c.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a Contact> for super::Contact<'a> {
+    fn from(c: &'a Contact) -> super::Contact<'a> {
+      c.as_borrowed()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Contact, ContactValue, parse, assemble};
+  use crate::msg::header::params::QValue;
+  use crate::msg::header::name_addr::DisplayName;
+
+  #[test]
+  fn contact_test() {
+    // RFC 3261 20.10
+    let value = b"\"Mr. Watson\" <sip:watson@worcester.bell-telephone.com>\r\n ;q=0.7; expires=3600,\r\n \
+\"Mr. Watson\" <mailto:watson@bell-telephone.com> ;q=0.1";
+    let (rest, contact) = parse::contact(value).unwrap();
+    assert_eq!(rest, b"");
+    let contacts: Vec<Contact> = match contact {
+      ContactValue::Contacts(list) => list.iter().collect(),
+      ContactValue::Star => panic!("contacts expected")
+    };
+    assert_eq!(contacts.len(), 2);
+    assert_eq!(contacts[0].address.display_name, Some(DisplayName::Quoted(b"Mr. Watson")));
+    assert_eq!(contacts[0].q(), QValue::new(700));
+    assert_eq!(contacts[0].expires(), Some(3600));
+    assert_eq!(contacts[1].q(), QValue::new(100));
+    assert_eq!(contacts[1].expires(), None);
+
+    let (_, contact) = parse::contact(b"sip:a@b.com;q=2;expires=x").unwrap();
+    let c = match contact {
+      ContactValue::Contacts(list) => list.iter().next().unwrap(),
+      ContactValue::Star => panic!("contacts expected")
+    };
+    assert_eq!(c.q(), None);
+    assert_eq!(c.expires(), None);
+    assert_eq!(c.params.get(b"q"), Some(Some(&b"2"[..])));
+
+    assert_eq!(parse::contact(b"*"), Ok((&b""[..], ContactValue::Star)));
+    assert!(parse::contact(b"sip:a@b.com, ").is_err());
+    assert!(parse::contact(b"").is_err());
+  }
+
+  #[test]
+  fn contact_assemble_test() {
+    let mut buf = [0u8; 128];
+    let (_, contact) = parse::contact(b"sip:a@b.com;expires=60 ,Bob<sip:bob@c.com;lr>;q=1").unwrap();
+    let n = assemble::contact(contact, &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"<sip:a@b.com>;expires=60, Bob <sip:bob@c.com;lr>;q=1".as_ref());
+
+    let n = assemble::contact(ContactValue::Star, &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"*");
+  }
+
+  #[test]
+  fn contact_owned_test() {
+    let owned = {
+      let input = b"<sip:a@b.com>;expires=60".to_vec();
+      let (_, c) = parse::contact_param(&input).unwrap();
+      c.into_owned()
+    };
+    assert_eq!(owned.as_borrowed().expires(), Some(60));
+  }
+}
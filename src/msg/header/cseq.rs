@@ -0,0 +1,105 @@
+use crate::msg::method::Method;
+
+// CSeq  =  "CSeq" HCOLON 1*DIGIT LWS Method
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct CSeq<'a> {
+  pub number: u32,
+  pub method: Method<'a>
+}
+
+impl<'a> CSeq<'a> {
+  // sequence number MUST be less than 2**31, RFC 3261 8.1.1.5
+  pub const MAX_NUMBER: u32 = (1 << 31) - 1;
+}
+
+pub mod parse {
+  use crate::msg::Binary;
+  use crate::msg::abnf::ch::{lws, value_token};
+  use crate::msg::abnf::digit::decimal;
+  use crate::msg::method::Method;
+  use super::CSeq;
+  use nom::IResult;
+
+  // Method is taken as a whole token the same way Request-Line does
+  #[inline]
+  pub fn cseq(input: &Binary) -> IResult<&Binary, CSeq<'_>> {
+    let (rest, number) = decimal(input, CSeq::MAX_NUMBER as u64)?;
+    let (rest, _) = lws(rest)?;
+    let (rest, m) = value_token(rest)?;
+    let m = Method::from_token(m);
+    Ok((rest, CSeq { number: number as u32, method: m }))
+  }
+}
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::output::{Output, AssembleResult};
+  use super::CSeq;
+
+  pub fn cseq(c: CSeq, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.put_number(c.number as u64)?;
+    out.put_byte(b' ')?;
+    out.put(c.method.as_bytes())?;
+    out.finish()
+  }
+}
+
+pub mod owned {
+  use crate::msg::method::owned::Method;
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub struct CSeq {
+    pub number: u32,
+    pub method: Method
+  }
+
+  impl CSeq {
+    pub fn as_borrowed(&self) -> super::CSeq<'_> {
+      super::CSeq { number: self.number, method: self.method.as_borrowed() }
+    }
+  }
+
+  impl<'a> super::CSeq<'a> {
+    pub fn into_owned(self) -> CSeq {
+      CSeq { number: self.number, method: self.method.into_owned() }
+    }
+  }
+
+  impl<'a> From<super::CSeq<'a>> for CSeq {
+    fn from(c: super::CSeq<'a>) -> CSeq {
+      c.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a CSeq> for super::CSeq<'a> {
+    fn from(c: &'a CSeq) -> super::CSeq<'a> {
+      c.as_borrowed()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{CSeq, parse, assemble};
+  use crate::msg::method::Method;
+  use nom::{Err::Error, error::ErrorKind::{Digit, Space}};
+
+  #[test]
+  fn cseq_test() {
+    assert_eq!(parse::cseq(b"4711 INVITE"), Ok((&b""[..], CSeq { number: 4711, method: Method::Invite })));
+    assert_eq!(parse::cseq(b"1\r\n ACK"), Ok((&b""[..], CSeq { number: 1, method: Method::Ack })));
+    assert_eq!(parse::cseq(b"2 INVITEX"), Ok((&b""[..], CSeq { number: 2, method: Method::Token(b"INVITEX") })));
+    assert_eq!(parse::cseq(b"2147483647 BYE"), Ok((&b""[..], CSeq { number: CSeq::MAX_NUMBER, method: Method::Bye })));
+    assert_eq!(parse::cseq(b"2147483648 BYE"), Err(Error((&b"2147483648 BYE"[..], Digit))));
+    assert_eq!(parse::cseq(b"1INVITE"), Err(Error((&b"INVITE"[..], Space))));
+    assert!(parse::cseq(b"INVITE").is_err());
+    assert!(parse::cseq(b"1 ").is_err());
+
+    let mut buf = [0u8; 16];
+    let n = assemble::cseq(CSeq { number: 314159, method: Method::Invite }, &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"314159 INVITE");
+    let owned = CSeq { number: 1, method: Method::Token(b"PUBLISH") }.into_owned();
+    assert_eq!(owned.as_borrowed().method, Method::Token(b"PUBLISH"));
+  }
+}
@@ -0,0 +1,110 @@
+use crate::msg::Binary;
+use crate::msg::header::params::GenericParams;
+
+// Content-Disposition   =  "Content-Disposition" HCOLON
+//                          disp-type *( SEMI disp-param )
+// disp-type             =  "render" / "session" / "icon" / "alert"
+//                          / disp-extension-token
+// disp-param            =  handling-param / generic-param
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Disposition<'a> {
+  pub disp_type: &'a Binary,
+  pub params: GenericParams<'a>
+}
+
+impl<'a> Disposition<'a> {
+  pub fn is(&self, disp_type: &Binary) -> bool {
+    self.disp_type.eq_ignore_ascii_case(disp_type)
+  }
+
+  // handling-param  =  "handling" EQUAL
+  //                    ( "optional" / "required" / other-handling )
+  // Handling is required unless it says optional (RFC 3261 20.11).
+  pub fn is_required(&self) -> bool {
+    !matches!(self.params.get(b"handling"), Some(Some(h)) if h.eq_ignore_ascii_case(b"optional"))
+  }
+}
+
+pub mod parse {
+  use crate::msg::Binary;
+  use crate::msg::abnf::ch::value_token;
+  use crate::msg::header::params::parse::generic_params;
+  use super::Disposition;
+  use nom::IResult;
+
+  #[inline]
+  pub fn disposition(input: &Binary) -> IResult<&Binary, Disposition<'_>> {
+    let (rest, disp_type) = value_token(input)?;
+    let (rest, params) = generic_params(rest)?;
+    Ok((rest, Disposition { disp_type, params }))
+  }
+}
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::output::{Output, AssembleResult};
+  use super::Disposition;
+
+  pub fn disposition(d: Disposition, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.put(d.disp_type)?;
+    out.put(d.params.as_bytes())?;
+    out.finish()
+  }
+}
+
+pub mod owned {
+  use crate::msg::header::params::owned::GenericParams;
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub struct Disposition {
+    pub disp_type: Vec<u8>,
+    pub params: GenericParams
+  }
+
+  impl Disposition {
+    pub fn as_borrowed(&self) -> super::Disposition<'_> {
+      super::Disposition { disp_type: &self.disp_type, params: self.params.as_borrowed() }
+    }
+  }
+
+  impl<'a> super::Disposition<'a> {
+    pub fn into_owned(self) -> Disposition {
+      Disposition { disp_type: self.disp_type.to_vec(), params: self.params.into_owned() }
+    }
+  }
+
+  impl<'a> From<super::Disposition<'a>> for Disposition {
+    fn from(d: super::Disposition<'a>) -> Disposition {
+      d.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a Disposition> for super::Disposition<'a> {
+    fn from(d: &'a Disposition) -> super::Disposition<'a> {
+      d.as_borrowed()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{parse, assemble};
+
+  #[test]
+  fn disposition_test() {
+    let (rest, d) = parse::disposition(b"session;handling=optional").unwrap();
+    assert_eq!(rest, b"");
+    assert!(d.is(b"Session"));
+    assert!(!d.is_required());
+
+    let (_, d) = parse::disposition(b"render").unwrap();
+    assert!(d.is_required());
+    assert!(parse::disposition(b";x").is_err());
+
+    let mut buf = [0u8; 32];
+    let (_, d) = parse::disposition(b"icon ;handling=required").unwrap();
+    let n = assemble::disposition(d.into_owned().as_borrowed(), &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"icon ;handling=required");
+  }
+}
@@ -0,0 +1,138 @@
+use crate::msg::Binary;
+use crate::msg::abnf::ch::is_token;
+use crate::msg::header::name_addr::NameAddr;
+use crate::msg::header::params::GenericParams;
+
+// From        =  ( "From" / "f" ) HCOLON from-spec
+// from-spec   =  ( name-addr / addr-spec ) *( SEMI from-param )
+// from-param  =  tag-param / generic-param
+// To          =  ( "To" / "t" ) HCOLON ( name-addr / addr-spec )
+//                *( SEMI to-param )
+// to-param    =  tag-param / generic-param
+// Both headers have the same value, so they share the type.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct FromTo<'a> {
+  pub address: NameAddr<'a>,
+  pub params: GenericParams<'a>
+}
+
+impl<'a> FromTo<'a> {
+  // tag-param   =  "tag" EQUAL token
+  pub fn tag(&self) -> Option<&'a Binary> {
+    match self.params.get(b"tag") {
+      Some(Some(tag)) if is_token(tag) => Some(tag),
+      _ => None
+    }
+  }
+}
+
+pub mod parse {
+  use crate::msg::Binary;
+  use crate::msg::header::name_addr::parse::address;
+  use crate::msg::header::params::parse::generic_params;
+  use super::FromTo;
+  use nom::IResult;
+
+  // ( name-addr / addr-spec ) *( SEMI generic-param )
+  #[inline]
+  pub fn from_to(input: &Binary) -> IResult<&Binary, FromTo<'_>> {
+    let (rest, address) = address(input)?;
+    let (rest, params) = generic_params(rest)?;
+    Ok((rest, FromTo { address, params }))
+  }
+}
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::header::name_addr::assemble::name_addr;
+  use crate::msg::output::{Output, AssembleResult};
+  use super::FromTo;
+
+  pub fn from_to(v: FromTo, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.with(|b| name_addr(v.address, b))?;
+    out.put(v.params.as_bytes())?;
+    out.finish()
+  }
+}
+
+pub mod owned {
+  use crate::msg::header::name_addr::owned::NameAddr;
+  use crate::msg::header::params::owned::GenericParams;
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub struct FromTo {
+    pub address: NameAddr,
+    pub params: GenericParams
+  }
+
+  impl FromTo {
+    pub fn as_borrowed(&self) -> super::FromTo<'_> {
+      super::FromTo {
+        address: self.address.as_borrowed(),
+        params: self.params.as_borrowed()
+      }
+    }
+  }
+
+  impl<'a> super::FromTo<'a> {
+    pub fn into_owned(self) -> FromTo {
+      FromTo {
+        address: self.address.into_owned(),
+        params: self.params.into_owned()
+      }
+    }
+  }
+
+  impl<'a> From<super::FromTo<'a>> for FromTo {
+    fn from(v: super::FromTo<'a>) -> FromTo {
+      v.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a FromTo> for super::FromTo<'a> {
+    fn from(v: &'a FromTo) -> super::FromTo<'a> {
+      v.as_borrowed()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{parse, assemble};
+  use crate::msg::header::name_addr::DisplayName;
+
+  #[test]
+  fn from_to_test() {
+    let (rest, from) = parse::from_to(b"\"A. G. Bell\" <sip:agb@bell-telephone.com> ;tag=a48s").unwrap();
+    assert_eq!(rest, b"");
+    assert_eq!(from.address.display_name, Some(DisplayName::Quoted(b"A. G. Bell")));
+    assert_eq!(from.tag(), Some(&b"a48s"[..]));
+
+    let (_, to) = parse::from_to(b"sip:+12125551212@server.phone2net.com;TAG=887s;x").unwrap();
+    assert_eq!(to.tag(), Some(&b"887s"[..]));
+    assert_eq!(to.params.get(b"x"), Some(None));
+
+    let (_, to) = parse::from_to(b"The Operator <sip:operator@cs.columbia.edu>").unwrap();
+    assert_eq!(to.tag(), None);
+    assert_eq!(to.address.display_name.map(|n| n.unescaped().into_owned()), Some(b"The Operator".to_vec()));
+
+    let (_, to) = parse::from_to(b"<sip:a@b.com>;tag=\"x\"").unwrap();
+    assert_eq!(to.tag(), None);
+
+    let (rest, _) = parse::from_to(b"<sip:a@b.com>;tag=1 garbage").unwrap();
+    assert_eq!(rest, b" garbage");
+  }
+
+  #[test]
+  fn from_to_assemble_test() {
+    let mut buf = [0u8; 64];
+    let (_, from) = parse::from_to(b"Alice <sip:alice@atlanta.com>;tag=1928301774").unwrap();
+    let n = assemble::from_to(from, &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"Alice <sip:alice@atlanta.com>;tag=1928301774");
+
+    let (_, to) = parse::from_to(b"sip:bob@biloxi.com ; tag=a6c85cf").unwrap();
+    let n = assemble::from_to(to.into_owned().as_borrowed(), &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"<sip:bob@biloxi.com> ; tag=a6c85cf");
+  }
+}
@@ -0,0 +1,298 @@
+use crate::msg::Binary;
+use crate::msg::abnf::whole;
+use crate::msg::header::params::{GenericParams, QValue};
+use crate::msg::header::params::parse::qvalue;
+
+// media-type     =  m-type SLASH m-subtype *(SEMI m-parameter)
+// m-parameter    =  m-attribute EQUAL m-value
+// m-value        =  token / quoted-string
+// Types and attribute names are case-insensitive. In Accept the type
+// and subtype may be "*" and q is one of the parameters.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct MediaType<'a> {
+  pub mtype: &'a Binary,
+  pub subtype: &'a Binary,
+  pub params: GenericParams<'a>
+}
+
+impl<'a> MediaType<'a> {
+  pub fn new(mtype: &'a Binary, subtype: &'a Binary) -> MediaType<'a> {
+    MediaType { mtype, subtype, params: GenericParams::default() }
+  }
+
+  pub fn is(&self, mtype: &Binary, subtype: &Binary) -> bool {
+    self.mtype.eq_ignore_ascii_case(mtype) && self.subtype.eq_ignore_ascii_case(subtype)
+  }
+
+  pub fn is_multipart(&self) -> bool {
+    self.mtype.eq_ignore_ascii_case(b"multipart")
+  }
+
+  // Value of the parameter, quotes of a quoted-string are dropped
+  pub fn param(&self, name: &Binary) -> Option<&'a Binary> {
+    let value = self.params.get(name)??;
+    match value {
+      [b'"', inner @ .., b'"'] => Some(inner),
+      _ => Some(value)
+    }
+  }
+
+  pub fn charset(&self) -> Option<&'a Binary> {
+    self.param(b"charset")
+  }
+
+  // boundary of a multipart body, RFC 2046 5.1.1
+  pub fn boundary(&self) -> Option<&'a Binary> {
+    self.param(b"boundary")
+  }
+
+  // q of an Accept range, 1 when it is missing or malformed
+  pub fn q(&self) -> QValue {
+    self.param(b"q").and_then(|q| whole(qvalue(q))).unwrap_or_default()
+  }
+
+  // Whether the media type falls into this range of an Accept header.
+  // Parameters of the range other than q are not compared.
+  pub fn matches(&self, media: &MediaType) -> bool {
+    let any = |v: &Binary| v == b"*";
+    (any(self.mtype) || self.mtype.eq_ignore_ascii_case(media.mtype))
+      && (any(self.subtype) || self.subtype.eq_ignore_ascii_case(media.subtype))
+  }
+}
+
+// Ranges of a single Accept header, parsed again on iteration. An empty
+// Accept is allowed and means that no body is acceptable.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub struct AcceptList<'a>(&'a Binary);
+
+impl<'a> AcceptList<'a> {
+  pub fn as_bytes(&self) -> &'a Binary {
+    self.0
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  pub fn iter(&self) -> AcceptListIter<'a> {
+    AcceptListIter { rest: self.0, first: true }
+  }
+
+  // Whether the media type is acceptable, a range with q=0 excludes it.
+  // The most specific matching range counts (RFC 7231 5.3.2).
+  pub fn accepts(&self, media: &MediaType) -> bool {
+    let specificity = |r: &MediaType| (r.mtype != b"*") as u8 + (r.subtype != b"*") as u8;
+    self.iter()
+      .filter(|r| r.matches(media))
+      .max_by_key(specificity)
+      .is_some_and(|r| r.q().as_thousandths() > 0)
+  }
+}
+
+impl<'a> IntoIterator for AcceptList<'a> {
+  type Item = MediaType<'a>;
+  type IntoIter = AcceptListIter<'a>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+pub struct AcceptListIter<'a> {
+  rest: &'a Binary,
+  first: bool
+}
+
+impl<'a> Iterator for AcceptListIter<'a> {
+  type Item = MediaType<'a>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let rest = if self.first {
+      self.rest
+    } else {
+      parse::comma(self.rest).ok()?.0
+    };
+    let (r, range) = parse::media_range(rest).ok()?;
+    self.first = false;
+    self.rest = r;
+    Some(range)
+  }
+}
+
+pub mod parse {
+  use crate::msg::Binary;
+  use crate::msg::abnf::ch::{slash, value_token};
+  use crate::msg::header::params::parse::generic_params;
+  use super::{MediaType, AcceptList};
+  use nom::IResult;
+  use nom::Err::Error;
+  use nom::error::ErrorKind::Verify;
+
+  pub use crate::msg::abnf::ch::comma;
+
+  // Content-Type     =  ( "Content-Type" / "c" ) HCOLON media-type
+  // media-type       =  m-type SLASH m-subtype *(SEMI m-parameter)
+  #[inline]
+  pub fn media_type(input: &Binary) -> IResult<&Binary, MediaType<'_>> {
+    let (rest, mtype) = value_token(input)?;
+    let (rest, _) = slash(rest)?;
+    let (rest, subtype) = value_token(rest)?;
+    let (rest, params) = generic_params(rest)?;
+    Ok((rest, MediaType { mtype, subtype, params }))
+  }
+
+  // media-range    =  ( "*/*"
+  //                   / ( m-type SLASH "*" )
+  //                   / ( m-type SLASH m-subtype )
+  //                   ) *( SEMI m-parameter )
+  // accept-range   =  media-range *(SEMI accept-param)
+  #[inline]
+  pub fn media_range(input: &Binary) -> IResult<&Binary, MediaType<'_>> {
+    let (rest, range) = media_type(input)?;
+    if range.mtype == b"*" && range.subtype != b"*" {
+      return Err(Error((input, Verify)));
+    }
+    Ok((rest, range))
+  }
+
+  // Accept         =  "Accept" HCOLON
+  //                    [ accept-range *(COMMA accept-range) ]
+  #[inline]
+  pub fn accept(input: &Binary) -> IResult<&Binary, AcceptList<'_>> {
+    let mut rest = match media_range(input) {
+      Ok((r, _)) => r,
+      Err(_) => return Ok((input, AcceptList(&input[..0])))
+    };
+    while let Ok((r, _)) = comma(rest) {
+      let (r, _) = media_range(r)?;
+      rest = r;
+    }
+    Ok((rest, AcceptList(&input[..input.len() - rest.len()])))
+  }
+}
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::output::{Output, AssembleResult};
+  use super::MediaType;
+
+  pub fn media_type(m: MediaType, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.put(m.mtype)?;
+    out.put_byte(b'/')?;
+    out.put(m.subtype)?;
+    out.put(m.params.as_bytes())?;
+    out.finish()
+  }
+
+  pub fn accept<'a, I>(ranges: I, buf: &mut Binary) -> AssembleResult
+    where I: IntoIterator<Item = MediaType<'a>> {
+    let mut out = Output::new(buf);
+    for (i, r) in ranges.into_iter().enumerate() {
+      if i > 0 {
+        out.put(b", ")?;
+      }
+      out.with(|b| media_type(r, b))?;
+    }
+    out.finish()
+  }
+}
+
+pub mod owned {
+  use crate::msg::header::params::owned::GenericParams;
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub struct MediaType {
+    pub mtype: Vec<u8>,
+    pub subtype: Vec<u8>,
+    pub params: GenericParams
+  }
+
+  impl MediaType {
+    pub fn as_borrowed(&self) -> super::MediaType<'_> {
+      super::MediaType {
+        mtype: &self.mtype,
+        subtype: &self.subtype,
+        params: self.params.as_borrowed()
+      }
+    }
+  }
+
+  impl<'a> super::MediaType<'a> {
+    pub fn into_owned(self) -> MediaType {
+      MediaType {
+        mtype: self.mtype.to_vec(),
+        subtype: self.subtype.to_vec(),
+        params: self.params.into_owned()
+      }
+    }
+  }
+
+  impl<'a> From<super::MediaType<'a>> for MediaType {
+    fn from(m: super::MediaType<'a>) -> MediaType {
+      m.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a MediaType> for super::MediaType<'a> {
+    fn from(m: &'a MediaType) -> super::MediaType<'a> {
+      m.as_borrowed()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{MediaType, parse, assemble};
+  use crate::msg::header::params::QValue;
+
+  #[test]
+  fn media_type_test() {
+    let (rest, m) = parse::media_type(b"multipart/mixed ; boundary=\"unique boundary 1\";charset=UTF-8").unwrap();
+    assert_eq!(rest, b"");
+    assert!(m.is(b"Multipart", b"MIXED"));
+    assert!(m.is_multipart());
+    assert_eq!(m.boundary(), Some(&b"unique boundary 1"[..]));
+    assert_eq!(m.charset(), Some(&b"UTF-8"[..]));
+    assert_eq!(m.param(b"x"), None);
+
+    let (rest, m) = parse::media_type(b"application/sdp").unwrap();
+    assert_eq!(rest, b"");
+    assert!(m.is(b"application", b"sdp") && m.params.is_empty());
+
+    assert!(parse::media_type(b"application").is_err());
+    assert!(parse::media_type(b"application/").is_err());
+
+    let mut buf = [0u8; 64];
+    let (_, m) = parse::media_type(b"application/pidf+xml;charset=utf-8").unwrap();
+    let n = assemble::media_type(m.into_owned().as_borrowed(), &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"application/pidf+xml;charset=utf-8");
+  }
+
+  #[test]
+  fn accept_test() {
+    let (rest, list) = parse::accept(b"application/sdp;level=1, application/*;q=0.5,\r\n */*;q=0").unwrap();
+    assert_eq!(rest, b"");
+    let ranges: Vec<_> = list.iter().collect();
+    assert_eq!(ranges.len(), 3);
+    assert_eq!(ranges[0].q(), QValue::MAX);
+    assert_eq!(ranges[1].q(), QValue::new(500).unwrap());
+
+    assert!(list.accepts(&MediaType::new(b"application", b"SDP")));
+    assert!(list.accepts(&MediaType::new(b"application", b"pidf+xml")));
+    assert!(!list.accepts(&MediaType::new(b"text", b"plain")));
+
+    let (rest, list) = parse::accept(b"").unwrap();
+    assert_eq!(rest, b"");
+    assert!(list.is_empty());
+    assert!(!list.accepts(&MediaType::new(b"application", b"sdp")));
+
+    assert!(parse::media_range(b"*/sdp").is_err());
+    assert!(parse::accept(b"application/sdp,").is_err());
+
+    let mut buf = [0u8; 64];
+    let (_, list) = parse::accept(b"application/sdp, text/*;q=0.1").unwrap();
+    let n = assemble::accept(list.iter().skip(1), &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"text/*;q=0.1");
+  }
+}
@@ -0,0 +1,369 @@
+use super::Binary;
+use std::borrow::Cow;
+
+pub mod auth;
+pub mod call_id;
+pub mod contact;
+pub mod cseq;
+pub mod disposition;
+pub mod from_to;
+pub mod media_type;
+pub mod name;
+pub mod name_addr;
+pub mod numeric;
+pub mod params;
+pub mod route;
+pub mod via;
+
+pub use self::name::HeaderName;
+
+// message-header  =  field-name HCOLON field-value CRLF
+// Value is trimmed of the surrounding whitespace, folded lines are kept
+// as they appear in the input.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Header<'a> {
+  pub name: HeaderName<'a>,
+  pub value: &'a Binary
+}
+
+impl<'a> Header<'a> {
+  pub fn unfolded_value(&self) -> Cow<'a, Binary> {
+    unfold(self.value)
+  }
+}
+
+// Replaces every folded line break together with the whitespace around
+// it by a single SP, as RFC 3261 7.3.1 allows. Values without folds are
+// returned borrowed.
+pub fn unfold(value: &Binary) -> Cow<'_, Binary> {
+  if !value.contains(&b'\r') {
+    return Cow::Borrowed(value);
+  }
+
+  let is_wsp = |b: &u8| *b == b' ' || *b == b'\t';
+  let mut out = Vec::with_capacity(value.len());
+  let mut rest = value;
+  while let Some(cr) = rest.iter().position(|b| *b == b'\r') {
+    let before = &rest[..cr];
+    let kept = before.iter().rposition(|b| !is_wsp(b)).map_or(0, |i| i + 1);
+    out.extend_from_slice(&before[..kept]);
+    out.push(b' ');
+    rest = &rest[cr..];
+    let skip = rest.iter().position(|b| !is_wsp(b) && *b != b'\r' && *b != b'\n').unwrap_or(rest.len());
+    rest = &rest[skip..];
+  }
+  out.extend_from_slice(rest);
+  Cow::Owned(out)
+}
+
+// Header section which was already validated by the parser, kept as the
+// original slice including the empty line. Headers are parsed again on
+// every iteration.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub struct Headers<'a>(&'a Binary);
+
+impl<'a> Headers<'a> {
+  pub fn as_bytes(&self) -> &'a Binary {
+    self.0
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.0.len() <= 2
+  }
+
+  pub fn iter(&self) -> HeadersIter<'a> {
+    HeadersIter { rest: self.0 }
+  }
+
+  // first header with the name, compact forms included
+  pub fn get(&self, name: HeaderName) -> Option<Header<'a>> {
+    self.iter().find(|h| h.name == name)
+  }
+
+  // every header with the name in the order of appearance
+  pub fn get_all<'n>(&self, name: HeaderName<'n>) -> impl Iterator<Item = Header<'a>> + 'n
+    where 'a: 'n {
+    self.iter().filter(move |h| h.name == name)
+  }
+}
+
+impl<'a> IntoIterator for Headers<'a> {
+  type Item = Header<'a>;
+  type IntoIter = HeadersIter<'a>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+pub struct HeadersIter<'a> {
+  rest: &'a Binary
+}
+
+impl<'a> Iterator for HeadersIter<'a> {
+  type Item = Header<'a>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let (r, h) = parse::header(self.rest).ok()?;
+    self.rest = r;
+    Some(h)
+  }
+}
+
+pub mod parse {
+  use crate::msg::{Binary, Byte};
+  use crate::msg::abnf::ch::{token, crlf, hcolon};
+  use crate::msg::error::ParseError;
+  use crate::msg::header::{Header, Headers, HeaderName};
+  use crate::msg::header::auth::parse::{authentication_info, challenge, credentials};
+  use crate::msg::header::call_id::parse::call_id;
+  use crate::msg::header::contact::parse::contact;
+  use crate::msg::header::cseq::parse::cseq;
+  use crate::msg::header::disposition::parse::disposition;
+  use crate::msg::header::from_to::parse::from_to;
+  use crate::msg::header::media_type::parse::{accept, media_type};
+  use crate::msg::header::numeric::parse::{content_length, expires, max_forwards};
+  use crate::msg::header::route::parse::route;
+  use crate::msg::header::via::parse::via;
+  use nom::{IResult, Needed};
+  use nom::Err::{Error, Failure, Incomplete};
+  use nom::error::ErrorKind::CrLf;
+
+  #[inline]
+  fn is_lws_byte(b: &Byte) -> bool {
+    matches!(*b, b' ' | b'\t' | b'\r' | b'\n')
+  }
+
+  fn trim(value: &Binary) -> &Binary {
+    let start = value.iter().position(|b| !is_lws_byte(b)).unwrap_or(value.len());
+    let end = value.iter().rposition(|b| !is_lws_byte(b)).map_or(start, |e| e + 1);
+    &value[start..end]
+  }
+
+  // field-value ends at a CRLF which is not followed by whitespace,
+  // CRLF followed by whitespace is a fold
+  #[inline]
+  fn field_value(input: &Binary) -> IResult<&Binary, &Binary> {
+    let mut i = 0usize;
+    loop {
+      match input.get(i) {
+        Some(b'\r') => match (input.get(i + 1), input.get(i + 2)) {
+          (Some(b'\n'), Some(b' ')) | (Some(b'\n'), Some(b'\t')) => i += 3,
+          (Some(b'\n'), Some(_)) => return Ok((&input[i + 2..], &input[..i])),
+          (Some(b'\n'), None) | (None, _) => return Err(Incomplete(Needed::Size(1))),
+          _ => return Err(Error((&input[i..], CrLf)))
+        },
+        Some(b'\n') => return Err(Error((&input[i..], CrLf))),
+        Some(_) => i += 1,
+        None => return Err(Incomplete(Needed::Size(2)))
+      }
+    }
+  }
+
+  // message-header  =  field-name HCOLON field-value CRLF
+  // HCOLON          =  *( SP / HTAB ) ":" SWS
+  #[inline]
+  pub fn header(input: &Binary) -> IResult<&Binary, Header<'_>> {
+    let (rest, name) = token(input)?;
+    let (rest, _) = hcolon(rest)?;
+    let (rest, value) = field_value(rest)?;
+    Ok((rest, Header { name: HeaderName::from_bytes(name), value: trim(value) }))
+  }
+
+  // Values of the well-known headers matched against their own rules,
+  // which message parsing leaves to whoever takes a header apart. The
+  // failure is named by the header, offsets count from `base`.
+  pub fn validate_values(base: &Binary, headers: Headers<'_>) -> Result<(), ParseError> {
+    headers.iter().try_for_each(|h| validate_value(base, h))
+  }
+
+  // Values of the other headers are a field-value and need nothing more
+  pub fn validate_value(base: &Binary, h: Header<'_>) -> Result<(), ParseError> {
+    fn extent<O>(res: IResult<&Binary, O>) -> IResult<&Binary, ()> {
+      res.map(|(r, _)| (r, ()))
+    }
+
+    let v = h.value;
+    let (rule, res) = match h.name {
+      HeaderName::Accept => ("Accept", extent(accept(v))),
+      HeaderName::AuthenticationInfo => ("Authentication-Info", extent(authentication_info(v))),
+      HeaderName::Authorization => ("Authorization", extent(credentials(v))),
+      HeaderName::CallId => ("Call-ID", extent(call_id(v))),
+      HeaderName::Contact => ("Contact", extent(contact(v))),
+      HeaderName::ContentDisposition => ("Content-Disposition", extent(disposition(v))),
+      HeaderName::ContentLength => ("Content-Length", extent(content_length(v))),
+      HeaderName::ContentType => ("Content-Type", extent(media_type(v))),
+      HeaderName::CSeq => ("CSeq", extent(cseq(v))),
+      HeaderName::Expires => ("Expires", extent(expires(v))),
+      HeaderName::From => ("From", extent(from_to(v))),
+      HeaderName::MaxForwards => ("Max-Forwards", extent(max_forwards(v))),
+      HeaderName::ProxyAuthenticate => ("Proxy-Authenticate", extent(challenge(v))),
+      HeaderName::ProxyAuthorization => ("Proxy-Authorization", extent(credentials(v))),
+      HeaderName::RecordRoute => ("Record-Route", extent(route(v))),
+      HeaderName::Route => ("Route", extent(route(v))),
+      HeaderName::To => ("To", extent(from_to(v))),
+      HeaderName::Via => ("Via", extent(via(v))),
+      HeaderName::WwwAuthenticate => ("WWW-Authenticate", extent(challenge(v))),
+      _ => return Ok(())
+    };
+
+    match res {
+      Ok((&[], _)) => Ok(()),
+      Ok((rest, _)) | Err(Error((rest, _))) | Err(Failure((rest, _))) => Err(ParseError::at(rule, base, rest)),
+      // the value is delimited already, it can't get any longer
+      Err(Incomplete(_)) => Err(ParseError::at(rule, base, &v[v.len()..]))
+    }
+  }
+
+  // *( message-header ) CRLF
+  #[inline]
+  pub fn headers(input: &Binary) -> IResult<&Binary, Headers<'_>> {
+    let mut rest = input;
+    loop {
+      match crlf(rest) {
+        Ok((r, _)) => return Ok((r, Headers(&input[..input.len() - r.len()]))),
+        Err(Error(_)) => {
+          let (r, _) = header(rest)?;
+          rest = r;
+        },
+        Err(e) => return Err(e)
+      }
+    }
+  }
+}
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::header::Header;
+  use crate::msg::output::{Output, AssembleResult};
+
+  // header line in the long form, the value is written as is
+  pub fn header(h: Header, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.put(h.name.as_bytes())?;
+    out.put(b": ")?;
+    out.put(h.value)?;
+    out.put(b"\r\n")?;
+    out.finish()
+  }
+}
+
+pub mod owned {
+  use crate::msg::Binary;
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash, Default)]
+  pub struct Headers(Vec<u8>);
+
+  impl Headers {
+    pub fn as_bytes(&self) -> &Binary {
+      &self.0
+    }
+
+    pub fn as_borrowed(&self) -> super::Headers<'_> {
+      super::Headers(&self.0)
+    }
+  }
+
+  impl<'a> super::Headers<'a> {
+    pub fn into_owned(self) -> Headers {
+      Headers(self.0.to_vec())
+    }
+  }
+
+  impl<'a> From<super::Headers<'a>> for Headers {
+    fn from(h: super::Headers<'a>) -> Headers {
+      h.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a Headers> for super::Headers<'a> {
+    fn from(h: &'a Headers) -> super::Headers<'a> {
+      h.as_borrowed()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Header, HeaderName, parse, assemble, unfold};
+  use crate::msg::error::ParseError;
+  use std::borrow::Cow;
+  use nom::{Err::{Incomplete, Error}, Needed, error::ErrorKind::CrLf};
+
+  #[test]
+  fn header_parse_test() {
+    assert_eq!(
+      parse::header(b"Max-Forwards: 70\r\nTo"),
+      Ok((&b"To"[..], Header { name: HeaderName::MaxForwards, value: b"70" }))
+    );
+    assert_eq!(
+      parse::header(b"Subject \t:\r\n  I know you're there,\r\n\tpick up!  \r\n\r\n"),
+      Ok((&b"\r\n"[..], Header { name: HeaderName::Subject, value: b"I know you're there,\r\n\tpick up!" }))
+    );
+    assert_eq!(
+      parse::header(b"X-Empty:\r\n\r\n"),
+      Ok((&b"\r\n"[..], Header { name: HeaderName::Other(b"X-Empty"), value: b"" }))
+    );
+
+    assert_eq!(parse::header(b"Max-Forwards: 70\r\n"), Err(Incomplete(Needed::Size(1))));
+    assert_eq!(parse::header(b"Max-Forwards: 70"), Err(Incomplete(Needed::Size(2))));
+    assert_eq!(parse::header(b"Max-Forwards: 70\nTo"), Err(Error((&b"\nTo"[..], CrLf))));
+    assert!(matches!(parse::header(b"Max Forwards: 70\r\n\r\n"), Err(Error(_))));
+  }
+
+  #[test]
+  fn headers_parse_test() {
+    let input = b"Call-ID: a84b4c76e66710\r\nl: 0\r\n\r\nbody";
+    let (rest, headers) = parse::headers(input).unwrap();
+    assert_eq!(rest, b"body");
+    assert_eq!(headers.as_bytes(), &input[..input.len() - 4]);
+
+    let mut it = headers.iter();
+    assert_eq!(it.next(), Some(Header { name: HeaderName::CallId, value: b"a84b4c76e66710" }));
+    assert_eq!(it.next(), Some(Header { name: HeaderName::ContentLength, value: b"0" }));
+    assert_eq!(it.next(), None);
+
+    assert_eq!(headers.get(HeaderName::ContentLength).map(|h| h.value), Some(&b"0"[..]));
+    assert_eq!(headers.get(HeaderName::To), None);
+
+    let (_, headers) = parse::headers(b"Route: <sip:a>\r\nv: x\r\nroute: <sip:b>\r\n\r\n").unwrap();
+    let routes: Vec<_> = headers.get_all(HeaderName::Route).map(|h| h.value).collect();
+    assert_eq!(routes, vec![&b"<sip:a>"[..], b"<sip:b>"]);
+
+    let (rest, headers) = parse::headers(b"\r\n").unwrap();
+    assert_eq!(rest, b"");
+    assert!(headers.is_empty());
+    assert_eq!(headers.iter().next(), None);
+
+    assert_eq!(parse::headers(b"Call-ID: a84b4c76e66710\r\n"), Err(Incomplete(Needed::Size(1))));
+    assert_eq!(parse::headers(b"Call-ID: a84b4c76e66710\r\n\r"), Err(Incomplete(Needed::Size(2))));
+  }
+
+  #[test]
+  fn validate_values_test() {
+    let values = |input: &'static [u8]| parse::validate_values(input, parse::headers(input).unwrap().1);
+    assert_eq!(values(b"Via: SIP/2.0/UDP a.com\r\nCSeq: 1 INVITE\r\nX-Custom: {[x\r\n\r\n"), Ok(()));
+    assert_eq!(values(b"Via: SIP/2.0/UDP\r\n\r\n"), Err(ParseError::new("Via", 16)));
+    assert_eq!(values(b"v: SIP/2.0/UDP a.com x\r\n\r\n"), Err(ParseError::new("Via", 20)));
+    assert_eq!(values(b"To: <sip:b@c>\r\nCSeq: x INVITE\r\n\r\n"), Err(ParseError::new("CSeq", 21)));
+  }
+
+  #[test]
+  fn unfold_test() {
+    assert_eq!(unfold(b"a b"), Cow::Borrowed(&b"a b"[..]));
+    assert_eq!(unfold(b"I know you're there,\r\n\tpick up!").as_ref(), b"I know you're there, pick up!".as_ref());
+    assert_eq!(unfold(b"a \t\r\n  \t b\r\n c").as_ref(), b"a b c".as_ref());
+
+    let (_, h) = parse::header(b"Subject:\r\n lunch\r\n  today\r\n\r\n").unwrap();
+    assert_eq!(h.value, b"lunch\r\n  today");
+    assert_eq!(h.unfolded_value().as_ref(), b"lunch today".as_ref());
+  }
+
+  #[test]
+  fn header_assemble_test() {
+    let mut buf = [0u8; 32];
+    let (_, h) = parse::header(b"v: SIP/2.0/UDP a\r\n\r\n").unwrap();
+    let n = assemble::header(h, &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"Via: SIP/2.0/UDP a\r\n");
+  }
+}
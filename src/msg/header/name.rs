@@ -0,0 +1,194 @@
+use crate::msg::Binary;
+
+// Header names are case-insensitive, a compact form is the same header as
+// its long form. Well-known names are recognized, every other name is
+// kept as it appears in the input.
+macro_rules! header_names (
+  ($( $variant:ident = $long:literal $(| $compact:literal)?; )+) => (
+    #[derive(Debug, Copy, Clone)]
+    pub enum HeaderName<'a> {
+      $( $variant, )+
+      Other(&'a Binary)
+    }
+
+    impl<'a> HeaderName<'a> {
+      pub fn from_bytes(name: &'a Binary) -> HeaderName<'a> {
+        $(
+          if name.eq_ignore_ascii_case($long) $(|| name.eq_ignore_ascii_case($compact))? {
+            return HeaderName::$variant;
+          }
+        )+
+        HeaderName::Other(name)
+      }
+
+      // long form of a well-known name
+      pub fn as_bytes(&self) -> &'a Binary {
+        match self {
+          $( HeaderName::$variant => $long, )+
+          HeaderName::Other(name) => name
+        }
+      }
+
+      pub fn compact(&self) -> Option<&'static Binary> {
+        match self {
+          $( HeaderName::$variant => header_names!(@compact $($compact)?), )+
+          HeaderName::Other(_) => None
+        }
+      }
+    }
+
+    pub mod owned {
+      use crate::msg::Binary;
+
+      #[derive(Debug, Clone)]
+      pub enum HeaderName {
+        $( $variant, )+
+        Other(Vec<u8>)
+      }
+
+      impl HeaderName {
+        pub fn as_borrowed(&self) -> super::HeaderName<'_> {
+          match self {
+            $( HeaderName::$variant => super::HeaderName::$variant, )+
+            HeaderName::Other(name) => super::HeaderName::Other(name)
+          }
+        }
+
+        pub fn as_bytes(&self) -> &Binary {
+          self.as_borrowed().as_bytes()
+        }
+      }
+
+      impl<'a> super::HeaderName<'a> {
+        pub fn into_owned(self) -> HeaderName {
+          match self {
+            $( super::HeaderName::$variant => HeaderName::$variant, )+
+            super::HeaderName::Other(name) => HeaderName::Other(name.to_vec())
+          }
+        }
+      }
+
+      impl PartialEq for HeaderName {
+        fn eq(&self, other: &HeaderName) -> bool {
+          self.as_borrowed() == other.as_borrowed()
+        }
+      }
+
+      impl Eq for HeaderName {}
+
+      impl std::hash::Hash for HeaderName {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+          self.as_bytes().iter().for_each(|b| state.write_u8(b.to_ascii_lowercase()));
+        }
+      }
+    }
+  );
+  (@compact) => (None);
+  (@compact $compact:literal) => (Some($compact));
+);
+
+header_names! {
+  Accept = b"Accept";
+  AcceptEncoding = b"Accept-Encoding";
+  AcceptLanguage = b"Accept-Language";
+  AlertInfo = b"Alert-Info";
+  Allow = b"Allow";
+  AllowEvents = b"Allow-Events" | b"u";
+  AuthenticationInfo = b"Authentication-Info";
+  Authorization = b"Authorization";
+  CallId = b"Call-ID" | b"i";
+  CallInfo = b"Call-Info";
+  Contact = b"Contact" | b"m";
+  ContentDisposition = b"Content-Disposition";
+  ContentEncoding = b"Content-Encoding" | b"e";
+  ContentId = b"Content-ID";
+  ContentLanguage = b"Content-Language";
+  ContentLength = b"Content-Length" | b"l";
+  ContentType = b"Content-Type" | b"c";
+  CSeq = b"CSeq";
+  Date = b"Date";
+  ErrorInfo = b"Error-Info";
+  Event = b"Event" | b"o";
+  Expires = b"Expires";
+  From = b"From" | b"f";
+  Identity = b"Identity" | b"y";
+  InReplyTo = b"In-Reply-To";
+  MaxForwards = b"Max-Forwards";
+  MimeVersion = b"MIME-Version";
+  MinExpires = b"Min-Expires";
+  MinSe = b"Min-SE";
+  Organization = b"Organization";
+  Path = b"Path";
+  Priority = b"Priority";
+  ProxyAuthenticate = b"Proxy-Authenticate";
+  ProxyAuthorization = b"Proxy-Authorization";
+  ProxyRequire = b"Proxy-Require";
+  RAck = b"RAck";
+  Reason = b"Reason";
+  RecordRoute = b"Record-Route";
+  ReferTo = b"Refer-To" | b"r";
+  ReferredBy = b"Referred-By" | b"b";
+  ReplyTo = b"Reply-To";
+  Require = b"Require";
+  RetryAfter = b"Retry-After";
+  Route = b"Route";
+  RSeq = b"RSeq";
+  Server = b"Server";
+  ServiceRoute = b"Service-Route";
+  SessionExpires = b"Session-Expires" | b"x";
+  Subject = b"Subject" | b"s";
+  SubscriptionState = b"Subscription-State";
+  Supported = b"Supported" | b"k";
+  Timestamp = b"Timestamp";
+  To = b"To" | b"t";
+  Unsupported = b"Unsupported";
+  UserAgent = b"User-Agent";
+  Via = b"Via" | b"v";
+  Warning = b"Warning";
+  WwwAuthenticate = b"WWW-Authenticate";
+}
+
+impl<'a, 'b> PartialEq<HeaderName<'b>> for HeaderName<'a> {
+  fn eq(&self, other: &HeaderName<'b>) -> bool {
+    self.as_bytes().eq_ignore_ascii_case(other.as_bytes())
+  }
+}
+
+impl<'a> Eq for HeaderName<'a> {}
+
+#[cfg(test)]
+mod tests {
+  use super::{HeaderName, owned};
+  use std::collections::HashSet;
+
+  #[test]
+  fn header_name_test() {
+    assert_eq!(HeaderName::from_bytes(b"v"), HeaderName::Via);
+    assert_eq!(HeaderName::from_bytes(b"VIA"), HeaderName::Via);
+    assert_eq!(HeaderName::from_bytes(b"call-id"), HeaderName::CallId);
+    assert_eq!(HeaderName::from_bytes(b"I"), HeaderName::CallId);
+    assert!(matches!(HeaderName::from_bytes(b"X-Custom"), HeaderName::Other(b"X-Custom")));
+    assert!(matches!(HeaderName::from_bytes(b"z"), HeaderName::Other(b"z")));
+
+    assert_eq!(HeaderName::Other(b"x-custom"), HeaderName::Other(b"X-CUSTOM"));
+    assert_eq!(HeaderName::Other(b"cseq"), HeaderName::CSeq);
+    assert_ne!(HeaderName::To, HeaderName::From);
+
+    assert_eq!(HeaderName::from_bytes(b"l").as_bytes(), b"Content-Length");
+    assert_eq!(HeaderName::ContentType.compact(), Some(&b"c"[..]));
+    assert_eq!(HeaderName::CSeq.compact(), None);
+    assert_eq!(HeaderName::Other(b"X").compact(), None);
+  }
+
+  #[test]
+  fn owned_header_name_test() {
+    let mut names = HashSet::new();
+    names.insert(HeaderName::from_bytes(b"x-custom").into_owned());
+    names.insert(HeaderName::from_bytes(b"m").into_owned());
+
+    assert!(names.contains(&owned::HeaderName::Other(b"X-Custom".to_vec())));
+    assert!(names.contains(&owned::HeaderName::Contact));
+    assert!(!names.contains(&owned::HeaderName::To));
+    assert_eq!(owned::HeaderName::Contact.as_borrowed(), HeaderName::Contact);
+  }
+}
@@ -0,0 +1,330 @@
+use crate::msg::Binary;
+use crate::msg::abnf::userinfo::unquote;
+use crate::msg::uri::Uri;
+use std::borrow::Cow;
+
+// display-name   =  *(token LWS)/ quoted-string
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum DisplayName<'a> {
+  // content of the quoted-string, quoted-pairs are kept as they are
+  Quoted(&'a Binary),
+  // token words together with the whitespace between them
+  Tokens(&'a Binary)
+}
+
+impl<'a> DisplayName<'a> {
+  pub fn as_bytes(&self) -> &'a Binary {
+    match self {
+      DisplayName::Quoted(name) => name,
+      DisplayName::Tokens(name) => name
+    }
+  }
+
+  // Text of the name: every quoted-pair is replaced by the character
+  // it escapes, whitespace between token words becomes a single SP.
+  // Names which need neither are returned borrowed.
+  pub fn unescaped(&self) -> Cow<'a, Binary> {
+    match *self {
+      DisplayName::Quoted(name) => unquote(name),
+      DisplayName::Tokens(name) => {
+        let is_ws = |b: &u8| matches!(*b, b' ' | b'\t' | b'\r' | b'\n');
+        let single_spaced = name.windows(2).all(|w| !is_ws(&w[0]) || !is_ws(&w[1]))
+          && name.iter().all(|b| !is_ws(b) || *b == b' ');
+        if single_spaced {
+          return Cow::Borrowed(name);
+        }
+        let words: Vec<&[u8]> = name.split(is_ws).filter(|w| !w.is_empty()).collect();
+        Cow::Owned(words.join(&b' '))
+      }
+    }
+  }
+}
+
+// name-addr      =  [ display-name ] LAQUOT addr-spec RAQUOT
+// addr-spec      =  SIP-URI / SIPS-URI / absoluteURI
+// A bare addr-spec is the same address without a display name.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct NameAddr<'a> {
+  pub display_name: Option<DisplayName<'a>>,
+  pub uri: Uri<'a>
+}
+
+pub mod parse {
+  use crate::msg::Binary;
+  use crate::msg::abnf::whole;
+  use crate::msg::abnf::ch::{lws, sws, value_token};
+  use crate::msg::abnf::userinfo::quoted_string;
+  use crate::msg::uri::{Uri, parse::uri};
+  use super::{DisplayName, NameAddr};
+  use nom::IResult;
+  use nom::Err::Error;
+  use nom::error::ErrorKind::{Char, Verify};
+
+  #[inline]
+  fn angle(input: &Binary, b: u8) -> IResult<&Binary, &Binary> {
+    match input.first() {
+      Some(i) if *i == b => Ok((&input[1..], &input[..1])),
+      _ => Err(Error((input, Char)))
+    }
+  }
+
+  // display-name   =  *(token LWS)/ quoted-string
+  #[inline]
+  pub fn display_name(input: &Binary) -> IResult<&Binary, DisplayName<'_>> {
+    if input.first() == Some(&b'"') {
+      let (rest, name) = quoted_string(input)?;
+      return Ok((rest, DisplayName::Quoted(name)));
+    }
+
+    let (mut rest, _) = value_token(input)?;
+    while let Ok((r, _)) = lws(rest).and_then(|(r, _)| value_token(r)) {
+      rest = r;
+    }
+    Ok((rest, DisplayName::Tokens(&input[..input.len() - rest.len()])))
+  }
+
+  // name-addr      =  [ display-name ] LAQUOT addr-spec RAQUOT
+  // LAQUOT  =  SWS "<"
+  // RAQUOT  =  ">" SWS
+  // The trailing SWS is left to the separator which follows.
+  #[inline]
+  pub fn name_addr(input: &Binary) -> IResult<&Binary, NameAddr<'_>> {
+    let (rest, display_name) = match display_name(input) {
+      Ok((r, name)) => (r, Some(name)),
+      Err(_) => (input, None)
+    };
+    let (rest, _) = sws(rest)?;
+    let (rest, _) = angle(rest, b'<')?;
+    let (rest, uri) = uri(rest)?;
+    let (rest, _) = angle(rest, b'>')?;
+    Ok((rest, NameAddr { display_name, uri }))
+  }
+
+  // addr-spec      =  SIP-URI / SIPS-URI / absoluteURI
+  // Outside of angle brackets a semicolon starts header parameters
+  // rather than URI ones (RFC 3261 20.10), so the URI ends at the first
+  // ";", "," or whitespace.
+  #[inline]
+  pub fn addr_spec(input: &Binary) -> IResult<&Binary, Uri<'_>> {
+    let len = input.iter()
+      .position(|b| matches!(*b, b';' | b',' | b' ' | b'\t' | b'\r' | b'\n'))
+      .unwrap_or(input.len());
+    match whole(uri(&input[..len])) {
+      Some(u) => Ok((&input[len..], u)),
+      None => Err(Error((input, Verify)))
+    }
+  }
+
+  // name-addr / addr-spec
+  #[inline]
+  pub fn address(input: &Binary) -> IResult<&Binary, NameAddr<'_>> {
+    match name_addr(input) {
+      Ok(r) => Ok(r),
+      Err(_) => {
+        let (rest, uri) = addr_spec(input)?;
+        Ok((rest, NameAddr { display_name: None, uri }))
+      }
+    }
+  }
+}
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::uri::assemble::uri;
+  use crate::msg::output::{Output, AssembleResult};
+  use super::{DisplayName, NameAddr};
+
+  // quoted-string out of application text, DQUOTE and backslash are
+  // the only characters which need a quoted-pair
+  pub fn quoted_string(text: &Binary, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.put_byte(b'"')?;
+    for &b in text {
+      if b == b'"' || b == b'\\' {
+        out.put_byte(b'\\')?;
+      }
+      out.put_byte(b)?;
+    }
+    out.put_byte(b'"')?;
+    out.finish()
+  }
+
+  pub fn display_name(name: DisplayName, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    match name {
+      DisplayName::Quoted(name) => {
+        out.put_byte(b'"')?;
+        out.put(name)?;
+        out.put_byte(b'"')?;
+      },
+      DisplayName::Tokens(name) => out.put(name)?
+    }
+    out.finish()
+  }
+
+  // Always the name-addr form, so URI parameters never get mistaken for
+  // header ones
+  pub fn name_addr(a: NameAddr, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    if let Some(name) = a.display_name {
+      out.with(|b| display_name(name, b))?;
+      out.put_byte(b' ')?;
+    }
+    out.put_byte(b'<')?;
+    out.with(|b| uri(a.uri, b))?;
+    out.put_byte(b'>')?;
+    out.finish()
+  }
+}
+
+pub mod owned {
+  use crate::msg::Binary;
+  use crate::msg::uri::owned::Uri;
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub enum DisplayName {
+    Quoted(Vec<u8>),
+    Tokens(Vec<u8>)
+  }
+
+  impl DisplayName {
+    pub fn as_bytes(&self) -> &Binary {
+      self.as_borrowed().as_bytes()
+    }
+
+    pub fn as_borrowed(&self) -> super::DisplayName<'_> {
+      match self {
+        DisplayName::Quoted(name) => super::DisplayName::Quoted(name),
+        DisplayName::Tokens(name) => super::DisplayName::Tokens(name)
+      }
+    }
+  }
+
+  impl<'a> super::DisplayName<'a> {
+    pub fn into_owned(self) -> DisplayName {
+      match self {
+        super::DisplayName::Quoted(name) => DisplayName::Quoted(name.to_vec()),
+        super::DisplayName::Tokens(name) => DisplayName::Tokens(name.to_vec())
+      }
+    }
+  }
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub struct NameAddr {
+    pub display_name: Option<DisplayName>,
+    pub uri: Uri
+  }
+
+  impl NameAddr {
+    pub fn as_borrowed(&self) -> super::NameAddr<'_> {
+      super::NameAddr {
+        display_name: self.display_name.as_ref().map(DisplayName::as_borrowed),
+        uri: self.uri.as_borrowed()
+      }
+    }
+  }
+
+  impl<'a> super::NameAddr<'a> {
+    pub fn into_owned(self) -> NameAddr {
+      NameAddr {
+        display_name: self.display_name.map(super::DisplayName::into_owned),
+        uri: self.uri.into_owned()
+      }
+    }
+  }
+
+  impl<'a> From<super::NameAddr<'a>> for NameAddr {
+    fn from(a: super::NameAddr<'a>) -> NameAddr {
+      a.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a NameAddr> for super::NameAddr<'a> {
+    fn from(a: &'a NameAddr) -> super::NameAddr<'a> {
+      a.as_borrowed()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{DisplayName, NameAddr, parse, assemble};
+  use crate::msg::uri::Uri;
+  use std::borrow::Cow;
+
+  #[test]
+  fn display_name_test() {
+    assert_eq!(parse::display_name(b"Bob <sip:b>"), Ok((&b" <sip:b>"[..], DisplayName::Tokens(b"Bob"))));
+    assert_eq!(parse::display_name(b"Lee M. Foote <sip:b>"), Ok((&b" <sip:b>"[..], DisplayName::Tokens(b"Lee M. Foote"))));
+    assert_eq!(
+      parse::display_name(b"\"Mr. \\\"Watson\\\"\"<sip:b>"),
+      Ok((&b"<sip:b>"[..], DisplayName::Quoted(b"Mr. \\\"Watson\\\"")))
+    );
+    assert!(parse::display_name(b"<sip:b>").is_err());
+
+    assert_eq!(DisplayName::Quoted(b"Mr. \\\"Watson\\\" \\\\o/").unescaped().as_ref(), b"Mr. \"Watson\" \\o/".as_ref());
+    assert_eq!(DisplayName::Quoted(b"Alice").unescaped(), Cow::Borrowed(&b"Alice"[..]));
+    assert_eq!(DisplayName::Tokens(b"Lee M. Foote").unescaped(), Cow::Borrowed(&b"Lee M. Foote"[..]));
+    assert_eq!(DisplayName::Tokens(b"Lee \t M.\r\n Foote").unescaped().as_ref(), b"Lee M. Foote".as_ref());
+  }
+
+  #[test]
+  fn address_test() {
+    let (rest, a) = parse::address(b"\"A. G. Bell\" <sip:agb@bell-telephone.com> ;tag=a48s").unwrap();
+    assert_eq!(rest, b" ;tag=a48s");
+    assert_eq!(a.display_name, Some(DisplayName::Quoted(b"A. G. Bell")));
+    assert!(matches!(a.uri, Uri::Sip(u) if u.user == Some(&b"agb"[..])));
+
+    // parameters inside of the brackets belong to the URI
+    let (rest, a) = parse::address(b"<sip:b.com;lr>;x").unwrap();
+    assert_eq!(rest, b";x");
+    assert!(matches!(a.uri, Uri::Sip(u) if u.parameters.as_bytes() == b";lr"));
+
+    // and outside of them to the header
+    let (rest, a) = parse::address(b"sip:+12125551212@server.phone2net.com;tag=887s").unwrap();
+    assert_eq!(rest, b";tag=887s");
+    assert_eq!(a.display_name, None);
+    assert!(matches!(a.uri, Uri::Sip(u) if u.parameters.is_empty()));
+
+    let (rest, a) = parse::address(b"Anonymous<tel:+1-212-555-1212>, x").unwrap();
+    assert_eq!(rest, b", x");
+    assert_eq!(a.display_name, Some(DisplayName::Tokens(b"Anonymous")));
+    assert!(matches!(a.uri, Uri::Absolute(_)));
+
+    assert!(parse::address(b"<sip:a@b.com").is_err());
+    assert!(parse::address(b"Bob sip:a@b.com").is_err());
+    assert!(parse::address(b"\"Bob\"").is_err());
+    assert!(parse::addr_spec(b"sip:a@b.com?").is_err());
+  }
+
+  #[test]
+  fn name_addr_assemble_test() {
+    let mut buf = [0u8; 64];
+    for (input, expected) in &[
+      (&b"\"A. G. Bell\"<sip:agb@bell-telephone.com>"[..], &b"\"A. G. Bell\" <sip:agb@bell-telephone.com>"[..]),
+      (b"Bob  <sips:bob@biloxi.com;transport=tcp>", b"Bob <sips:bob@biloxi.com;transport=tcp>"),
+      (b"sip:carol@chicago.com", b"<sip:carol@chicago.com>")
+    ] {
+      let (_, a) = parse::address(input).unwrap();
+      let n = assemble::name_addr(a, &mut buf).unwrap();
+      assert_eq!(&buf[..n], *expected);
+    }
+
+    let n = assemble::quoted_string(b"say \"hi\" \\o", &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"\"say \\\"hi\\\" \\\\o\"");
+    let (_, name) = parse::display_name(&buf[..n]).unwrap();
+    assert_eq!(name.unescaped().as_ref(), b"say \"hi\" \\o".as_ref());
+  }
+
+  #[test]
+  fn name_addr_owned_test() {
+    let owned = {
+      let input = b"Bob <sip:bob@biloxi.com>".to_vec();
+      let (_, a) = parse::name_addr(&input).unwrap();
+      a.into_owned()
+    };
+    let a: NameAddr = (&owned).into();
+    assert_eq!(a.display_name.map(|n| n.as_bytes()), Some(&b"Bob"[..]));
+    assert_eq!(owned.display_name.as_ref().map(|n| n.as_bytes()), Some(&b"Bob"[..]));
+  }
+}
@@ -0,0 +1,89 @@
+// Headers which carry a single number. Values which don't fit into the
+// type are errors, nothing is wrapped or clamped.
+
+pub mod parse {
+  use crate::msg::Binary;
+  use crate::msg::abnf::digit::decimal;
+  use crate::msg::header::params::parse::delta_seconds;
+  use nom::IResult;
+
+  // Max-Forwards  =  "Max-Forwards" HCOLON 1*DIGIT
+  // RFC 3261 20.22 limits it to 0-255
+  #[inline]
+  pub fn max_forwards(input: &Binary) -> IResult<&Binary, u8> {
+    let (rest, value) = decimal(input, u8::MAX as u64)?;
+    Ok((rest, value as u8))
+  }
+
+  // Content-Length  =  ( "Content-Length" / "l" ) HCOLON 1*DIGIT
+  #[inline]
+  pub fn content_length(input: &Binary) -> IResult<&Binary, usize> {
+    let (rest, value) = decimal(input, usize::MAX as u64)?;
+    Ok((rest, value as usize))
+  }
+
+  // Expires     =  "Expires" HCOLON delta-seconds
+  #[inline]
+  pub fn expires(input: &Binary) -> IResult<&Binary, u32> {
+    delta_seconds(input)
+  }
+}
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::output::{Output, AssembleResult};
+
+  pub fn max_forwards(value: u8, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.put_number(value as u64)?;
+    out.finish()
+  }
+
+  pub fn content_length(value: usize, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.put_number(value as u64)?;
+    out.finish()
+  }
+
+  pub fn expires(value: u32, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.put_number(value as u64)?;
+    out.finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{parse, assemble};
+  use nom::{Err::Error, error::ErrorKind::Digit};
+
+  #[test]
+  fn numeric_parse_test() {
+    assert_eq!(parse::max_forwards(b"70"), Ok((&b""[..], 70)));
+    assert_eq!(parse::max_forwards(b"255"), Ok((&b""[..], 255)));
+    assert_eq!(parse::max_forwards(b"256"), Err(Error((&b"256"[..], Digit))));
+    assert_eq!(parse::max_forwards(b"x"), Err(Error((&b"x"[..], Digit))));
+
+    assert_eq!(parse::content_length(b"0"), Ok((&b""[..], 0)));
+    assert_eq!(parse::content_length(b"349 "), Ok((&b" "[..], 349)));
+    assert_eq!(
+      parse::content_length(b"99999999999999999999999"),
+      Err(Error((&b"99999999999999999999999"[..], Digit)))
+    );
+
+    assert_eq!(parse::expires(b"5"), Ok((&b""[..], 5)));
+    assert_eq!(parse::expires(b"4294967295"), Ok((&b""[..], u32::MAX)));
+    assert_eq!(parse::expires(b"4294967296"), Err(Error((&b"4294967296"[..], Digit))));
+  }
+
+  #[test]
+  fn numeric_assemble_test() {
+    let mut buf = [0u8; 10];
+    let n = assemble::max_forwards(70, &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"70");
+    let n = assemble::content_length(0, &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"0");
+    let n = assemble::expires(u32::MAX, &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"4294967295");
+  }
+}
@@ -0,0 +1,302 @@
+use crate::msg::Binary;
+
+// *( SEMI generic-param ) which was already validated by the parser,
+// kept as the original slice starting at the first SEMI
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub struct GenericParams<'a>(&'a Binary);
+
+impl<'a> GenericParams<'a> {
+  pub fn as_bytes(&self) -> &'a Binary {
+    self.0
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  pub fn iter(&self) -> GenericParamsIter<'a> {
+    GenericParamsIter { rest: self.0 }
+  }
+
+  // Value of the first parameter with the name, names are
+  // case-insensitive. None when there is no such parameter,
+  // Some(None) when it has no value.
+  pub fn get(&self, name: &Binary) -> Option<Option<&'a Binary>> {
+    self.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v)
+  }
+}
+
+impl<'a> IntoIterator for GenericParams<'a> {
+  type Item = (&'a Binary, Option<&'a Binary>);
+  type IntoIter = GenericParamsIter<'a>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+pub struct GenericParamsIter<'a> {
+  rest: &'a Binary
+}
+
+impl<'a> Iterator for GenericParamsIter<'a> {
+  type Item = (&'a Binary, Option<&'a Binary>);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let (r, param) = parse::next_param(self.rest).ok()?;
+    self.rest = r;
+    Some(param)
+  }
+}
+
+// Preference of a contact or a media range in thousandths,
+// 1000 is the most preferred
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash)]
+pub struct QValue(u16);
+
+impl QValue {
+  pub const MAX: QValue = QValue(1000);
+
+  pub fn new(thousandths: u16) -> Option<QValue> {
+    if thousandths <= 1000 {
+      Some(QValue(thousandths))
+    } else {
+      None
+    }
+  }
+
+  pub fn as_thousandths(&self) -> u16 {
+    self.0
+  }
+}
+
+impl Default for QValue {
+  fn default() -> QValue {
+    QValue::MAX
+  }
+}
+
+pub mod parse {
+  use crate::msg::{Binary, Byte};
+  use crate::msg::abnf::ch::{is_token_char, value_token, equal, semi};
+  use crate::msg::abnf::digit::decimal;
+  use crate::msg::abnf::userinfo::quoted_string;
+  use super::{GenericParams, QValue};
+  use nom::IResult;
+  use nom::Err::Error;
+  use nom::error::ErrorKind::{TakeWhile1, Digit, Verify};
+
+  // gen-value      =  token / host / quoted-string
+  // A quoted-string is kept with its quotes. Otherwise the value spans
+  // token and host characters, the bare IPv6 address of via-received
+  // included; parameters with a known meaning check their value later.
+  #[inline]
+  pub fn gen_value(input: &Binary) -> IResult<&Binary, &Binary> {
+    if input.first() == Some(&b'"') {
+      let (rest, _) = quoted_string(input)?;
+      return Ok((rest, &input[..input.len() - rest.len()]));
+    }
+
+    let is_value_char = |i: &Byte| is_token_char(*i) || matches!(*i, b':' | b'[' | b']');
+    let len = input.iter().take_while(|i| is_value_char(i)).count();
+    if len == 0 {
+      return Err(Error((input, TakeWhile1)));
+    }
+    Ok((&input[len..], &input[..len]))
+  }
+
+  // generic-param  =  token [ EQUAL gen-value ]
+  #[inline]
+  pub fn generic_param(input: &Binary) -> IResult<&Binary, (&Binary, Option<&Binary>)> {
+    let (rest, name) = value_token(input)?;
+    match equal(rest) {
+      Ok((r, _)) => {
+        let (r, value) = gen_value(r)?;
+        Ok((r, (name, Some(value))))
+      },
+      Err(_) => Ok((rest, (name, None)))
+    }
+  }
+
+  // SEMI generic-param
+  #[inline]
+  pub fn next_param(input: &Binary) -> IResult<&Binary, (&Binary, Option<&Binary>)> {
+    let (rest, _) = semi(input)?;
+    generic_param(rest)
+  }
+
+  // *( SEMI generic-param )
+  #[inline]
+  pub fn generic_params(input: &Binary) -> IResult<&Binary, GenericParams<'_>> {
+    let mut rest = input;
+    while let Ok((r, _)) = next_param(rest) {
+      rest = r;
+    }
+    Ok((rest, GenericParams(&input[..input.len() - rest.len()])))
+  }
+
+  // qvalue         =  ( "0" [ "." 0*3DIGIT ] )
+  //                   / ( "1" [ "." 0*3("0") ] )
+  #[inline]
+  pub fn qvalue(input: &Binary) -> IResult<&Binary, QValue> {
+    let int = match input.first() {
+      Some(d @ b'0'..=b'1') => (d - b'0') as u16,
+      _ => return Err(Error((input, Digit)))
+    };
+
+    let mut len = 1usize;
+    let mut value = int * 1000;
+    if input.get(1) == Some(&b'.') {
+      len += 1;
+      let mut scale = 100u16;
+      while let Some(d @ b'0'..=b'9') = input.get(len) {
+        if scale == 0 {
+          break;
+        }
+        value += (d - b'0') as u16 * scale;
+        scale /= 10;
+        len += 1;
+      }
+    }
+
+    match QValue::new(value) {
+      Some(q) => Ok((&input[len..], q)),
+      None => Err(Error((input, Verify)))
+    }
+  }
+
+  // delta-seconds  =  1*DIGIT
+  // RFC 3261 limits it to (2**32)-1
+  #[inline]
+  pub fn delta_seconds(input: &Binary) -> IResult<&Binary, u32> {
+    let (rest, value) = decimal(input, u32::MAX as u64)?;
+    Ok((rest, value as u32))
+  }
+}
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::output::{Output, AssembleResult};
+  use super::QValue;
+
+  // shortest form, trailing zeros are dropped
+  pub fn qvalue(q: QValue, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    let value = q.as_thousandths();
+    out.put_byte(b'0' + (value / 1000) as u8)?;
+    let mut fraction = value % 1000;
+    if fraction != 0 {
+      out.put_byte(b'.')?;
+      let mut scale = 100u16;
+      while fraction != 0 {
+        out.put_byte(b'0' + (fraction / scale) as u8)?;
+        fraction %= scale;
+        scale /= 10;
+      }
+    }
+    out.finish()
+  }
+}
+
+pub mod owned {
+  use crate::msg::Binary;
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash, Default)]
+  pub struct GenericParams(Vec<u8>);
+
+  impl GenericParams {
+    pub fn as_bytes(&self) -> &Binary {
+      &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+      self.0.is_empty()
+    }
+
+    pub fn as_borrowed(&self) -> super::GenericParams<'_> {
+      super::GenericParams(&self.0)
+    }
+  }
+
+  impl<'a> super::GenericParams<'a> {
+    pub fn into_owned(self) -> GenericParams {
+      GenericParams(self.0.to_vec())
+    }
+  }
+
+  impl<'a> From<super::GenericParams<'a>> for GenericParams {
+    fn from(p: super::GenericParams<'a>) -> GenericParams {
+      p.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a GenericParams> for super::GenericParams<'a> {
+    fn from(p: &'a GenericParams) -> super::GenericParams<'a> {
+      p.as_borrowed()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::QValue;
+  use super::parse::{generic_param, generic_params, gen_value, qvalue, delta_seconds};
+  use super::assemble;
+  use nom::{Err::Error, error::ErrorKind::{Digit, Verify}};
+
+  #[test]
+  fn generic_param_test() {
+    assert_eq!(generic_param(b"lr"), Ok((&b""[..], (&b"lr"[..], None))));
+    assert_eq!(generic_param(b"tag=1928301774;x"), Ok((&b";x"[..], (&b"tag"[..], Some(&b"1928301774"[..])))));
+    assert_eq!(generic_param(b"x = \"a;b\" "), Ok((&b" "[..], (&b"x"[..], Some(&b"\"a;b\""[..])))));
+    assert_eq!(generic_param(b"received=2001:db8::9"), Ok((&b""[..], (&b"received"[..], Some(&b"2001:db8::9"[..])))));
+    assert!(generic_param(b"x=;").is_err());
+    assert!(generic_param(b"=x").is_err());
+
+    assert_eq!(gen_value(b"[::1],"), Ok((&b","[..], &b"[::1]"[..])));
+    assert!(gen_value(b"\"open").is_err());
+  }
+
+  #[test]
+  fn generic_params_test() {
+    let (rest, params) = generic_params(b" ;tag=a6c85cf ; Expires=60;lr, <sip:b>").unwrap();
+    assert_eq!(rest, b", <sip:b>");
+    assert_eq!(params.as_bytes(), b" ;tag=a6c85cf ; Expires=60;lr");
+    assert_eq!(params.get(b"tag"), Some(Some(&b"a6c85cf"[..])));
+    assert_eq!(params.get(b"expires"), Some(Some(&b"60"[..])));
+    assert_eq!(params.get(b"lr"), Some(None));
+    assert_eq!(params.get(b"q"), None);
+    assert_eq!(params.iter().count(), 3);
+
+    let (rest, params) = generic_params(b"x").unwrap();
+    assert_eq!(rest, b"x");
+    assert!(params.is_empty());
+  }
+
+  #[test]
+  fn qvalue_test() {
+    assert_eq!(qvalue(b"0.7"), Ok((&b""[..], QValue::new(700).unwrap())));
+    assert_eq!(qvalue(b"1"), Ok((&b""[..], QValue::MAX)));
+    assert_eq!(qvalue(b"1.000;"), Ok((&b";"[..], QValue::MAX)));
+    assert_eq!(qvalue(b"0."), Ok((&b""[..], QValue::new(0).unwrap())));
+    assert_eq!(qvalue(b"0.0015"), Ok((&b"5"[..], QValue::new(1).unwrap())));
+    assert_eq!(qvalue(b"1.5"), Err(Error((&b"1.5"[..], Verify))));
+    assert_eq!(qvalue(b"2"), Err(Error((&b"2"[..], Digit))));
+    assert_eq!(qvalue(b".5"), Err(Error((&b".5"[..], Digit))));
+
+    let mut buf = [0u8; 5];
+    for (q, s) in &[(1000u16, &b"1"[..]), (0, b"0"), (500, b"0.5"), (25, b"0.025"), (120, b"0.12")] {
+      let n = assemble::qvalue(QValue::new(*q).unwrap(), &mut buf).unwrap();
+      assert_eq!(&buf[..n], *s);
+    }
+  }
+
+  #[test]
+  fn delta_seconds_test() {
+    assert_eq!(delta_seconds(b"3600"), Ok((&b""[..], 3600)));
+    assert_eq!(delta_seconds(b"0;x"), Ok((&b";x"[..], 0)));
+    assert_eq!(delta_seconds(b"4294967295"), Ok((&b""[..], u32::MAX)));
+    assert_eq!(delta_seconds(b"4294967296"), Err(Error((&b"4294967296"[..], Digit))));
+    assert_eq!(delta_seconds(b"x"), Err(Error((&b"x"[..], Digit))));
+  }
+}
@@ -0,0 +1,262 @@
+use crate::msg::Binary;
+use crate::msg::abnf::whole;
+use crate::msg::abnf::uri_params::UriParam;
+use crate::msg::header::{Headers, HeaderName};
+use crate::msg::header::name_addr::NameAddr;
+use crate::msg::header::params::GenericParams;
+use crate::msg::uri::Uri;
+
+// route-param    =  name-addr *( SEMI rr-param )
+// rec-route      =  name-addr *( SEMI rr-param )
+// path-value     =  name-addr *( SEMI rr-param )
+// sr-value       =  name-addr *( SEMI rr-param )
+// rr-param       =  generic-param
+// Route, Record-Route, Path (RFC 3327) and Service-Route (RFC 3608)
+// entries are the same, so they share the type.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Route<'a> {
+  pub address: NameAddr<'a>,
+  pub params: GenericParams<'a>
+}
+
+impl<'a> Route<'a> {
+  // entry without display name and parameters, e.g. the remote target
+  // which goes to the end of a strict route
+  pub fn new(uri: Uri<'a>) -> Route<'a> {
+    Route { address: NameAddr { display_name: None, uri }, params: GenericParams::default() }
+  }
+
+  pub fn uri(&self) -> Uri<'a> {
+    self.address.uri
+  }
+
+  // Element this entry points to is a loose router. "lr=on" of early
+  // implementations is taken as well.
+  pub fn is_loose(&self) -> bool {
+    match self.address.uri {
+      Uri::Sip(u) | Uri::Sips(u) => u.parameters.iter().any(|p| match p {
+        UriParam::Lr => true,
+        UriParam::Other(name, _) => name.eq_ignore_ascii_case(b"lr"),
+        _ => false
+      }),
+      Uri::Absolute(_) => false
+    }
+  }
+}
+
+// Entries of a single header, every entry is parsed again on iteration
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct RouteList<'a>(&'a Binary);
+
+impl<'a> RouteList<'a> {
+  pub fn as_bytes(&self) -> &'a Binary {
+    self.0
+  }
+
+  pub fn iter(&self) -> RouteListIter<'a> {
+    RouteListIter { rest: self.0, first: true }
+  }
+}
+
+impl<'a> IntoIterator for RouteList<'a> {
+  type Item = Route<'a>;
+  type IntoIter = RouteListIter<'a>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+pub struct RouteListIter<'a> {
+  rest: &'a Binary,
+  first: bool
+}
+
+impl<'a> Iterator for RouteListIter<'a> {
+  type Item = Route<'a>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let rest = if self.first {
+      self.rest
+    } else {
+      parse::comma(self.rest).ok()?.0
+    };
+    let (r, route) = parse::route_param(rest).ok()?;
+    self.first = false;
+    self.rest = r;
+    Some(route)
+  }
+}
+
+// Every entry of the Route, Record-Route, Path or Service-Route headers
+// of a message from the topmost one down. A malformed header ends the
+// sequence.
+pub fn routes<'a, 'n>(headers: Headers<'a>, name: HeaderName<'n>) -> impl Iterator<Item = Route<'a>> + 'n
+  where 'a: 'n {
+  headers.get_all(name)
+    .map(|h| whole(parse::route(h.value)))
+    .take_while(Option::is_some)
+    .flat_map(|list| list.into_iter().flatten())
+}
+
+pub mod parse {
+  use crate::msg::Binary;
+  use crate::msg::header::name_addr::parse::name_addr;
+  use crate::msg::header::params::parse::generic_params;
+  use super::{Route, RouteList};
+  use nom::IResult;
+
+  pub(super) use crate::msg::abnf::ch::comma;
+
+  // name-addr *( SEMI rr-param ), a bare addr-spec is not allowed
+  #[inline]
+  pub fn route_param(input: &Binary) -> IResult<&Binary, Route<'_>> {
+    let (rest, address) = name_addr(input)?;
+    let (rest, params) = generic_params(rest)?;
+    Ok((rest, Route { address, params }))
+  }
+
+  // Route        =  "Route" HCOLON route-param *(COMMA route-param)
+  // Record-Route =  "Record-Route" HCOLON rec-route *(COMMA rec-route)
+  // Path         =  "Path" HCOLON path-value *( COMMA path-value )
+  // Service-Route = "Service-Route" HCOLON sr-value *( COMMA sr-value)
+  #[inline]
+  pub fn route(input: &Binary) -> IResult<&Binary, RouteList<'_>> {
+    let (mut rest, _) = route_param(input)?;
+    while let Ok((r, _)) = comma(rest) {
+      let (r, _) = route_param(r)?;
+      rest = r;
+    }
+    Ok((rest, RouteList(&input[..input.len() - rest.len()])))
+  }
+}
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::header::name_addr::assemble::name_addr;
+  use crate::msg::output::{Output, AssembleResult};
+  use super::Route;
+
+  pub fn route_param(r: Route, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.with(|b| name_addr(r.address, b))?;
+    out.put(r.params.as_bytes())?;
+    out.finish()
+  }
+
+  // Entries separated by COMMA, pushing or popping one is a matter of
+  // the iterator given
+  pub fn route<'a, I>(routes: I, buf: &mut Binary) -> AssembleResult
+    where I: IntoIterator<Item = Route<'a>> {
+    let mut out = Output::new(buf);
+    for (i, r) in routes.into_iter().enumerate() {
+      if i > 0 {
+        out.put(b", ")?;
+      }
+      out.with(|b| route_param(r, b))?;
+    }
+    out.finish()
+  }
+}
+
+pub mod owned {
+  use crate::msg::header::name_addr::owned::NameAddr;
+  use crate::msg::header::params::owned::GenericParams;
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub struct Route {
+    pub address: NameAddr,
+    pub params: GenericParams
+  }
+
+  impl Route {
+    pub fn as_borrowed(&self) -> super::Route<'_> {
+      super::Route {
+        address: self.address.as_borrowed(),
+        params: self.params.as_borrowed()
+      }
+    }
+  }
+
+  impl<'a> super::Route<'a> {
+    pub fn into_owned(self) -> Route {
+      Route {
+        address: self.address.into_owned(),
+        params: self.params.into_owned()
+      }
+    }
+  }
+
+  impl<'a> From<super::Route<'a>> for Route {
+    fn from(r: super::Route<'a>) -> Route {
+      r.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a Route> for super::Route<'a> {
+    fn from(r: &'a Route) -> super::Route<'a> {
+      r.as_borrowed()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Route, routes, parse, assemble};
+  use crate::msg::header::HeaderName;
+  use crate::msg::header::parse::headers;
+  use crate::msg::uri::{Uri, parse::uri};
+  use std::iter::once;
+
+  #[test]
+  fn route_test() {
+    let value = b"<sip:bigbox3.site3.atlanta.com;lr>,\r\n <sip:server10.biloxi.com;lr>;x=1, Proxy <sip:p.com>";
+    let (rest, list) = parse::route(value).unwrap();
+    assert_eq!(rest, b"");
+    let entries: Vec<Route> = list.iter().collect();
+    assert_eq!(entries.len(), 3);
+    assert!(entries[0].is_loose() && entries[1].is_loose());
+    assert!(!entries[2].is_loose());
+    assert_eq!(entries[1].params.get(b"x"), Some(Some(&b"1"[..])));
+
+    let (_, list) = parse::route(b"<sip:old.example.com;lr=on>").unwrap();
+    assert!(list.iter().next().unwrap().is_loose());
+
+    // only name-addr is allowed
+    assert!(parse::route(b"sip:p.com;lr").is_err());
+    assert!(parse::route(b"<sip:p.com>,").is_err());
+  }
+
+  #[test]
+  fn routes_test() {
+    let (_, hs) = headers(b"Record-Route: <sip:a.com;lr>, <sip:b.com;lr>\r\n\
+Route: <sip:x.com;lr>\r\n\
+Record-Route: <sip:c.com;lr>\r\n\r\n").unwrap();
+    let rr: Vec<_> = routes(hs, HeaderName::RecordRoute).collect();
+    assert_eq!(rr.len(), 3);
+    assert_eq!(routes(hs, HeaderName::Route).count(), 1);
+    assert_eq!(routes(hs, HeaderName::Path).count(), 0);
+
+    // a proxy pushes its own entry on top and the next hop pops it again
+    let (_, mine) = uri(b"sip:p1.example.com;lr").unwrap();
+    let mut buf = [0u8; 128];
+    let n = assemble::route(once(Route::new(mine)).chain(rr.iter().cloned()), &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"<sip:p1.example.com;lr>, <sip:a.com;lr>, <sip:b.com;lr>, <sip:c.com;lr>".as_ref());
+
+    let pushed = buf[..n].to_vec();
+    let (_, list) = parse::route(&pushed).unwrap();
+    let n = assemble::route(list.iter().skip(1), &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"<sip:a.com;lr>, <sip:b.com;lr>, <sip:c.com;lr>".as_ref());
+  }
+
+  #[test]
+  fn route_owned_test() {
+    let owned = {
+      let input = b"<sip:a.com;lr>;x".to_vec();
+      let (_, r) = parse::route_param(&input).unwrap();
+      r.into_owned()
+    };
+    assert!(owned.as_borrowed().is_loose());
+    assert!(matches!(owned.as_borrowed().uri(), Uri::Sip(_)));
+  }
+}
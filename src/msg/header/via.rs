@@ -0,0 +1,630 @@
+use crate::msg::Binary;
+use crate::msg::abnf::whole;
+use crate::msg::abnf::host::Host;
+use crate::msg::abnf::uri_params::Transport;
+use crate::msg::header::{Headers, HeaderName};
+use crate::msg::header::params::parse::next_param;
+use std::net::IpAddr;
+
+// Branch of a request which follows RFC 3261 starts with the magic cookie
+pub const MAGIC_COOKIE: &Binary = b"z9hG4bK";
+
+// via-parm          =  sent-protocol LWS sent-by *( SEMI via-params )
+// sent-protocol     =  protocol-name SLASH protocol-version
+//                      SLASH transport
+// sent-by           =  host [ COLON port ]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Via<'a> {
+  pub protocol_name: &'a Binary,
+  pub protocol_version: &'a Binary,
+  pub transport: Transport<'a>,
+  pub host: Host<'a>,
+  pub port: Option<u16>,
+  pub params: ViaParameters<'a>
+}
+
+impl<'a> Via<'a> {
+  pub fn branch(&self) -> Option<&'a Binary> {
+    self.params.iter().find_map(|p| match p {
+      ViaParam::Branch(b) => Some(b),
+      _ => None
+    })
+  }
+
+  // branch is unique across space and time, so it alone identifies
+  // the transaction
+  pub fn has_magic_cookie(&self) -> bool {
+    self.branch().is_some_and(|b| b.starts_with(MAGIC_COOKIE))
+  }
+
+  pub fn received(&self) -> Option<IpAddr> {
+    self.params.iter().find_map(|p| match p {
+      ViaParam::Received(ip) => Some(ip),
+      _ => None
+    })
+  }
+
+  // Some(None) is the valueless rport of RFC 3581, the client asks for
+  // the source port to be filled in
+  pub fn rport(&self) -> Option<Option<u16>> {
+    self.params.iter().find_map(|p| match p {
+      ViaParam::Rport(port) => Some(port),
+      _ => None
+    })
+  }
+
+  pub fn maddr(&self) -> Option<Host<'a>> {
+    self.params.iter().find_map(|p| match p {
+      ViaParam::Maddr(h) => Some(h),
+      _ => None
+    })
+  }
+
+  pub fn ttl(&self) -> Option<u8> {
+    self.params.iter().find_map(|p| match p {
+      ViaParam::Ttl(ttl) => Some(ttl),
+      _ => None
+    })
+  }
+}
+
+// via-params        =  via-ttl / via-maddr
+//                      / via-received / via-branch
+//                      / response-port / via-extension
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum ViaParam<'a> {
+  Ttl(u8),
+  Maddr(Host<'a>),
+  Received(IpAddr),
+  Branch(&'a Binary),
+  Rport(Option<u16>),
+  Other(&'a Binary, Option<&'a Binary>)
+}
+
+// via-params which were already validated by the parser, kept as the
+// original slice starting at the first SEMI
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub struct ViaParameters<'a>(&'a Binary);
+
+impl<'a> ViaParameters<'a> {
+  pub fn as_bytes(&self) -> &'a Binary {
+    self.0
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  pub fn iter(&self) -> ViaParametersIter<'a> {
+    ViaParametersIter { rest: self.0 }
+  }
+
+  // Value of a parameter as it appears in the input, well-known
+  // parameters included. None when there is no such parameter,
+  // Some(None) when it has no value.
+  pub fn get(&self, name: &Binary) -> Option<Option<&'a Binary>> {
+    let mut rest = self.0;
+    while let Ok((r, (n, v))) = next_param(rest) {
+      if n.eq_ignore_ascii_case(name) {
+        return Some(v);
+      }
+      rest = r;
+    }
+    None
+  }
+}
+
+impl<'a> IntoIterator for ViaParameters<'a> {
+  type Item = ViaParam<'a>;
+  type IntoIter = ViaParametersIter<'a>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+pub struct ViaParametersIter<'a> {
+  rest: &'a Binary
+}
+
+impl<'a> Iterator for ViaParametersIter<'a> {
+  type Item = ViaParam<'a>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let (r, (name, value)) = next_param(self.rest).ok()?;
+    self.rest = r;
+    Some(parse::well_known_parameter(name, value).unwrap_or(ViaParam::Other(name, value)))
+  }
+}
+
+// Via  =  ( "Via" / "v" ) HCOLON via-parm *(COMMA via-parm)
+// Value of a single Via header, every via-parm is parsed again on
+// iteration.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct ViaList<'a>(&'a Binary);
+
+impl<'a> ViaList<'a> {
+  pub fn as_bytes(&self) -> &'a Binary {
+    self.0
+  }
+
+  pub fn iter(&self) -> ViaListIter<'a> {
+    ViaListIter { rest: self.0, first: true }
+  }
+}
+
+impl<'a> IntoIterator for ViaList<'a> {
+  type Item = Via<'a>;
+  type IntoIter = ViaListIter<'a>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+pub struct ViaListIter<'a> {
+  rest: &'a Binary,
+  first: bool
+}
+
+impl<'a> Iterator for ViaListIter<'a> {
+  type Item = Via<'a>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let rest = if self.first {
+      self.rest
+    } else {
+      parse::comma(self.rest).ok()?.0
+    };
+    let (r, via) = parse::via_parm(rest).ok()?;
+    self.first = false;
+    self.rest = r;
+    Some(via)
+  }
+}
+
+// Every via-parm of the message from the topmost one down, no matter
+// whether they share a header or not. A malformed Via header ends
+// the sequence.
+pub fn vias<'a>(headers: Headers<'a>) -> impl Iterator<Item = Via<'a>> {
+  headers.get_all(HeaderName::Via)
+    .map(|h| whole(parse::via(h.value)))
+    .take_while(Option::is_some)
+    .flat_map(|list| list.into_iter().flatten())
+}
+
+pub mod parse {
+  use crate::msg::Binary;
+  use crate::msg::abnf::whole;
+  use crate::msg::abnf::ch::{lws, slash, colon, value_token, is_token};
+  use crate::msg::abnf::host::{Host, host, hostport, port};
+  use crate::msg::abnf::uri_params::{Transport, ttl};
+  use crate::msg::header::params::parse::next_param;
+  use super::{Via, ViaParam, ViaParameters, ViaList};
+  use nom::IResult;
+  use std::net::{IpAddr, Ipv6Addr};
+
+  pub(super) use crate::msg::abnf::ch::comma;
+
+  // via-received      =  "received" EQUAL (IPv4address / IPv6address)
+  // IPv6 addresses are often written as references, both are taken.
+  fn received(value: &Binary) -> Option<IpAddr> {
+    match whole(host(value)) {
+      Some(Host::Ipv4(ip)) => Some(IpAddr::V4(ip)),
+      Some(Host::Ipv6(ip)) => Some(IpAddr::V6(ip)),
+      _ => std::str::from_utf8(value).ok()?.parse::<Ipv6Addr>().ok().map(IpAddr::V6)
+    }
+  }
+
+  // via-ttl           =  "ttl" EQUAL ttl
+  // via-maddr         =  "maddr" EQUAL host
+  // via-branch        =  "branch" EQUAL token
+  // response-port     =  "rport" [EQUAL 1*DIGIT]
+  // via-extension     =  generic-param
+  pub(super) fn well_known_parameter<'a>(name: &'a Binary, value: Option<&'a Binary>)
+    -> Option<ViaParam<'a>> {
+    let is = |n: &[u8]| name.eq_ignore_ascii_case(n);
+
+    match value {
+      None if is(b"rport") => Some(ViaParam::Rport(None)),
+      Some(v) if is(b"rport") => whole(port(v)).map(|p| ViaParam::Rport(Some(p))),
+      Some(v) if is(b"branch") && is_token(v) => Some(ViaParam::Branch(v)),
+      Some(v) if is(b"received") => received(v).map(ViaParam::Received),
+      Some(v) if is(b"ttl") => whole(ttl(v)).map(ViaParam::Ttl),
+      Some(v) if is(b"maddr") => whole(host(v)).map(ViaParam::Maddr),
+      _ => None
+    }
+  }
+
+  // *( SEMI via-params )
+  #[inline]
+  pub fn via_params(input: &Binary) -> IResult<&Binary, ViaParameters<'_>> {
+    let mut rest = input;
+    while let Ok((r, _)) = next_param(rest) {
+      rest = r;
+    }
+    Ok((rest, ViaParameters(&input[..input.len() - rest.len()])))
+  }
+
+  // sent-by           =  host [ COLON port ]
+  // Plain hostport goes first, whitespace around the colon is rare
+  #[inline]
+  pub fn sent_by(input: &Binary) -> IResult<&Binary, (Host<'_>, Option<u16>)> {
+    if let Ok((rest, (h, p))) = hostport(input) {
+      if p.is_some() || colon(rest).is_err() {
+        return Ok((rest, (h, p)));
+      }
+    }
+
+    let (rest, h) = host(input)?;
+    let (rest, _) = colon(rest)?;
+    let (rest, p) = port(rest)?;
+    Ok((rest, (h, Some(p))))
+  }
+
+  // via-parm          =  sent-protocol LWS sent-by *( SEMI via-params )
+  // sent-protocol     =  protocol-name SLASH protocol-version
+  //                      SLASH transport
+  // protocol-name     =  "SIP" / token
+  // protocol-version  =  token
+  // transport         =  "UDP" / "TCP" / "TLS" / "SCTP"
+  //                      / other-transport
+  #[inline]
+  pub fn via_parm(input: &Binary) -> IResult<&Binary, Via<'_>> {
+    let (rest, protocol_name) = value_token(input)?;
+    let (rest, _) = slash(rest)?;
+    let (rest, protocol_version) = value_token(rest)?;
+    let (rest, _) = slash(rest)?;
+    let (rest, transport) = value_token(rest)?;
+    let (rest, _) = lws(rest)?;
+    let (rest, (host, port)) = sent_by(rest)?;
+    let (rest, params) = via_params(rest)?;
+
+    Ok((rest, Via {
+      protocol_name,
+      protocol_version,
+      transport: Transport::from_token(transport),
+      host,
+      port,
+      params
+    }))
+  }
+
+  // via-parm *(COMMA via-parm)
+  #[inline]
+  pub fn via(input: &Binary) -> IResult<&Binary, ViaList<'_>> {
+    let (mut rest, _) = via_parm(input)?;
+    while let Ok((r, _)) = comma(rest) {
+      let (r, _) = via_parm(r)?;
+      rest = r;
+    }
+    Ok((rest, ViaList(&input[..input.len() - rest.len()])))
+  }
+}
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::abnf::host::assemble::{host, hostport};
+  use crate::msg::abnf::uri_params::Transport;
+  use crate::msg::output::{Output, AssembleResult};
+  use super::{Via, ViaParam};
+
+  // via-params without the leading SEMI
+  pub fn via_param(p: ViaParam, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    match p {
+      ViaParam::Ttl(ttl) => {
+        out.put(b"ttl=")?;
+        out.put_number(ttl as u64)?;
+      },
+      ViaParam::Maddr(h) => {
+        out.put(b"maddr=")?;
+        out.with(|b| host(h, b))?;
+      },
+      ViaParam::Received(ip) => {
+        out.put(b"received=")?;
+        out.put_display(ip)?;
+      },
+      ViaParam::Branch(b) => {
+        out.put(b"branch=")?;
+        out.put(b)?;
+      },
+      ViaParam::Rport(port) => {
+        out.put(b"rport")?;
+        if let Some(p) = port {
+          out.put_byte(b'=')?;
+          out.put_number(p as u64)?;
+        }
+      },
+      ViaParam::Other(name, value) => {
+        out.put(name)?;
+        if let Some(v) = value {
+          out.put_byte(b'=')?;
+          out.put(v)?;
+        }
+      }
+    }
+    out.finish()
+  }
+
+  // *( SEMI via-params )
+  pub fn via_params<'a, I>(params: I, buf: &mut Binary) -> AssembleResult
+    where I: IntoIterator<Item = ViaParam<'a>> {
+    let mut out = Output::new(buf);
+    for p in params {
+      out.put_byte(b';')?;
+      out.with(|b| via_param(p, b))?;
+    }
+    out.finish()
+  }
+
+  // Transports are case-insensitive, the well-known ones are written in
+  // upper case as everybody does
+  pub fn via_parm(v: Via, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.put(v.protocol_name)?;
+    out.put_byte(b'/')?;
+    out.put(v.protocol_version)?;
+    out.put_byte(b'/')?;
+    match v.transport {
+      Transport::Other(t) => out.put(t)?,
+      t => for b in t.as_bytes() {
+        out.put_byte(b.to_ascii_uppercase())?;
+      }
+    }
+    out.put_byte(b' ')?;
+    out.with(|b| hostport(v.host, v.port, b))?;
+    out.put(v.params.as_bytes())?;
+    out.finish()
+  }
+
+  // via-parm *(COMMA via-parm)
+  pub fn via<'a, I>(vias: I, buf: &mut Binary) -> AssembleResult
+    where I: IntoIterator<Item = Via<'a>> {
+    let mut out = Output::new(buf);
+    for (i, v) in vias.into_iter().enumerate() {
+      if i > 0 {
+        out.put(b", ")?;
+      }
+      out.with(|b| via_parm(v, b))?;
+    }
+    out.finish()
+  }
+}
+
+pub mod owned {
+  use crate::msg::Binary;
+  use crate::msg::abnf::host::owned::Host;
+  use crate::msg::abnf::uri_params::owned::Transport;
+  use std::net::IpAddr;
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub enum ViaParam {
+    Ttl(u8),
+    Maddr(Host),
+    Received(IpAddr),
+    Branch(Vec<u8>),
+    Rport(Option<u16>),
+    Other(Vec<u8>, Option<Vec<u8>>)
+  }
+
+  impl ViaParam {
+    pub fn as_borrowed(&self) -> super::ViaParam<'_> {
+      match self {
+        ViaParam::Ttl(ttl) => super::ViaParam::Ttl(*ttl),
+        ViaParam::Maddr(h) => super::ViaParam::Maddr(h.as_borrowed()),
+        ViaParam::Received(ip) => super::ViaParam::Received(*ip),
+        ViaParam::Branch(b) => super::ViaParam::Branch(b),
+        ViaParam::Rport(p) => super::ViaParam::Rport(*p),
+        ViaParam::Other(n, v) => super::ViaParam::Other(n, v.as_deref())
+      }
+    }
+  }
+
+  impl<'a> super::ViaParam<'a> {
+    pub fn into_owned(self) -> ViaParam {
+      match self {
+        super::ViaParam::Ttl(ttl) => ViaParam::Ttl(ttl),
+        super::ViaParam::Maddr(h) => ViaParam::Maddr(h.into_owned()),
+        super::ViaParam::Received(ip) => ViaParam::Received(ip),
+        super::ViaParam::Branch(b) => ViaParam::Branch(b.to_vec()),
+        super::ViaParam::Rport(p) => ViaParam::Rport(p),
+        super::ViaParam::Other(n, v) => ViaParam::Other(n.to_vec(), v.map(<[u8]>::to_vec))
+      }
+    }
+  }
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash, Default)]
+  pub struct ViaParameters(Vec<u8>);
+
+  impl ViaParameters {
+    pub fn as_bytes(&self) -> &Binary {
+      &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+      self.0.is_empty()
+    }
+
+    pub fn as_borrowed(&self) -> super::ViaParameters<'_> {
+      super::ViaParameters(&self.0)
+    }
+  }
+
+  impl<'a> super::ViaParameters<'a> {
+    pub fn into_owned(self) -> ViaParameters {
+      ViaParameters(self.0.to_vec())
+    }
+  }
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub struct Via {
+    pub protocol_name: Vec<u8>,
+    pub protocol_version: Vec<u8>,
+    pub transport: Transport,
+    pub host: Host,
+    pub port: Option<u16>,
+    pub params: ViaParameters
+  }
+
+  impl Via {
+    pub fn as_borrowed(&self) -> super::Via<'_> {
+      super::Via {
+        protocol_name: &self.protocol_name,
+        protocol_version: &self.protocol_version,
+        transport: self.transport.as_borrowed(),
+        host: self.host.as_borrowed(),
+        port: self.port,
+        params: self.params.as_borrowed()
+      }
+    }
+  }
+
+  impl<'a> super::Via<'a> {
+    pub fn into_owned(self) -> Via {
+      Via {
+        protocol_name: self.protocol_name.to_vec(),
+        protocol_version: self.protocol_version.to_vec(),
+        transport: self.transport.into_owned(),
+        host: self.host.into_owned(),
+        port: self.port,
+        params: self.params.into_owned()
+      }
+    }
+  }
+
+  impl<'a> From<super::Via<'a>> for Via {
+    fn from(v: super::Via<'a>) -> Via {
+      v.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a Via> for super::Via<'a> {
+    fn from(v: &'a Via) -> super::Via<'a> {
+      v.as_borrowed()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Via, ViaParam, vias, parse, assemble};
+  use crate::msg::abnf::host::{Host, Hostname};
+  use crate::msg::abnf::uri_params::Transport;
+  use crate::msg::header::parse::headers;
+  use std::net::{IpAddr, Ipv4Addr};
+
+  fn hostname(name: &[u8]) -> Host<'_> {
+    Host::Hostname(Hostname::new(name).unwrap())
+  }
+
+  #[test]
+  fn via_parm_test() {
+    let (rest, via) = parse::via_parm(b"SIP/2.0/UDP erlang.bell-telephone.com:5060;branch=z9hG4bK87asdks7").unwrap();
+    assert_eq!(rest, b"");
+    assert_eq!(via.protocol_name, b"SIP");
+    assert_eq!(via.protocol_version, b"2.0");
+    assert_eq!(via.transport, Transport::UDP);
+    assert_eq!(via.host, hostname(b"erlang.bell-telephone.com"));
+    assert_eq!(via.port, Some(5060));
+    assert_eq!(via.branch(), Some(&b"z9hG4bK87asdks7"[..]));
+    assert!(via.has_magic_cookie());
+    assert_eq!(via.received(), None);
+    assert_eq!(via.rport(), None);
+
+    // RFC 3261 20.42, whitespace is allowed around every separator
+    let (rest, via) = parse::via_parm(b"SIP / 2.0 / UDP first.example.com: 4000;ttl=16\r\n ;maddr=224.2.0.1 ;branch=z9hG4bKa7c6a8dlze.1").unwrap();
+    assert_eq!(rest, b"");
+    assert_eq!(via.port, Some(4000));
+    assert_eq!(via.ttl(), Some(16));
+    assert_eq!(via.maddr(), Some(Host::Ipv4(Ipv4Addr::new(224, 2, 0, 1))));
+    assert_eq!(via.branch(), Some(&b"z9hG4bKa7c6a8dlze.1"[..]));
+
+    let (_, via) = parse::via_parm(b"SIP/2.0/tls [2001:db8::1];received=2001:db8::9;rport=5061;x;y=\"a b\"").unwrap();
+    assert_eq!(via.transport, Transport::TLS);
+    assert_eq!(via.port, None);
+    assert_eq!(via.received(), Some("2001:db8::9".parse::<IpAddr>().unwrap()));
+    assert_eq!(via.rport(), Some(Some(5061)));
+    assert!(!via.has_magic_cookie());
+    assert_eq!(via.params.get(b"X"), Some(None));
+    assert_eq!(via.params.get(b"y"), Some(Some(&b"\"a b\""[..])));
+    assert_eq!(via.params.get(b"rport"), Some(Some(&b"5061"[..])));
+    assert_eq!(via.params.get(b"z"), None);
+
+    let (rest, via) = parse::via_parm(b"SIP/2.0/WS df7jal23ls0d.invalid;rport;branch=z9hG4bKhjhs8ass877 ,").unwrap();
+    assert_eq!(rest, b" ,");
+    assert_eq!(via.transport, Transport::Other(b"WS"));
+    assert_eq!(via.rport(), Some(None));
+
+    assert!(parse::via_parm(b"SIP/2.0 host").is_err());
+    assert!(parse::via_parm(b"SIP/2.0/UDPhost").is_err());
+    assert!(parse::via_parm(b"SIP/2.0/UDP ").is_err());
+  }
+
+  #[test]
+  fn via_params_test() {
+    let (rest, params) = parse::via_params(b";ttl=300;received=a.com;rport=0;maddr=[::1];branch=\"q\";ttl=1 x").unwrap();
+    assert_eq!(rest, b" x");
+    let params: Vec<_> = params.iter().collect();
+    assert_eq!(params, vec![
+      ViaParam::Other(b"ttl", Some(b"300")),
+      ViaParam::Other(b"received", Some(b"a.com")),
+      ViaParam::Other(b"rport", Some(b"0")),
+      ViaParam::Maddr(Host::Ipv6("::1".parse().unwrap())),
+      ViaParam::Other(b"branch", Some(b"\"q\"")),
+      ViaParam::Ttl(1)
+    ]);
+  }
+
+  #[test]
+  fn via_list_test() {
+    let value = b"SIP/2.0/UDP a.com;branch=z9hG4bK1 ,\r\n SIP/2.0/TCP b.com:5070;branch=z9hG4bK2";
+    let (rest, list) = parse::via(value).unwrap();
+    assert_eq!(rest, b"");
+    let hosts: Vec<_> = list.iter().map(|v| v.host).collect();
+    assert_eq!(hosts, vec![hostname(b"a.com"), hostname(b"b.com")]);
+
+    assert!(parse::via(b"SIP/2.0/UDP a.com, x").is_err());
+    assert_eq!(parse::via(b"SIP/2.0/UDP a.com x").map(|(r, l)| (r, l.iter().count())), Ok((&b" x"[..], 1)));
+
+    let (_, headers) = headers(b"v: SIP/2.0/UDP a.com, SIP/2.0/UDP b.com\r\nTo: <sip:x@y>\r\nVia: SIP/2.0/UDP c.com\r\n\r\n").unwrap();
+    let hosts: Vec<_> = vias(headers).map(|v| v.host).collect();
+    assert_eq!(hosts, vec![hostname(b"a.com"), hostname(b"b.com"), hostname(b"c.com")]);
+  }
+
+  #[test]
+  fn via_assemble_test() {
+    let mut buf = [0u8; 128];
+    let value = b"SIP/2.0/udp pc33.atlanta.com ; branch=z9hG4bK776asdhds;rport, SIP/2.0/Foo [::1]:5060";
+    let (_, list) = parse::via(value).unwrap();
+    let n = assemble::via(list, &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"SIP/2.0/UDP pc33.atlanta.com ; branch=z9hG4bK776asdhds;rport, SIP/2.0/Foo [::1]:5060".as_ref());
+
+    let params = [
+      ViaParam::Branch(b"z9hG4bK776"),
+      ViaParam::Received(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))),
+      ViaParam::Rport(Some(5060)),
+      ViaParam::Rport(None),
+      ViaParam::Ttl(1),
+      ViaParam::Maddr(Host::Ipv4(Ipv4Addr::new(224, 2, 0, 1))),
+      ViaParam::Other(b"x", None)
+    ];
+    let n = assemble::via_params(params.iter().cloned(), &mut buf).unwrap();
+    assert_eq!(&buf[..n], b";branch=z9hG4bK776;received=192.0.2.1;rport=5060;rport;ttl=1;maddr=224.2.0.1;x".as_ref());
+    let (_, parsed) = parse::via_params(&buf[..n]).unwrap();
+    assert!(parsed.iter().eq(params.iter().cloned()));
+  }
+
+  #[test]
+  fn via_owned_test() {
+    let owned = {
+      let input = b"SIP/2.0/SCTP a.com;branch=z9hG4bK1".to_vec();
+      let (_, via) = parse::via_parm(&input).unwrap();
+      via.into_owned()
+    };
+    let via: Via = (&owned).into();
+    assert_eq!(via.transport, Transport::SCTP);
+    assert_eq!(via.branch(), Some(&b"z9hG4bK1"[..]));
+    assert_eq!(ViaParam::Rport(None).into_owned().as_borrowed(), ViaParam::Rport(None));
+  }
+}
@@ -12,7 +12,7 @@ pub fn word(input: &Binary, output: &mut Binary) -> usize {
 }
 
 fn generate(input: &Binary, output: &mut Binary, lookup: &'static Binary) -> usize {
-  Hasher::new(input.into_iter(), lookup, 256)
+  Hasher::new(input.iter(), lookup, 256)
     .zip(output)
     .fold(0, |cnt, (i, o)|{
       *o = i;
@@ -66,43 +66,24 @@ impl<'a, T> Iterator for Hasher<'a, T>
     }
 
     if self.acc > 0 {
-      return Some(self.calculate())
+      Some(self.calculate())
     } else {
       None
     }
   }
 }
 
-static TOKEN_LOOKUP_TABLE: &'static Binary = &[
-  // other symbols
-  '-' as u8, '.' as u8, '!' as u8, '*' as u8, '_' as u8, '+' as u8, '`' as u8, '\'' as u8, '~' as u8,
-  // numbers
-  '0' as u8, '1' as u8, '2' as u8, '3' as u8, '4' as u8, '5' as u8, '6' as u8, '7' as u8, '8' as u8, '9' as u8,
-  // lower case letters
-  'a' as u8, 'b' as u8, 'c' as u8, 'd' as u8, 'e' as u8, 'f' as u8, 'g' as u8, 'h' as u8, 'i' as u8, 'j' as u8,
-  'k' as u8, 'l' as u8, 'm' as u8, 'n' as u8, 'o' as u8, 'p' as u8, 'q' as u8, 'r' as u8, 's' as u8, 't' as u8,
-  'u' as u8, 'v' as u8, 'w' as u8, 'x' as u8, 'y' as u8, 'z' as u8,
-  // upper case letters
-  'A' as u8, 'B' as u8, 'C' as u8, 'D' as u8, 'E' as u8, 'F' as u8, 'G' as u8, 'H' as u8, 'I' as u8, 'J' as u8,
-  'K' as u8, 'L' as u8, 'M' as u8, 'N' as u8, 'O' as u8, 'P' as u8, 'Q' as u8, 'R' as u8, 'S' as u8, 'T' as u8,
-  'U' as u8, 'V' as u8, 'W' as u8, 'X' as u8, 'Y' as u8, 'Z' as u8
-];
-
-static WORD_LOOKUP_TABLE: &'static Binary = &[
-  // other symbols
-  '-' as u8, '.' as u8, '!' as u8, '*' as u8, '_' as u8, '+' as u8, '`' as u8, '\'' as u8, '~' as u8, '(' as u8, ')' as u8,
-  '<' as u8, '>' as u8, ':' as u8, '\\' as u8, '"' as u8, '/' as u8, '[' as u8, ']' as u8, '?' as u8, '{' as u8, '}' as u8,
-  // numbers
-  '0' as u8, '1' as u8, '2' as u8, '3' as u8, '4' as u8, '5' as u8, '6' as u8, '7' as u8, '8' as u8, '9' as u8,
-  // lower case letters
-  'a' as u8, 'b' as u8, 'c' as u8, 'd' as u8, 'e' as u8, 'f' as u8, 'g' as u8, 'h' as u8, 'i' as u8, 'j' as u8,
-  'k' as u8, 'l' as u8, 'm' as u8, 'n' as u8, 'o' as u8, 'p' as u8, 'q' as u8, 'r' as u8, 's' as u8, 't' as u8,
-  'u' as u8, 'v' as u8, 'w' as u8, 'x' as u8, 'y' as u8, 'z' as u8,
-  // upper case letters
-  'A' as u8, 'B' as u8, 'C' as u8, 'D' as u8, 'E' as u8, 'F' as u8, 'G' as u8, 'H' as u8, 'I' as u8, 'J' as u8,
-  'K' as u8, 'L' as u8, 'M' as u8, 'N' as u8, 'O' as u8, 'P' as u8, 'Q' as u8, 'R' as u8, 'S' as u8, 'T' as u8,
-  'U' as u8, 'V' as u8, 'W' as u8, 'X' as u8, 'Y' as u8, 'Z' as u8
-];
+// other symbols, numbers, lower case letters, upper case letters
+static TOKEN_LOOKUP_TABLE: &Binary = b"-.!*_+`'~\
+  0123456789\
+  abcdefghijklmnopqrstuvwxyz\
+  ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+// other symbols, numbers, lower case letters, upper case letters
+static WORD_LOOKUP_TABLE: &Binary = b"-.!*_+`'~()<>:\\\"/[]?{}\
+  0123456789\
+  abcdefghijklmnopqrstuvwxyz\
+  ABCDEFGHIJKLMNOPQRSTUVWXYZ";
 
 
 #[cfg(test)]
@@ -119,7 +100,7 @@ mod tests {
   }
 
   fn is_token_char(ch: &u8) -> bool {
-    TOKEN_LOOKUP_TABLE.contains(&ch)
+    TOKEN_LOOKUP_TABLE.contains(ch)
   }
 
   fn is_word_char(ch: &u8) -> bool {
@@ -134,7 +115,7 @@ mod tests {
       let input: [u8; 30] = rand::random();
       let size = token(&input[..rnd.gen_range(2, 30)], &mut output);
       assert!(size > 0);
-      output[0..size].into_iter().for_each(|ch| assert!(is_token_char(ch)));
+      output[0..size].iter().for_each(|ch| assert!(is_token_char(ch)));
     }
   }
 
@@ -146,7 +127,7 @@ mod tests {
       let input: [u8; 30] = rand::random();
       let size = word(&input[..rnd.gen_range(2, 30)], &mut output);
       assert!(size > 0);
-      output[0..size].into_iter().for_each(|ch| assert!(is_word_char(ch)));
+      output[0..size].iter().for_each(|ch| assert!(is_word_char(ch)));
     }
   }
 
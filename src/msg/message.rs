@@ -0,0 +1,493 @@
+use super::Binary;
+use super::abnf::whole;
+use super::builder::MessageBuilder;
+use super::header::{Headers, HeaderName};
+use super::header::from_to::parse::from_to;
+use super::header::media_type::{MediaType, parse::media_type};
+use super::abnf::escape::HEX;
+use super::method::Method;
+use super::multipart::Multipart;
+use super::start_line::{RequestLine, StatusLine};
+use super::status::StatusCode;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Request<'a> {
+  pub line: RequestLine<'a>,
+  pub headers: Headers<'a>,
+  pub body: &'a Binary
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Response<'a> {
+  pub line: StatusLine<'a>,
+  pub headers: Headers<'a>,
+  pub body: &'a Binary
+}
+
+// SIP-message  =  Request / Response
+// Everything points into the buffer the message was parsed from.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum SipMessage<'a> {
+  Request(Request<'a>),
+  Response(Response<'a>)
+}
+
+impl<'a> SipMessage<'a> {
+  pub fn headers(&self) -> Headers<'a> {
+    match self {
+      SipMessage::Request(r) => r.headers,
+      SipMessage::Response(r) => r.headers
+    }
+  }
+
+  pub fn body(&self) -> &'a Binary {
+    match self {
+      SipMessage::Request(r) => r.body,
+      SipMessage::Response(r) => r.body
+    }
+  }
+
+  // None when the header is missing or malformed
+  pub fn content_type(&self) -> Option<MediaType<'a>> {
+    whole(media_type(self.headers().get(HeaderName::ContentType)?.value))
+  }
+
+  // Parts of a multipart body, e.g. SDP with ISUP or PIDF-LO
+  pub fn multipart(&self) -> Option<Multipart<'a>> {
+    Multipart::of(&self.content_type()?, self.body())
+  }
+
+  // Response to the request as RFC 3261 8.2.6 has it: Via, From, To,
+  // Call-ID and CSeq are copied and To gets a tag unless it has one or
+  // the response is 100 Trying, which copies Timestamp instead. The tag
+  // is made of `random`, 32 bits out of a cryptographically secure
+  // generator as RFC 3261 19.3 asks; responses which should carry the
+  // same tag are given the same bytes. The reason is the registered one
+  // when None. None for responses, for ACK, which is never answered, and
+  // for requests without a To which parses.
+  pub fn make_response(&self, code: StatusCode, reason: Option<&'a Binary>, random: [u8; 4])
+    -> Option<MessageBuilder<'a>> {
+    let request = match self {
+      SipMessage::Request(r) if r.line.method != Method::Ack => r,
+      _ => return None
+    };
+    let to = whole(from_to(request.headers.get(HeaderName::To)?.value))?;
+
+    let mut builder = MessageBuilder::response(code);
+    if let Some(reason) = reason {
+      builder = builder.with_reason(reason);
+    }
+    let trying = code == StatusCode::TRYING;
+    for h in request.headers.iter() {
+      let copied = [HeaderName::Via, HeaderName::From, HeaderName::To, HeaderName::CallId, HeaderName::CSeq];
+      if copied.contains(&h.name) || (trying && h.name == HeaderName::Timestamp) {
+        builder = builder.with_header(h.name, h.value);
+      }
+    }
+
+    if !trying && to.tag().is_none() {
+      builder = builder.with_to_tag(&to_tag(random));
+    }
+    Some(builder)
+  }
+}
+
+// every random bit ends up in the tag, which is never empty
+fn to_tag(random: [u8; 4]) -> Vec<u8> {
+  let mut tag = Vec::with_capacity(random.len() * 2);
+  for b in random.iter() {
+    tag.push(HEX[(b >> 4) as usize]);
+    tag.push(HEX[(b & 0xf) as usize]);
+  }
+  tag
+}
+
+pub mod parse {
+  use crate::msg::Binary;
+  use crate::msg::abnf::whole;
+  use crate::msg::header::{Headers, HeaderName, parse::headers};
+  use crate::msg::header::numeric::parse as numeric;
+  use crate::msg::error::{ParseError, ParseResult, rule};
+  use crate::msg::start_line::StartLine;
+  use crate::msg::start_line::parse::{start_line, strict_start_line, checked_start_line};
+  use crate::msg::version::Version;
+  use crate::msg::version::parse::{version, strict_version};
+  use super::{SipMessage, Request, Response};
+  use nom::IResult;
+  use nom::Err::{self, Error};
+  use nom::error::ErrorKind::{self, Digit, Verify};
+
+  // Content-Length  =  ( "Content-Length" / "l" ) HCOLON 1*DIGIT
+  // Several Content-Length headers are fine as long as they agree.
+  pub fn content_length(headers: Headers<'_>) -> Result<Option<usize>, Err<(&Binary, ErrorKind)>> {
+    let mut length = None;
+    for h in headers.iter() {
+      if h.name != HeaderName::ContentLength {
+        continue;
+      }
+
+      let value = whole(numeric::content_length(h.value)).ok_or(Error((h.value, Digit)))?;
+
+      match length {
+        Some(l) if l != value => return Err(Error((h.value, Verify))),
+        _ => length = Some(value)
+      }
+    }
+    Ok(length)
+  }
+
+  // content_length() of the headers of `base`, which the offset counts from
+  pub fn checked_content_length(base: &Binary, headers: Headers<'_>) -> Result<Option<usize>, ParseError> {
+    content_length(headers).map_err(|e| ParseError::from_nom("Content-Length", base, e))
+  }
+
+  fn message_of<'a>(line: StartLine<'a>, headers: Headers<'a>, body: &'a Binary) -> SipMessage<'a> {
+    match line {
+      StartLine::Request(line) => SipMessage::Request(Request { line, headers, body }),
+      StartLine::Status(line) => SipMessage::Response(Response { line, headers, body })
+    }
+  }
+
+  // SIP-message  =  start-line *message-header CRLF [ message-body ]
+  // The body takes Content-Length bytes. Without Content-Length the body
+  // is the rest of the input, which is what RFC 3261 18.3 prescribes for
+  // datagrams; stream transports frame messages with framer::Framer first.
+  #[inline]
+  fn message_with<L>(input: &Binary, start_line: L) -> IResult<&Binary, SipMessage<'_>>
+    where L: Fn(&Binary) -> IResult<&Binary, StartLine<'_>> {
+    let (rest, line) = start_line(input)?;
+    let (rest, headers) = headers(rest)?;
+    let (rest, body) = match content_length(headers)? {
+      Some(len) => take!(rest, len)?,
+      None => (&rest[rest.len()..], rest)
+    };
+
+    Ok((rest, message_of(line, headers, body)))
+  }
+
+  #[inline]
+  pub fn message(input: &Binary) -> IResult<&Binary, SipMessage<'_>> {
+    message_with(input, start_line)
+  }
+
+  // Message of SIP/2.0 only, anything else is a Verify error at the
+  // version
+  #[inline]
+  pub fn strict_message(input: &Binary) -> IResult<&Binary, SipMessage<'_>> {
+    message_with(input, strict_start_line)
+  }
+
+  // message_with() with the failure named by the rule which didn't match,
+  // for a 400 which tells the peer what is wrong. It accepts exactly what
+  // message_with() does, header values are checked by validate_values().
+  fn checked_message_with<V>(input: &Binary, version: V) -> ParseResult<'_, SipMessage<'_>>
+    where V: Fn(&Binary) -> IResult<&Binary, Version> {
+    let (rest, line) = checked_start_line(input, version)?;
+    let (rest, headers) = rule("message-header", input, headers(rest))?;
+    let (rest, body) = match checked_content_length(input, headers)? {
+      Some(len) => rule("message-body", input, take!(rest, len))?,
+      None => (&rest[rest.len()..], rest)
+    };
+
+    Ok((rest, message_of(line, headers, body)))
+  }
+
+  pub fn checked_message(input: &Binary) -> ParseResult<'_, SipMessage<'_>> {
+    checked_message_with(input, version)
+  }
+
+  pub fn checked_strict_message(input: &Binary) -> ParseResult<'_, SipMessage<'_>> {
+    checked_message_with(input, strict_version)
+  }
+}
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::start_line::assemble::{request_line, status_line};
+  use crate::msg::output::{Output, AssembleResult};
+  use super::SipMessage;
+
+  pub fn message(m: SipMessage, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    match m {
+      SipMessage::Request(r) => out.with(|b| request_line(r.line, b))?,
+      SipMessage::Response(r) => out.with(|b| status_line(r.line, b))?
+    }
+    out.put(m.headers().as_bytes())?;
+    out.put(m.body())?;
+    out.finish()
+  }
+}
+
+pub mod owned {
+  use crate::msg::header::owned::Headers;
+  use crate::msg::start_line::owned::{RequestLine, StatusLine};
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub struct Request {
+    pub line: RequestLine,
+    pub headers: Headers,
+    pub body: Vec<u8>
+  }
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub struct Response {
+    pub line: StatusLine,
+    pub headers: Headers,
+    pub body: Vec<u8>
+  }
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub enum SipMessage {
+    Request(Request),
+    Response(Response)
+  }
+
+  impl SipMessage {
+    pub fn as_borrowed(&self) -> super::SipMessage<'_> {
+      match self {
+        SipMessage::Request(r) => super::SipMessage::Request(super::Request {
+          line: r.line.as_borrowed(),
+          headers: r.headers.as_borrowed(),
+          body: &r.body
+        }),
+        SipMessage::Response(r) => super::SipMessage::Response(super::Response {
+          line: r.line.as_borrowed(),
+          headers: r.headers.as_borrowed(),
+          body: &r.body
+        })
+      }
+    }
+  }
+
+  impl<'a> super::SipMessage<'a> {
+    pub fn into_owned(self) -> SipMessage {
+      match self {
+        super::SipMessage::Request(r) => SipMessage::Request(Request {
+          line: r.line.into_owned(),
+          headers: r.headers.into_owned(),
+          body: r.body.to_vec()
+        }),
+        super::SipMessage::Response(r) => SipMessage::Response(Response {
+          line: r.line.into_owned(),
+          headers: r.headers.into_owned(),
+          body: r.body.to_vec()
+        })
+      }
+    }
+  }
+
+  impl<'a> From<super::SipMessage<'a>> for SipMessage {
+    fn from(m: super::SipMessage<'a>) -> SipMessage {
+      m.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a SipMessage> for super::SipMessage<'a> {
+    fn from(m: &'a SipMessage) -> super::SipMessage<'a> {
+      m.as_borrowed()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{SipMessage, parse, assemble};
+  use crate::msg::header::{Header, HeaderName};
+  use crate::msg::method::Method;
+  use crate::msg::error::ParseError;
+  use crate::msg::header::from_to::parse::from_to;
+  use crate::msg::status::StatusCode;
+  use nom::{Err::{Incomplete, Error}, Needed, error::ErrorKind::{Digit, Verify}};
+
+  // RFC 3261 24.2
+  const INVITE: &[u8] = b"INVITE sip:bob@biloxi.com SIP/2.0\r\n\
+Via: SIP/2.0/UDP pc33.atlanta.com;branch=z9hG4bKnashds8\r\n\
+Max-Forwards: 70\r\n\
+To: Bob <sip:bob@biloxi.com>\r\n\
+From: Alice <sip:alice@atlanta.com>;tag=1928301774\r\n\
+Call-ID: a84b4c76e66710\r\n\
+CSeq: 314159 INVITE\r\n\
+Contact: <sip:alice@pc33.atlanta.com>\r\n\
+Content-Type: application/sdp\r\n\
+Content-Length: 4\r\n\
+\r\n\
+v=0\n";
+
+  #[test]
+  fn message_parse_test() {
+    let mut input = INVITE.to_vec();
+    input.extend_from_slice(b"SIP/2.0");
+
+    let (rest, m) = parse::message(&input).unwrap();
+    assert_eq!(rest, b"SIP/2.0");
+    assert_eq!(m.body(), b"v=0\n");
+    assert_eq!(m.headers().iter().count(), 9);
+    assert_eq!(m.headers().iter().nth(2), Some(Header { name: HeaderName::To, value: b"Bob <sip:bob@biloxi.com>" }));
+    assert!(m.content_type().unwrap().is(b"application", b"sdp"));
+    assert!(m.multipart().is_none());
+    match m {
+      SipMessage::Request(r) => assert_eq!(r.line.method, Method::Invite),
+      _ => panic!("request expected")
+    }
+
+    let (rest, m) = parse::message(b"SIP/2.0 180 Ringing\r\nl: 0\r\n\r\n").unwrap();
+    assert_eq!(rest, b"");
+    assert_eq!(m.body(), b"");
+    match m {
+      SipMessage::Response(r) => assert_eq!(r.line.code, StatusCode::RINGING),
+      _ => panic!("response expected")
+    }
+
+    // no Content-Length, the body is the rest of the datagram
+    let (rest, m) = parse::message(b"SIP/2.0 200 OK\r\n\r\nabc").unwrap();
+    assert_eq!(rest, b"");
+    assert_eq!(m.body(), b"abc");
+    assert!(m.content_type().is_none());
+
+    let (_, m) = parse::message(b"SIP/2.0 200 OK\r\nc: multipart/mixed;boundary=b\r\n\r\n--b\r\n\r\nabc\r\n--b--").unwrap();
+    assert_eq!(m.multipart().unwrap().iter().next().unwrap().body, b"abc");
+  }
+
+  #[test]
+  fn message_parse_fail_test() {
+    assert_eq!(parse::message(&INVITE[..INVITE.len() - 2]), Err(Incomplete(Needed::Size(4))));
+    assert_eq!(parse::message(&INVITE[..INVITE.len() - 10]), Err(Incomplete(Needed::Size(2))));
+    assert_eq!(
+      parse::message(b"SIP/2.0 200 OK\r\nContent-Length: 1x\r\n\r\n"),
+      Err(Error((&b"1x"[..], Digit)))
+    );
+    assert_eq!(
+      parse::message(b"SIP/2.0 200 OK\r\nContent-Length: 99999999999999999999999\r\n\r\n"),
+      Err(Error((&b"99999999999999999999999"[..], Digit)))
+    );
+    assert_eq!(
+      parse::message(b"SIP/2.0 200 OK\r\nl: 1\r\nContent-Length: 2\r\n\r\nab"),
+      Err(Error((&b"2"[..], Verify)))
+    );
+    assert!(parse::message(b"SIP/2.0 200 OK\r\nl: 1\r\nContent-Length: 1\r\n\r\na").is_ok());
+
+    assert!(parse::strict_message(INVITE).is_ok());
+    assert!(parse::message(b"SIP/2.1 200 OK\r\n\r\n").is_ok());
+    assert_eq!(
+      parse::strict_message(b"SIP/2.1 200 OK\r\n\r\n"),
+      Err(Error((&b"SIP/2.1 200 OK\r\n\r\n"[..], Verify)))
+    );
+  }
+
+  #[test]
+  fn message_assemble_test() {
+    let (_, m) = parse::message(INVITE).unwrap();
+    let mut buf = [0u8; 512];
+    let n = assemble::message(m, &mut buf).unwrap();
+    assert_eq!(&buf[..n], INVITE);
+
+    let owned = {
+      let input = INVITE.to_vec();
+      let (_, m) = parse::message(&input).unwrap();
+      m.into_owned()
+    };
+    let n = assemble::message(owned.as_borrowed(), &mut buf).unwrap();
+    assert_eq!(&buf[..n], INVITE);
+  }
+
+  fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+  }
+
+  #[test]
+  fn make_response_test() {
+    let mut input = INVITE.to_vec();
+    let at = input.windows(4).position(|w| w == b"Max-").unwrap();
+    input.splice(at..at, b"Via: SIP/2.0/UDP p1.example.com;branch=z9hG4bK1\r\nTimestamp: 54\r\n".iter().copied());
+    let (_, m) = parse::message(&input).unwrap();
+
+    let random = [0x5c, 0x1f, 0xa0, 0x7e];
+    let ok = m.make_response(StatusCode::OK, None, random).unwrap().to_vec().unwrap();
+    let (_, r) = parse::message(&ok).unwrap();
+    match r {
+      SipMessage::Response(r) => assert_eq!((r.line.code, r.line.reason), (StatusCode::OK, &b"OK"[..])),
+      _ => panic!("response expected")
+    }
+    let names: Vec<_> = r.headers().iter().map(|h| h.name).collect();
+    assert_eq!(names, [
+      HeaderName::Via, HeaderName::Via, HeaderName::To, HeaderName::From,
+      HeaderName::CallId, HeaderName::CSeq, HeaderName::ContentLength
+    ]);
+    assert_eq!(r.headers().iter().nth(1).unwrap().value, b"SIP/2.0/UDP p1.example.com;branch=z9hG4bK1");
+    let to = r.headers().get(HeaderName::To).unwrap().value;
+    assert_eq!(from_to(to).unwrap().1.tag(), Some(&b"5C1FA07E"[..]));
+
+    // the same tag out of the same bytes only, none for 100 Trying
+    let busy = m.make_response(StatusCode::BUSY_HERE, Some(b"Busy"), random).unwrap().to_vec().unwrap();
+    assert!(busy.starts_with(b"SIP/2.0 486 Busy\r\n"));
+    assert!(contains(&busy, to));
+    let other = m.make_response(StatusCode::BUSY_HERE, None, [0x5c, 0x1f, 0xa0, 0x7f]).unwrap().to_vec().unwrap();
+    assert!(!contains(&other, to));
+    let trying = m.make_response(StatusCode::TRYING, None, random).unwrap().to_vec().unwrap();
+    assert!(contains(&trying, b"To: Bob <sip:bob@biloxi.com>\r\n"));
+    assert!(contains(&trying, b"Timestamp: 54\r\n"));
+    assert!(!contains(&ok, b"Timestamp"));
+
+    // in-dialog requests keep their tag
+    let (_, bye) = parse::message(b"BYE sip:alice@pc33.atlanta.com SIP/2.0\r\nTo: <sip:a@b>;tag=x\r\n\r\n").unwrap();
+    let r = bye.make_response(StatusCode::OK, None, random).unwrap()
+      .with_via(b"SIP/2.0/UDP h")
+      .with_header(HeaderName::From, b"<sip:c@d>")
+      .with_header(HeaderName::CallId, b"1")
+      .with_header(HeaderName::CSeq, b"2 BYE")
+      .to_vec()
+      .unwrap();
+    assert!(contains(&r, b"To: <sip:a@b>;tag=x\r\n"));
+
+    let (_, ack) = parse::message(b"ACK sip:a@b SIP/2.0\r\n\r\n").unwrap();
+    assert!(ack.make_response(StatusCode::OK, None, random).is_none());
+    let (_, ringing) = parse::message(b"SIP/2.0 180 Ringing\r\n\r\n").unwrap();
+    assert!(ringing.make_response(StatusCode::OK, None, random).is_none());
+
+    // a tag out of zero bytes is still one, a To which doesn't parse gets no response
+    let (_, invite) = parse::message(b"INVITE sip:a@b SIP/2.0\r\nTo: <sip:a@b>\r\n\r\n").unwrap();
+    let r = invite.make_response(StatusCode::OK, None, [0; 4]).unwrap()
+      .with_via(b"SIP/2.0/UDP h")
+      .with_header(HeaderName::From, b"<sip:c@d>")
+      .with_header(HeaderName::CallId, b"1")
+      .with_header(HeaderName::CSeq, b"2 INVITE")
+      .to_vec()
+      .unwrap();
+    assert!(contains(&r, b"To: <sip:a@b>;tag=00000000\r\n"));
+    let (_, broken) = parse::message(b"INVITE sip:a@b SIP/2.0\r\nTo: <sip:a@b\r\n\r\n").unwrap();
+    assert!(broken.make_response(StatusCode::OK, None, random).is_none());
+    let (_, missing) = parse::message(b"INVITE sip:a@b SIP/2.0\r\n\r\n").unwrap();
+    assert!(missing.make_response(StatusCode::OK, None, random).is_none());
+  }
+
+  #[test]
+  fn checked_message_test() {
+    let err = |rule, offset| Err(ParseError::new(rule, offset));
+    assert!(parse::checked_message(INVITE).is_ok());
+    assert_eq!(parse::checked_message(b"INV@TE sip:a@b SIP/2.0\r\n\r\n"), err("Method", 3));
+    assert_eq!(parse::checked_message(b"INVITE sip:bob@:5060 SIP/2.0\r\n\r\n"), err("hostport", 15));
+    assert_eq!(parse::checked_message(b"INVITE sip:bob@biloxi.com:99999 SIP/2.0\r\n\r\n"), err("hostport", 30));
+    assert_eq!(parse::checked_message(b"INVITE sip:bob@biloxi.com;=x SIP/2.0\r\n\r\n"), err("uri-parameter", 25));
+    assert_eq!(parse::checked_message(b"INVITE sip:bob@biloxi.com? SIP/2.0\r\n\r\n"), err("headers", 25));
+    assert_eq!(parse::checked_message(b"INVITE bad SIP/2.0\r\n\r\n"), err("absoluteURI", 10));
+    assert_eq!(parse::checked_message(b"INVITE sip:bob@biloxi.com SIP/2.x\r\n\r\n"), err("SIP-Version", 32));
+    assert_eq!(parse::checked_message(b"SIP/2.0 2000 OK\r\n\r\n"), err("Status-Code", 11));
+    assert_eq!(parse::checked_message(b"SIP/2.0 200 O\x01K\r\n\r\n"), err("Reason-Phrase", 13));
+    assert_eq!(parse::checked_message(b"SIP/2.0 200 OK\r\nVia x\r\n\r\n"), err("message-header", 20));
+    assert_eq!(parse::checked_message(b"SIP/2.0 200 OK\r\nl: 5\r\nl: 6\r\n\r\nab"), err("Content-Length", 25));
+    assert_eq!(parse::checked_message(b"SIP/2.0 200 OK\r\nl: x\r\n\r\n"), err("Content-Length", 19));
+
+    // header values are up to validate_values()
+    let m = b"SIP/2.0 200 OK\r\nCSeq: x INVITE\r\n\r\n";
+    assert!(parse::message(m).is_ok());
+    assert!(parse::checked_message(m).is_ok());
+
+    let e = parse::checked_message(b"SIP/2.0 200 OK\r\nl: 5\r\n\r\nab").unwrap_err();
+    assert_eq!((e.rule, e.offset, e.needs_more()), ("message-body", 26, true));
+
+    assert!(parse::checked_message(b"SIP/2.1 200 OK\r\n\r\n").is_ok());
+    assert_eq!(parse::checked_strict_message(b"SIP/2.1 200 OK\r\n\r\n"), err("SIP-Version", 0));
+    assert_eq!(parse::checked_strict_message(b"BYE sip:a@b SIP/3.0\r\n\r\n"), err("SIP-Version", 12));
+  }
+}
@@ -1,7 +1,9 @@
-
 use super::Binary;
 
-#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+// Methods of RFC 3261 and of the extensions in common use. Equality is
+// by the method name, so Token(b"REFER") equals Refer; methods are case
+// sensitive.
+#[derive(Debug, Clone, Copy)]
 pub enum Method <'a> {
   Register,
   Invite,
@@ -9,12 +11,81 @@ pub enum Method <'a> {
   Cancel,
   Bye,
   Options,
+  // RFC 6665
+  Subscribe,
+  Notify,
+  // RFC 3515
+  Refer,
+  // RFC 3428
+  Message,
+  // RFC 6086
+  Info,
+  // RFC 3262
+  Prack,
+  // RFC 3311
+  Update,
+  // RFC 3903
+  Publish,
   Token(&'a Binary)
 }
 
+impl<'a> Method<'a> {
+  // Method named by the whole token
+  pub fn from_token(token: &'a Binary) -> Method<'a> {
+    match token {
+      b"REGISTER" => Method::Register,
+      b"INVITE" => Method::Invite,
+      b"ACK" => Method::Ack,
+      b"CANCEL" => Method::Cancel,
+      b"BYE" => Method::Bye,
+      b"OPTIONS" => Method::Options,
+      b"SUBSCRIBE" => Method::Subscribe,
+      b"NOTIFY" => Method::Notify,
+      b"REFER" => Method::Refer,
+      b"MESSAGE" => Method::Message,
+      b"INFO" => Method::Info,
+      b"PRACK" => Method::Prack,
+      b"UPDATE" => Method::Update,
+      b"PUBLISH" => Method::Publish,
+      token => Method::Token(token)
+    }
+  }
+
+  pub fn as_bytes(&self) -> &'a Binary {
+    match self {
+      Method::Token(token) => token,
+      Method::Register => b"REGISTER",
+      Method::Invite => b"INVITE",
+      Method::Ack => b"ACK",
+      Method::Cancel => b"CANCEL",
+      Method::Bye => b"BYE",
+      Method::Options => b"OPTIONS",
+      Method::Subscribe => b"SUBSCRIBE",
+      Method::Notify => b"NOTIFY",
+      Method::Refer => b"REFER",
+      Method::Message => b"MESSAGE",
+      Method::Info => b"INFO",
+      Method::Prack => b"PRACK",
+      Method::Update => b"UPDATE",
+      Method::Publish => b"PUBLISH",
+    }
+  }
+}
+
+impl<'a, 'b> PartialEq<Method<'b>> for Method<'a> {
+  fn eq(&self, other: &Method<'b>) -> bool {
+    self.as_bytes() == other.as_bytes()
+  }
+}
+
+impl<'a> Eq for Method<'a> {}
+
 pub mod parse {
-  use super::super::abnf::ch::token;
+  use crate::msg::abnf::ch::token;
   use crate::msg::method::Method;
+  use crate::msg::Binary;
+  use nom::IResult;
+
   //  INVITEm           =  %x49.4E.56.49.54.45 ; INVITE in caps
   //  ACKm              =  %x41.43.4B ; ACK in caps
   //  OPTIONSm          =  %x4F.50.54.49.4F.4E.53 ; OPTIONS in caps
@@ -25,36 +96,124 @@ pub mod parse {
   //                       / CANCELm / REGISTERm
   //                       / extension-method
   //  extension-method  =  token
-  named!(#[inline], pub method<Method>,
-    alt!(
-        tag!(b"REGISTER") => { |_| Method::Register } |
-        tag!(b"INVITE") => { |_| Method::Invite } |
-        tag!(b"ACK") => { |_| Method::Ack } |
-        tag!(b"CANCEL") => { |_| Method::Cancel } |
-        tag!(b"BYE") => { |_| Method::Bye } |
-        tag!(b"OPTIONS") => { |_| Method::Options } |
-        token => { |t| Method::Token(t) }
-      )
-  );
+  // The method is the whole token, so "BYEX" is an extension method and
+  // not BYE followed by "X"
+  #[inline]
+  pub fn method(input: &Binary) -> IResult<&Binary, Method<'_>> {
+    let (rest, m) = token(input)?;
+    Ok((rest, Method::from_token(m)))
+  }
 }
 
 pub mod assemble {
   use crate::msg::Binary;
   use crate::msg::method::Method;
-  use std::io::Write;
-
-  pub fn method(m: Method, mut buf: &mut Binary) -> std::io::Result<usize> {
-    match m {
-      Method::Token(token) => buf.write(token),
-      Method::Register => buf.write(b"REGISTER"),
-      Method::Invite => buf.write(b"INVITE"),
-      Method::Ack => buf.write(b"ACK"),
-      Method::Cancel => buf.write(b"CANCEL"),
-      Method::Bye => buf.write(b"BYE"),
-      Method::Options => buf.write(b"OPTIONS"),
+  use crate::msg::output::{Output, AssembleResult};
+
+  pub fn method(m: Method, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.put(m.as_bytes())?;
+    out.finish()
+  }
+}
+
+// Method which doesn't borrow from the input buffer
+pub mod owned {
+  use crate::msg::Binary;
+  use std::hash::{Hash, Hasher};
+
+  #[derive(Debug, Clone)]
+  pub enum Method {
+    Register,
+    Invite,
+    Ack,
+    Cancel,
+    Bye,
+    Options,
+    Subscribe,
+    Notify,
+    Refer,
+    Message,
+    Info,
+    Prack,
+    Update,
+    Publish,
+    Token(Vec<u8>)
+  }
+
+  impl PartialEq for Method {
+    fn eq(&self, other: &Method) -> bool {
+      self.as_bytes() == other.as_bytes()
     }
   }
 
+  impl Eq for Method {}
+
+  impl Hash for Method {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+      self.as_bytes().hash(state)
+    }
+  }
+
+  impl Method {
+    pub fn as_bytes(&self) -> &Binary {
+      self.as_borrowed().as_bytes()
+    }
+
+    pub fn as_borrowed(&self) -> super::Method<'_> {
+      match self {
+        Method::Register => super::Method::Register,
+        Method::Invite => super::Method::Invite,
+        Method::Ack => super::Method::Ack,
+        Method::Cancel => super::Method::Cancel,
+        Method::Bye => super::Method::Bye,
+        Method::Options => super::Method::Options,
+        Method::Subscribe => super::Method::Subscribe,
+        Method::Notify => super::Method::Notify,
+        Method::Refer => super::Method::Refer,
+        Method::Message => super::Method::Message,
+        Method::Info => super::Method::Info,
+        Method::Prack => super::Method::Prack,
+        Method::Update => super::Method::Update,
+        Method::Publish => super::Method::Publish,
+        Method::Token(t) => super::Method::Token(t)
+      }
+    }
+  }
+
+  impl<'a> super::Method<'a> {
+    pub fn into_owned(self) -> Method {
+      match self {
+        super::Method::Register => Method::Register,
+        super::Method::Invite => Method::Invite,
+        super::Method::Ack => Method::Ack,
+        super::Method::Cancel => Method::Cancel,
+        super::Method::Bye => Method::Bye,
+        super::Method::Options => Method::Options,
+        super::Method::Subscribe => Method::Subscribe,
+        super::Method::Notify => Method::Notify,
+        super::Method::Refer => Method::Refer,
+        super::Method::Message => Method::Message,
+        super::Method::Info => Method::Info,
+        super::Method::Prack => Method::Prack,
+        super::Method::Update => Method::Update,
+        super::Method::Publish => Method::Publish,
+        super::Method::Token(t) => Method::Token(t.to_vec())
+      }
+    }
+  }
+
+  impl<'a> From<super::Method<'a>> for Method {
+    fn from(m: super::Method<'a>) -> Method {
+      m.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a Method> for super::Method<'a> {
+    fn from(m: &'a Method) -> super::Method<'a> {
+      m.as_borrowed()
+    }
+  }
 }
 
 #[cfg(test)]
@@ -64,6 +223,7 @@ mod tests {
     parse,
     assemble
   };
+  use crate::msg::output::AssembleError;
   use nom::{
     Err::Incomplete,
     Needed
@@ -71,16 +231,36 @@ mod tests {
 
   #[test]
   fn method_parse_test() {
-    assert_eq!(parse::method("INVITE".as_bytes()), Ok(("".as_bytes(), Method::Invite)));
-    assert_eq!(parse::method("REGISTER".as_bytes()), Ok(("".as_bytes(), Method::Register)));
-    assert_eq!(parse::method("AC".as_bytes()), Err(Incomplete(Needed::Size(3))));
+    assert_eq!(parse::method("INVITE ".as_bytes()), Ok((" ".as_bytes(), Method::Invite)));
+    assert_eq!(parse::method("REGISTER\r".as_bytes()), Ok(("\r".as_bytes(), Method::Register)));
+    // INVITE might go on as an extension method
+    assert_eq!(parse::method("INVITE".as_bytes()), Err(Incomplete(Needed::Size(1))));
+    assert_eq!(parse::method("AC".as_bytes()), Err(Incomplete(Needed::Size(1))));
     assert_eq!(parse::method("BYE some".as_bytes()), Ok((" some".as_bytes(), Method::Bye)));
+    assert_eq!(parse::method("BYEX some".as_bytes()), Ok((" some".as_bytes(), Method::Token(b"BYEX"))));
+    assert_eq!(parse::method("SUBSCRIBE ".as_bytes()), Ok((" ".as_bytes(), Method::Subscribe)));
+    assert_eq!(parse::method("PRACKS ".as_bytes()), Ok((" ".as_bytes(), Method::Token(b"PRACKS"))));
+    assert_eq!(parse::method("notify ".as_bytes()), Ok((" ".as_bytes(), Method::Token(b"notify"))));
     assert_eq!(parse::method("asdf-sdf .".as_bytes()), Ok((" .".as_bytes(), Method::Token("asdf-sdf".as_bytes()))));
   }
 
+  #[test]
+  fn method_eq_test() {
+    assert_eq!(Method::Token(b"REFER"), Method::Refer);
+    assert_ne!(Method::Token(b"refer"), Method::Refer);
+    assert_eq!(Method::from_token(b"PUBLISH"), Method::Publish);
+    assert!(matches!(Method::from_token(b"UPDATE"), Method::Update));
+    assert!(matches!(Method::from_token(b"INFOS"), Method::Token(b"INFOS")));
+
+    let owned = Method::Token(b"MESSAGE").into_owned();
+    assert_eq!(owned, Method::Message.into_owned());
+    let set: std::collections::HashSet<_> = vec![owned, Method::Message.into_owned()].into_iter().collect();
+    assert_eq!(set.len(), 1);
+  }
+
   #[test]
   fn method_assemble_test() {
-    let mut output = [0 as u8; 21];
+    let mut output = [0u8; 21];
     assert_eq!(assemble::method(Method::Invite, &mut output).unwrap(), 6);
     assert_eq!(std::str::from_utf8(&output[0..6]).unwrap(), "INVITE");
 
@@ -90,9 +270,13 @@ mod tests {
     assert_eq!(assemble::method(Method::Token(b"SUBSCRIBE"), &mut output[11..]).unwrap(), 9);
     assert_eq!(std::str::from_utf8(&output[0..20]).unwrap(), "INVITEREFERSUBSCRIBE");
 
-    assert_eq!(assemble::method(Method::Options, &mut output[20..]).unwrap(), 1);
-    assert_eq!(std::str::from_utf8(&output).unwrap(), "INVITEREFERSUBSCRIBEO");
-
-    assert_eq!(assemble::method(Method::Bye, &mut output[21..]).unwrap(), 0);
+    assert_eq!(
+      assemble::method(Method::Options, &mut output[20..]),
+      Err(AssembleError::NotEnoughOutSpace { written: 0 })
+    );
+    assert_eq!(
+      assemble::method(Method::Bye, &mut output[21..]),
+      Err(AssembleError::NotEnoughOutSpace { written: 0 })
+    );
   }
 }
\ No newline at end of file
@@ -5,7 +5,20 @@ pub type Utf8Char = char;
 pub type Utf8Str = str;
 
 pub mod abnf;
+pub mod builder;
+pub mod error;
+pub mod framer;
+pub mod header;
 pub mod id;
+pub mod message;
 pub mod method;
+pub mod multipart;
+pub mod output;
+pub mod routing;
+pub mod start_line;
+pub mod status;
 pub mod uri;
-pub mod version;
\ No newline at end of file
+pub mod version;
+
+// msg::parse::message is the entry point for whole messages
+pub use self::message::parse;
@@ -0,0 +1,279 @@
+use super::Binary;
+use super::abnf::whole;
+use super::header::{Headers, HeaderName};
+use super::header::disposition::{Disposition, parse::disposition};
+use super::header::media_type::{MediaType, parse::media_type};
+use super::header::parse::headers;
+
+// body-part  =  MIME-part-headers [CRLF *OCTET]
+// A part without headers is text/plain in the MIME defaults, so
+// content_type() is None for it rather than a made up value.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Part<'a> {
+  pub headers: Headers<'a>,
+  pub body: &'a Binary
+}
+
+impl<'a> Part<'a> {
+  pub fn content_type(&self) -> Option<MediaType<'a>> {
+    whole(media_type(self.headers.get(HeaderName::ContentType)?.value))
+  }
+
+  // Content-ID without the angle brackets, RFC 2045 7
+  pub fn content_id(&self) -> Option<&'a Binary> {
+    match self.headers.get(HeaderName::ContentId)?.value {
+      [b'<', id @ .., b'>'] => Some(id),
+      id => Some(id)
+    }
+  }
+
+  pub fn content_disposition(&self) -> Option<Disposition<'a>> {
+    whole(disposition(self.headers.get(HeaderName::ContentDisposition)?.value))
+  }
+
+  // Body of a part which is multipart itself
+  pub fn multipart(&self) -> Option<Multipart<'a>> {
+    Multipart::of(&self.content_type()?, self.body)
+  }
+}
+
+// multipart-body  =  [preamble CRLF]
+//                    dash-boundary transport-padding CRLF
+//                    body-part *encapsulation
+//                    close-delimiter transport-padding
+//                    [CRLF epilogue]
+// encapsulation   =  delimiter transport-padding CRLF body-part
+// delimiter       =  CRLF dash-boundary
+// Parts are found again on every iteration. The CRLF in front of a
+// delimiter belongs to the delimiter, not to the body of the part.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Multipart<'a> {
+  body: &'a Binary,
+  boundary: &'a Binary
+}
+
+impl<'a> Multipart<'a> {
+  // boundary  =  0*69<bchars> bcharsnospace, RFC 2046 5.1.1
+  pub fn new(body: &'a Binary, boundary: &'a Binary) -> Option<Multipart<'a>> {
+    let is_bchar = |b: &u8| b.is_ascii_alphanumeric() || b"'()+_,-./:=? ".contains(b);
+    match boundary.last() {
+      Some(b' ') | None => None,
+      Some(_) if boundary.len() > 70 || !boundary.iter().all(is_bchar) => None,
+      Some(_) => Some(Multipart { body, boundary })
+    }
+  }
+
+  // Body of a message or part with this Content-Type, None unless the
+  // type is multipart with a valid boundary
+  pub fn of(content_type: &MediaType<'a>, body: &'a Binary) -> Option<Multipart<'a>> {
+    if !content_type.is_multipart() {
+      return None;
+    }
+    Multipart::new(body, content_type.boundary()?)
+  }
+
+  pub fn boundary(&self) -> &'a Binary {
+    self.boundary
+  }
+
+  pub fn iter(&self) -> MultipartIter<'a> {
+    MultipartIter { body: self.body, boundary: self.boundary, pos: 0, first: true, done: false }
+  }
+
+  // First part with the Content-ID, the way cid: URLs refer to parts
+  pub fn get(&self, content_id: &Binary) -> Option<Part<'a>> {
+    self.iter().find(|p| p.content_id() == Some(content_id))
+  }
+
+  // Delimiter line at or after `from`: its start including the leading
+  // CRLF, the end of the line and whether it is the close delimiter.
+  // Only the first delimiter may go without the CRLF, at the very start.
+  fn delimiter(&self, from: usize) -> Option<(usize, usize, bool)> {
+    let body = self.body;
+    let mut at = from;
+    loop {
+      let start = if at == 0 && body.starts_with(b"--") {
+        0
+      } else {
+        at + body[at..].windows(4).position(|w| w == b"\r\n--")?
+      };
+      let dashes = if start == 0 && body.starts_with(b"--") { 0 } else { start + 2 };
+      let line = &body[dashes + 2..];
+      if line.starts_with(self.boundary) {
+        let after = dashes + 2 + self.boundary.len();
+        let rest = &body[after..];
+        if rest.starts_with(b"--") {
+          return Some((start, after + 2, true));
+        }
+        let padding = rest.iter().take_while(|b| **b == b' ' || **b == b'\t').count();
+        if rest[padding..].starts_with(b"\r\n") {
+          return Some((start, after + padding + 2, false));
+        }
+      }
+      at = dashes + 1;
+    }
+  }
+}
+
+impl<'a> IntoIterator for Multipart<'a> {
+  type Item = Part<'a>;
+  type IntoIter = MultipartIter<'a>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.iter()
+  }
+}
+
+// Stops at the close delimiter or at the first malformed part. A body
+// cut short of the close delimiter ends with whatever its last part has.
+pub struct MultipartIter<'a> {
+  body: &'a Binary,
+  boundary: &'a Binary,
+  pos: usize,
+  first: bool,
+  done: bool
+}
+
+impl<'a> Iterator for MultipartIter<'a> {
+  type Item = Part<'a>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.done {
+      return None;
+    }
+    let multipart = Multipart { body: self.body, boundary: self.boundary };
+    if self.first {
+      self.first = false;
+      let (_, end, close) = multipart.delimiter(0)?;
+      if close {
+        self.done = true;
+        return None;
+      }
+      self.pos = end;
+    }
+
+    let content = match multipart.delimiter(self.pos) {
+      Some((start, end, close)) => {
+        let content = &self.body[self.pos..start];
+        self.pos = end;
+        self.done = close;
+        content
+      },
+      None => {
+        self.done = true;
+        &self.body[self.pos..]
+      }
+    };
+
+    let part = part(content);
+    if part.is_none() {
+      self.done = true;
+    }
+    part
+  }
+}
+
+fn part(content: &Binary) -> Option<Part<'_>> {
+  if content.is_empty() {
+    return Some(Part { headers: Headers::default(), body: content });
+  }
+  if let Some(body) = content.strip_prefix(b"\r\n") {
+    return Some(Part { headers: Headers::default(), body });
+  }
+  let (body, headers) = headers(content).ok()?;
+  Some(Part { headers, body })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Multipart;
+  use crate::msg::header::media_type::parse::media_type;
+
+  const BODY: &[u8] = b"preamble\r\n\
+    --boundary1\r\n\
+    Content-Type: application/sdp\r\n\
+    \r\n\
+    v=0\r\n\
+    o=alice 2890844526 2890842807 IN IP4 atlanta.example.com\r\n\
+    \r\n\
+    --boundary1 \t\r\n\
+    Content-Type: application/pidf+xml\r\n\
+    Content-ID: <target123@atlanta.example.com>\r\n\
+    Content-Disposition: by-reference;handling=optional\r\n\
+    \r\n\
+    <?xml version=\"1.0\"?>\r\n\
+    <presence/>\r\n\
+    --boundary1--\r\n\
+    epilogue";
+
+  #[test]
+  fn multipart_test() {
+    let (_, ct) = media_type(b"multipart/mixed;boundary=\"boundary1\"").unwrap();
+    let multipart = Multipart::of(&ct, BODY).unwrap();
+    assert_eq!(multipart.boundary(), b"boundary1");
+
+    let parts: Vec<_> = multipart.iter().collect();
+    assert_eq!(parts.len(), 2);
+    assert!(parts[0].content_type().unwrap().is(b"application", b"sdp"));
+    assert_eq!(parts[0].body, &b"v=0\r\no=alice 2890844526 2890842807 IN IP4 atlanta.example.com\r\n"[..]);
+    assert_eq!(parts[0].content_id(), None);
+
+    assert!(parts[1].content_type().unwrap().is(b"application", b"pidf+xml"));
+    assert_eq!(parts[1].body, &b"<?xml version=\"1.0\"?>\r\n<presence/>"[..]);
+    let d = parts[1].content_disposition().unwrap();
+    assert!(d.is(b"by-reference") && !d.is_required());
+    assert_eq!(multipart.get(b"target123@atlanta.example.com"), Some(parts[1]));
+
+    let (_, ct) = media_type(b"application/sdp").unwrap();
+    assert!(Multipart::of(&ct, BODY).is_none());
+    assert!(Multipart::new(BODY, b"").is_none());
+    assert!(Multipart::new(BODY, b"trailing ").is_none());
+    assert!(Multipart::new(BODY, b"x;y").is_none());
+  }
+
+  #[test]
+  fn nested_test() {
+    let body = b"--outer\r\n\
+      Content-Type: multipart/alternative; boundary=inner\r\n\
+      \r\n\
+      --inner\r\n\
+      \r\n\
+      plain\r\n\
+      --inner\r\n\
+      Content-Type: text/html\r\n\
+      \r\n\
+      <p>html</p>\r\n\
+      --inner--\r\n\
+      --outer\r\n\
+      Content-Type: application/ISUP;version=itu-t92+\r\n\
+      \r\n\
+      \x01\x00\r\n\x02\r\n\
+      --outer--";
+    let parts: Vec<_> = Multipart::new(body, b"outer").unwrap().iter().collect();
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[1].body, b"\x01\x00\r\n\x02");
+
+    let inner: Vec<_> = parts[0].multipart().unwrap().iter().collect();
+    assert_eq!(inner.len(), 2);
+    assert!(inner[0].headers.is_empty() && inner[0].content_type().is_none());
+    assert_eq!(inner[0].body, b"plain");
+    assert_eq!(inner[1].body, b"<p>html</p>");
+  }
+
+  #[test]
+  fn malformed_test() {
+    // no close delimiter, a look-alike line stays in the body
+    let body = b"--b\r\n\r\none\r\n--bb\r\n--b\r\n\r\ntwo\r\n";
+    let parts: Vec<_> = Multipart::new(body, b"b").unwrap().iter().collect();
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[0].body, b"one\r\n--bb");
+    assert_eq!(parts[1].body, b"two\r\n");
+
+    // headers of the second part never end
+    let body = b"--b\r\n\r\none\r\n--b\r\nContent-Type: text/plain\r\n--b--";
+    assert_eq!(Multipart::new(body, b"b").unwrap().iter().count(), 1);
+
+    assert_eq!(Multipart::new(b"no delimiters", b"b").unwrap().iter().count(), 0);
+    assert_eq!(Multipart::new(b"--b--\r\n", b"b").unwrap().iter().count(), 0);
+  }
+}
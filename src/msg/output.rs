@@ -0,0 +1,197 @@
+use super::{Binary, Byte};
+use super::abnf::escape::{Context, escape_at, HEX};
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum AssembleError {
+  NotEnoughOutSpace {
+    written: usize
+  },
+  // a component which should be in its escaped form has a byte which
+  // is neither allowed there nor part of a "%HH" escape
+  NotEscaped {
+    written: usize
+  }
+}
+
+pub type AssembleResult = Result<usize, AssembleError>;
+
+// Cursor over the caller provided buffer which is shared by assemblers.
+// Every put either writes the whole element or nothing, so on failure
+// `written` tells where the last complete element ends. An assembler run
+// through `with` is not one element: it may fail after some of its own
+// puts, which stay in the buffer and are counted in `written`.
+pub struct Output<'a> {
+  buf: &'a mut Binary,
+  written: usize
+}
+
+impl<'a> Output<'a> {
+  pub fn new(buf: &'a mut Binary) -> Output<'a> {
+    Output { buf, written: 0 }
+  }
+
+  pub fn written(&self) -> usize {
+    self.written
+  }
+
+  pub fn finish(self) -> AssembleResult {
+    Ok(self.written)
+  }
+
+  #[inline]
+  fn reserve(&mut self, len: usize) -> Result<&mut Binary, AssembleError> {
+    if self.buf.len() - self.written < len {
+      return Err(AssembleError::NotEnoughOutSpace { written: self.written });
+    }
+    let start = self.written;
+    self.written += len;
+    Ok(&mut self.buf[start..start + len])
+  }
+
+  pub fn put(&mut self, data: &Binary) -> Result<(), AssembleError> {
+    self.reserve(data.len())?.copy_from_slice(data);
+    Ok(())
+  }
+
+  pub fn put_byte(&mut self, b: Byte) -> Result<(), AssembleError> {
+    self.reserve(1)?[0] = b;
+    Ok(())
+  }
+
+  pub fn put_number(&mut self, mut n: u64) -> Result<(), AssembleError> {
+    let mut digits = [0u8; 20];
+    let mut i = digits.len();
+    loop {
+      i -= 1;
+      digits[i] = b'0' + (n % 10) as u8;
+      n /= 10;
+      if n == 0 {
+        break;
+      }
+    }
+    self.put(&digits[i..])
+  }
+
+  // Writes a short displayable value like an IP address
+  pub fn put_display<T: std::fmt::Display>(&mut self, value: T) -> Result<(), AssembleError> {
+    use std::io::Write;
+    let mut tmp = [0u8; 64];
+    let len = {
+      let mut cursor = &mut tmp[..];
+      write!(cursor, "{}", value).map_err(|_| AssembleError::NotEnoughOutSpace { written: self.written })?;
+      64 - cursor.len()
+    };
+    self.put(&tmp[..len])
+  }
+
+  // Writes a component which is already in its escaped form, the way
+  // parsers hand it out. Bytes `allowed` rejects are only accepted as
+  // part of a "%HH" escape, anything else is an error rather than being
+  // escaped here: application bytes go through put_encoded instead.
+  pub fn put_component<F>(&mut self, data: &Binary, allowed: F) -> Result<(), AssembleError>
+    where F: Fn(Byte) -> bool {
+    let mut i = 0usize;
+    while i < data.len() {
+      if allowed(data[i]) {
+        i += 1;
+      } else if escape_at(data, i).is_some() {
+        i += 3;
+      } else {
+        return Err(AssembleError::NotEscaped { written: self.written });
+      }
+    }
+    self.put(data)
+  }
+
+  // Writes application bytes as a component of the given context,
+  // escaping every byte the context doesn't allow, '%' included.
+  pub fn put_encoded(&mut self, data: &Binary, context: Context) -> Result<(), AssembleError> {
+    let len = data.iter().map(|b| if context.allows(*b) { 1 } else { 3 }).sum();
+    let out = self.reserve(len)?;
+    let mut o = 0usize;
+    for &b in data {
+      if context.allows(b) {
+        out[o] = b;
+        o += 1;
+      } else {
+        out[o] = b'%';
+        out[o + 1] = HEX[(b >> 4) as usize];
+        out[o + 2] = HEX[(b & 0xf) as usize];
+        o += 3;
+      }
+    }
+    Ok(())
+  }
+
+  // Runs an assembler over the rest of the buffer. On failure the error
+  // counts what the assembler wrote up to its last complete element.
+  pub fn with<F>(&mut self, f: F) -> Result<(), AssembleError>
+    where F: FnOnce(&mut Binary) -> AssembleResult {
+    let start = self.written;
+    match f(&mut self.buf[start..]) {
+      Ok(n) => {
+        self.written += n;
+        Ok(())
+      },
+      Err(AssembleError::NotEnoughOutSpace { written }) => {
+        Err(AssembleError::NotEnoughOutSpace { written: start + written })
+      },
+      Err(AssembleError::NotEscaped { written }) => {
+        Err(AssembleError::NotEscaped { written: start + written })
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Output, AssembleError};
+  use crate::msg::abnf::escape::Context;
+
+  #[test]
+  fn output_test() {
+    let mut buf = [0u8; 10];
+    let mut out = Output::new(&mut buf);
+    assert_eq!(out.put(b"sip:"), Ok(()));
+    assert_eq!(out.put_number(5060), Ok(()));
+    assert_eq!(out.put(b"abc"), Err(AssembleError::NotEnoughOutSpace { written: 8 }));
+    assert_eq!(out.put_byte(b'@'), Ok(()));
+    assert_eq!(out.finish(), Ok(9));
+    assert_eq!(&buf[..9], b"sip:5060@");
+  }
+
+  #[test]
+  fn component_test() {
+    let mut buf = [0u8; 20];
+    let mut out = Output::new(&mut buf);
+    assert_eq!(out.put_component(b"a%20b%41", |b| b.is_ascii_alphanumeric()), Ok(()));
+    assert_eq!(out.put_component(b"a b", |b| b.is_ascii_alphanumeric()), Err(AssembleError::NotEscaped { written: 8 }));
+    assert_eq!(out.put_component(b"%4g", |b| b.is_ascii_alphanumeric()), Err(AssembleError::NotEscaped { written: 8 }));
+    assert_eq!(out.put_component(b"4%", |b| b.is_ascii_alphanumeric()), Err(AssembleError::NotEscaped { written: 8 }));
+    assert_eq!(out.finish(), Ok(8));
+    assert_eq!(&buf[..8], b"a%20b%41".as_ref());
+  }
+
+  #[test]
+  fn encoded_test() {
+    let mut buf = [0u8; 11];
+    let mut out = Output::new(&mut buf);
+    assert_eq!(out.put_encoded(b"a b%41", Context::User), Ok(()));
+    assert_eq!(out.put_encoded(b" ", Context::User), Err(AssembleError::NotEnoughOutSpace { written: 10 }));
+    assert_eq!(out.finish(), Ok(10));
+    assert_eq!(&buf[..10], b"a%20b%2541".as_ref());
+  }
+
+  #[test]
+  fn with_test() {
+    let mut buf = [0u8; 5];
+    let mut out = Output::new(&mut buf);
+    out.put(b"ab").unwrap();
+    assert_eq!(out.with(|b| {
+      let mut inner = Output::new(b);
+      inner.put(b"c")?;
+      inner.put(b"def")?;
+      inner.finish()
+    }), Err(AssembleError::NotEnoughOutSpace { written: 3 }));
+  }
+}
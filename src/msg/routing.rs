@@ -0,0 +1,175 @@
+use super::abnf::uri_headers::UriHeaders;
+use super::header::route::Route;
+use super::uri::{Uri, SipUri};
+
+// Request-URI and Route entries of a request on its way to the next hop.
+// Route entries are in the order they are sent, the first one is where
+// a loose routed request goes. `strict` is set once the request was
+// rewritten for a strict router, which is then in the Request-URI.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Routing<'a> {
+  pub request_uri: Uri<'a>,
+  pub route: Vec<Route<'a>>,
+  pub strict: bool
+}
+
+// Route URIs never carry headers (RFC 3261 19.1.1), a Request-URI which
+// comes out of one must not either
+fn request_uri_of(uri: Uri<'_>) -> Uri<'_> {
+  fn strip(u: SipUri<'_>) -> SipUri<'_> {
+    SipUri { headers: UriHeaders::default(), ..u }
+  }
+
+  match uri {
+    Uri::Sip(u) => Uri::Sip(strip(u)),
+    Uri::Sips(u) => Uri::Sips(strip(u)),
+    Uri::Absolute(u) => Uri::Absolute(u)
+  }
+}
+
+impl<'a> Routing<'a> {
+  // RFC 3261 12.2.1.1, request within a dialog. With an empty route set
+  // or a loose router first the request goes to the remote target
+  // through every entry, otherwise the strict router gets it in the
+  // Request-URI and the remote target ends the Route.
+  pub fn in_dialog<I>(remote_target: Uri<'a>, route_set: I) -> Routing<'a>
+    where I: IntoIterator<Item = Route<'a>> {
+    let mut routing = Routing {
+      request_uri: remote_target,
+      route: route_set.into_iter().collect(),
+      strict: false
+    };
+    routing.route_to_strict_router();
+    routing
+  }
+
+  // RFC 3261 16.4, route information preprocessing of a proxy.
+  // `is_local` tells whether the URI is one this proxy put into
+  // a Record-Route or otherwise owns.
+  //
+  // A Request-URI of this proxy means the previous hop was a strict
+  // router, the original Request-URI is then the last Route entry.
+  // After that the proxy's own entry is popped off the top.
+  pub fn preprocess<I, F>(request_uri: Uri<'a>, route: I, is_local: F) -> Routing<'a>
+    where I: IntoIterator<Item = Route<'a>>, F: Fn(&Uri) -> bool {
+    let mut routing = Routing { request_uri, route: route.into_iter().collect(), strict: false };
+
+    if is_local(&routing.request_uri) {
+      if let Some(last) = routing.route.pop() {
+        routing.request_uri = last.uri();
+      }
+    }
+
+    if routing.route.first().is_some_and(|r| is_local(&r.uri())) {
+      routing.route.remove(0);
+    }
+
+    routing
+  }
+
+  // RFC 3261 16.6 step 6. A first Route entry without "lr" is a strict
+  // router, which expects the next hop in the Request-URI: the
+  // Request-URI goes to the end of the Route and the first entry takes
+  // its place. Returns whether the request was rewritten.
+  pub fn route_to_strict_router(&mut self) -> bool {
+    match self.route.first() {
+      Some(first) if !first.is_loose() => {
+        let first = self.route.remove(0);
+        self.route.push(Route::new(self.request_uri));
+        self.request_uri = request_uri_of(first.uri());
+        self.strict = true;
+        true
+      },
+      _ => false
+    }
+  }
+
+  // RFC 3261 16.6 step 7, URI the request is to be sent to. It is the
+  // Request-URI when that holds a strict router, otherwise the first
+  // Route entry when there is one and the Request-URI again when not.
+  pub fn next_hop(&self) -> Uri<'a> {
+    if self.strict {
+      return self.request_uri;
+    }
+    self.route.first().map_or(self.request_uri, Route::uri)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Routing;
+  use crate::msg::header::route::{Route, parse::route};
+  use crate::msg::uri::{Uri, parse::uri};
+
+  fn u(input: &[u8]) -> Uri<'_> {
+    uri(input).unwrap().1
+  }
+
+  fn routes(input: &[u8]) -> Vec<Route<'_>> {
+    route(input).unwrap().1.iter().collect()
+  }
+
+  fn uris<'a>(routing: &Routing<'a>) -> Vec<Uri<'a>> {
+    routing.route.iter().map(Route::uri).collect()
+  }
+
+  #[test]
+  fn in_dialog_test() {
+    let target = u(b"sip:callee@u1.example.com");
+
+    let r = Routing::in_dialog(target, None);
+    assert_eq!(r.request_uri, target);
+    assert!(r.route.is_empty());
+    assert_eq!(r.next_hop(), target);
+
+    let set = routes(b"<sip:p1.example.com;lr>, <sip:p2.example.com>");
+    let r = Routing::in_dialog(target, set.clone());
+    assert_eq!(r.request_uri, target);
+    assert_eq!(r.route, set);
+    assert!(!r.strict);
+    assert_eq!(r.next_hop(), u(b"sip:p1.example.com;lr"));
+
+    // RFC 3261 12.2.1.1 example
+    let set = routes(b"<sip:p1.example.com?x=y>, <sip:p2.example.com;lr>");
+    let r = Routing::in_dialog(target, set);
+    assert_eq!(r.request_uri, u(b"sip:p1.example.com"));
+    assert_eq!(uris(&r), vec![u(b"sip:p2.example.com;lr"), target]);
+    assert!(r.strict);
+    assert_eq!(r.next_hop(), u(b"sip:p1.example.com"));
+  }
+
+  #[test]
+  fn preprocess_test() {
+    let me = u(b"sip:p2.domain.com;lr");
+    let is_local = |uri: &Uri| uri.equivalent(&me);
+
+    // loose routed request, our entry is popped
+    let r = Routing::preprocess(
+      u(b"sip:callee@domain.com"),
+      routes(b"<sip:p2.domain.com;lr>, <sip:p3.middle.com;lr>"),
+      is_local
+    );
+    assert_eq!(r.request_uri, u(b"sip:callee@domain.com"));
+    assert_eq!(uris(&r), vec![u(b"sip:p3.middle.com;lr")]);
+
+    // strict router before us put our URI into the Request-URI
+    let r = Routing::preprocess(
+      u(b"sip:p2.domain.com;lr"),
+      routes(b"<sip:p3.middle.com>, <sip:callee@u2.domain.com>"),
+      is_local
+    );
+    assert_eq!(r.request_uri, u(b"sip:callee@u2.domain.com"));
+    assert_eq!(uris(&r), vec![u(b"sip:p3.middle.com")]);
+
+    // and the next hop is a strict router as well
+    let mut r = r;
+    assert!(r.route_to_strict_router());
+    assert_eq!(r.request_uri, u(b"sip:p3.middle.com"));
+    assert_eq!(uris(&r), vec![u(b"sip:callee@u2.domain.com")]);
+    assert_eq!(r.next_hop(), u(b"sip:p3.middle.com"));
+
+    let mut r = Routing::preprocess(u(b"sip:callee@domain.com"), None, is_local);
+    assert!(!r.route_to_strict_router());
+    assert_eq!(r.next_hop(), u(b"sip:callee@domain.com"));
+  }
+}
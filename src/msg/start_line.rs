@@ -0,0 +1,423 @@
+use super::Binary;
+use super::method::Method;
+use super::status::StatusCode;
+use super::uri::Uri;
+use super::version::Version;
+
+// Request-Line  =  Method SP Request-URI SP SIP-Version CRLF
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct RequestLine<'a> {
+  pub method: Method<'a>,
+  pub uri: Uri<'a>,
+  pub version: Version
+}
+
+// Status-Line  =  SIP-Version SP Status-Code SP Reason-Phrase CRLF
+// Reason-Phrase is kept as it appears in the input, i.e. still escaped.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct StatusLine<'a> {
+  pub version: Version,
+  pub code: StatusCode,
+  pub reason: &'a Binary
+}
+
+// start-line   =  Request-Line / Status-Line
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum StartLine<'a> {
+  Request(RequestLine<'a>),
+  Status(StatusLine<'a>)
+}
+
+pub mod parse {
+  use crate::msg::{Binary, Byte};
+  use crate::msg::abnf::ch::{token, sp, crlf, is_unreserved};
+  use crate::msg::abnf::absolute_uri::is_reserved;
+  use crate::msg::abnf::escape::escape_at;
+  use crate::msg::method::Method;
+  use crate::msg::status::parse::status_code;
+  use crate::msg::error::{ParseResult, rule};
+  use crate::msg::uri::parse::{uri, checked_uri};
+  use crate::msg::version::Version;
+  use crate::msg::version::parse::{version, strict_version};
+  use super::{RequestLine, StatusLine, StartLine};
+  use nom::{IResult, Needed};
+  use nom::Err::Incomplete;
+
+  // Method is taken as a whole token, so "INVITEX" is an extension
+  // method and not INVITE followed by garbage
+  #[inline]
+  fn request_line_with<V>(input: &Binary, version: V) -> IResult<&Binary, RequestLine<'_>>
+    where V: Fn(&Binary) -> IResult<&Binary, Version> {
+    let (rest, m) = token(input)?;
+    let m = Method::from_token(m);
+    let (rest, _) = sp(rest)?;
+    let (rest, u) = uri(rest)?;
+    let (rest, _) = sp(rest)?;
+    let (rest, v) = version(rest)?;
+    let (rest, _) = crlf(rest)?;
+    Ok((rest, RequestLine { method: m, uri: u, version: v }))
+  }
+
+  // request_line_with() with the failure named by the rule which didn't
+  // match. A separator which isn't there is blamed on the element before it.
+  pub fn checked_request_line<V>(input: &Binary, version: V) -> ParseResult<'_, RequestLine<'_>>
+    where V: Fn(&Binary) -> IResult<&Binary, Version> {
+    let (rest, m) = rule("Method", input, token(input))?;
+    let m = Method::from_token(m);
+    let (rest, _) = rule("Method", input, sp(rest))?;
+    let (rest, u) = checked_uri(input, rest)?;
+    // parameters and headers of a SIP-URI end where they stop matching,
+    // so whatever is left before the SP is one of them gone wrong
+    let uri_rule = match rest.first() {
+      Some(b';') => "uri-parameter",
+      Some(b'?') => "headers",
+      _ => "Request-URI"
+    };
+    let (rest, _) = rule(uri_rule, input, sp(rest))?;
+    let (rest, v) = rule("SIP-Version", input, version(rest))?;
+    let (rest, _) = rule("SIP-Version", input, crlf(rest))?;
+    Ok((rest, RequestLine { method: m, uri: u, version: v }))
+  }
+
+  #[inline]
+  pub fn request_line(input: &Binary) -> IResult<&Binary, RequestLine<'_>> {
+    request_line_with(input, version)
+  }
+
+  // Request-Line of SIP/2.0 only
+  #[inline]
+  pub fn strict_request_line(input: &Binary) -> IResult<&Binary, RequestLine<'_>> {
+    request_line_with(input, strict_version)
+  }
+
+  // characters allowed in Reason-Phrase without escaping
+  #[inline]
+  pub fn is_reason_char(b: Byte) -> bool {
+    is_reserved(b) || is_unreserved(b) || b >= 0x80 || b == b' ' || b == b'\t'
+  }
+
+  // Reason-Phrase   =  *(reserved / unreserved / escaped
+  //                    / UTF8-NONASCII / UTF8-CONT / SP / HTAB)
+  #[inline]
+  pub fn reason_phrase(input: &Binary) -> IResult<&Binary, &Binary> {
+    let mut i = 0usize;
+    while i < input.len() {
+      if is_reason_char(input[i]) {
+        i += 1;
+      } else if escape_at(input, i).is_some() {
+        i += 3;
+      } else if input[i] == b'%' && input.len() - i < 3 {
+        return Err(Incomplete(Needed::Size(3)));
+      } else {
+        break;
+      }
+    }
+    Ok((&input[i..], &input[..i]))
+  }
+
+  #[inline]
+  fn status_line_with<V>(input: &Binary, version: V) -> IResult<&Binary, StatusLine<'_>>
+    where V: Fn(&Binary) -> IResult<&Binary, Version> {
+    let (rest, v) = version(input)?;
+    let (rest, _) = sp(rest)?;
+    let (rest, code) = status_code(rest)?;
+    let (rest, _) = sp(rest)?;
+    let (rest, reason) = reason_phrase(rest)?;
+    let (rest, _) = crlf(rest)?;
+    Ok((rest, StatusLine { version: v, code, reason }))
+  }
+
+  // Status-Line with the failure named by the rule which didn't match,
+  // "SIP/2.0 2000 OK" has a malformed Status-Code
+  pub fn checked_status_line<V>(input: &Binary, version: V) -> ParseResult<'_, StatusLine<'_>>
+    where V: Fn(&Binary) -> IResult<&Binary, Version> {
+    let (rest, v) = rule("SIP-Version", input, version(input))?;
+    let (rest, _) = rule("SIP-Version", input, sp(rest))?;
+    let (rest, code) = rule("Status-Code", input, status_code(rest))?;
+    let (rest, _) = rule("Status-Code", input, sp(rest))?;
+    let (rest, reason) = rule("Reason-Phrase", input, reason_phrase(rest))?;
+    let (rest, _) = rule("Reason-Phrase", input, crlf(rest))?;
+    Ok((rest, StatusLine { version: v, code, reason }))
+  }
+
+  #[inline]
+  pub fn status_line(input: &Binary) -> IResult<&Binary, StatusLine<'_>> {
+    status_line_with(input, version)
+  }
+
+  // Status-Line of SIP/2.0 only
+  #[inline]
+  pub fn strict_status_line(input: &Binary) -> IResult<&Binary, StatusLine<'_>> {
+    status_line_with(input, strict_version)
+  }
+
+  // A token can't contain "/", so whatever starts with "SIP/" is a
+  // Status-Line
+  named!(#[inline], pub start_line<StartLine>, alt!(
+    status_line => { StartLine::Status } |
+    request_line => { StartLine::Request }
+  ));
+
+  // Dispatched on "SIP/" rather than tried in turn, so that another
+  // version is a Verify error at the version and not an Alt one
+  #[inline]
+  pub fn strict_start_line(input: &Binary) -> IResult<&Binary, StartLine<'_>> {
+    if input.len() >= 4 && input[..4].eq_ignore_ascii_case(b"SIP/") {
+      let (rest, line) = strict_status_line(input)?;
+      Ok((rest, StartLine::Status(line)))
+    } else {
+      let (rest, line) = strict_request_line(input)?;
+      Ok((rest, StartLine::Request(line)))
+    }
+  }
+
+  // start-line of the given versions with the failure named by the rule
+  // which didn't match, dispatched the same way
+  pub fn checked_start_line<V>(input: &Binary, version: V) -> ParseResult<'_, StartLine<'_>>
+    where V: Fn(&Binary) -> IResult<&Binary, Version> {
+    if input.len() >= 4 && input[..4].eq_ignore_ascii_case(b"SIP/") {
+      let (rest, line) = checked_status_line(input, version)?;
+      Ok((rest, StartLine::Status(line)))
+    } else {
+      let (rest, line) = checked_request_line(input, version)?;
+      Ok((rest, StartLine::Request(line)))
+    }
+  }
+}
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::uri::assemble::uri;
+  use crate::msg::status::assemble::status_code;
+  use crate::msg::method::assemble::method;
+  use crate::msg::version::assemble::version;
+  use crate::msg::output::{Output, AssembleResult};
+  use super::{RequestLine, StatusLine, StartLine};
+  use super::parse::is_reason_char;
+
+  pub fn request_line(l: RequestLine, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.with(|b| method(l.method, b))?;
+    out.put_byte(b' ')?;
+    out.with(|b| uri(l.uri, b))?;
+    out.put_byte(b' ')?;
+    out.with(|b| version(l.version, b))?;
+    out.put(b"\r\n")?;
+    out.finish()
+  }
+
+  pub fn status_line(l: StatusLine, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.with(|b| version(l.version, b))?;
+    out.put_byte(b' ')?;
+    out.with(|b| status_code(l.code, b))?;
+    out.put_byte(b' ')?;
+    out.put_component(l.reason, is_reason_char)?;
+    out.put(b"\r\n")?;
+    out.finish()
+  }
+
+  pub fn start_line(l: StartLine, buf: &mut Binary) -> AssembleResult {
+    match l {
+      StartLine::Request(r) => request_line(r, buf),
+      StartLine::Status(s) => status_line(s, buf)
+    }
+  }
+}
+
+pub mod owned {
+  use crate::msg::method::owned::Method;
+  use crate::msg::status::StatusCode;
+  use crate::msg::uri::owned::Uri;
+  use crate::msg::version::Version;
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub struct RequestLine {
+    pub method: Method,
+    pub uri: Uri,
+    pub version: Version
+  }
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub struct StatusLine {
+    pub version: Version,
+    pub code: StatusCode,
+    pub reason: Vec<u8>
+  }
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub enum StartLine {
+    Request(RequestLine),
+    Status(StatusLine)
+  }
+
+  impl RequestLine {
+    pub fn as_borrowed(&self) -> super::RequestLine<'_> {
+      super::RequestLine {
+        method: self.method.as_borrowed(),
+        uri: self.uri.as_borrowed(),
+        version: self.version
+      }
+    }
+  }
+
+  impl<'a> super::RequestLine<'a> {
+    pub fn into_owned(self) -> RequestLine {
+      RequestLine {
+        method: self.method.into_owned(),
+        uri: self.uri.into_owned(),
+        version: self.version
+      }
+    }
+  }
+
+  impl StatusLine {
+    pub fn as_borrowed(&self) -> super::StatusLine<'_> {
+      super::StatusLine {
+        version: self.version,
+        code: self.code,
+        reason: &self.reason
+      }
+    }
+  }
+
+  impl<'a> super::StatusLine<'a> {
+    pub fn into_owned(self) -> StatusLine {
+      StatusLine {
+        version: self.version,
+        code: self.code,
+        reason: self.reason.to_vec()
+      }
+    }
+  }
+
+  impl StartLine {
+    pub fn as_borrowed(&self) -> super::StartLine<'_> {
+      match self {
+        StartLine::Request(r) => super::StartLine::Request(r.as_borrowed()),
+        StartLine::Status(s) => super::StartLine::Status(s.as_borrowed())
+      }
+    }
+  }
+
+  impl<'a> super::StartLine<'a> {
+    pub fn into_owned(self) -> StartLine {
+      match self {
+        super::StartLine::Request(r) => StartLine::Request(r.into_owned()),
+        super::StartLine::Status(s) => StartLine::Status(s.into_owned())
+      }
+    }
+  }
+
+  impl<'a> From<super::StartLine<'a>> for StartLine {
+    fn from(l: super::StartLine<'a>) -> StartLine {
+      l.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a StartLine> for super::StartLine<'a> {
+    fn from(l: &'a StartLine) -> super::StartLine<'a> {
+      l.as_borrowed()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{StartLine, StatusLine, parse, assemble};
+  use crate::msg::method::Method;
+  use crate::msg::status::StatusCode;
+  use crate::msg::uri::Uri;
+  use crate::msg::version::Version;
+  use crate::msg::output::AssembleError;
+  use nom::{Err::{Incomplete, Error}, Needed, error::ErrorKind::{Digit, Tag, Verify}};
+
+  #[test]
+  fn request_line_parse_test() {
+    let (rest, line) = parse::request_line(b"INVITE sip:bob@biloxi.com SIP/2.0\r\nVia").unwrap();
+    assert_eq!(rest, b"Via");
+    assert_eq!(line.method, Method::Invite);
+    assert!(matches!(line.uri, Uri::Sip(_)));
+    assert_eq!(line.version, Version(2, 0));
+
+    let (_, line) = parse::request_line(b"INVITEX tel:+1-201-555-0123 SIP/2.0\r\n").unwrap();
+    assert_eq!(line.method, Method::Token(b"INVITEX"));
+    assert!(matches!(line.uri, Uri::Absolute(_)));
+
+    assert_eq!(parse::request_line(b"INVITE sip:bob@biloxi.com SIP/2.0"), Err(Incomplete(Needed::Size(1))));
+    assert!(matches!(parse::request_line(b"INVITE  sip:bob@biloxi.com SIP/2.0\r\n"), Err(Error(_))));
+    assert!(matches!(parse::request_line(b"INVITE sip:bob@biloxi.com SIP/2.0\n"), Err(Error(_))));
+  }
+
+  #[test]
+  fn status_line_parse_test() {
+    assert_eq!(
+      parse::status_line(b"SIP/2.0 180 Ringing\r\n"),
+      Ok((&b""[..], StatusLine { version: Version(2, 0), code: StatusCode::RINGING, reason: b"Ringing" }))
+    );
+    assert_eq!(
+      parse::status_line("SIP/2.0 404 Не найдено %41\tx\r\n".as_bytes()),
+      Ok((&b""[..], StatusLine { version: Version(2, 0), code: StatusCode::NOT_FOUND, reason: "Не найдено %41\tx".as_bytes() }))
+    );
+    assert_eq!(
+      parse::status_line(b"SIP/2.0 200 \r\n"),
+      Ok((&b""[..], StatusLine { version: Version(2, 0), code: StatusCode::OK, reason: b"" }))
+    );
+
+    assert_eq!(parse::status_line(b"SIP/2.0 20"), Err(Incomplete(Needed::Size(3))));
+    assert_eq!(parse::status_line(b"SIP/2.0 200 OK %4"), Err(Incomplete(Needed::Size(3))));
+    assert_eq!(parse::status_line(b"SIP/2.0 2x0 OK\r\n"), Err(Error((&b"2x0 OK\r\n"[..], Digit))));
+    assert_eq!(parse::status_line(b"SIP/2.0 099 OK\r\n"), Err(Error((&b"099 OK\r\n"[..], Verify))));
+    assert_eq!(parse::status_line(b"SIP/2.0 200 O\x01K\r\n"), Err(Error((&b"\x01K\r\n"[..], Tag))));
+  }
+
+  #[test]
+  fn start_line_parse_test() {
+    assert!(matches!(parse::start_line(b"SIP/2.0 200 OK\r\n"), Ok((_, StartLine::Status(_)))));
+    assert!(matches!(parse::start_line(b"SIP sip:x SIP/2.0\r\n"), Ok((_, StartLine::Request(_)))));
+    assert!(matches!(parse::start_line(b"BYE sip:x SIP/2.0\r\n"), Ok((_, StartLine::Request(_)))));
+    assert_eq!(parse::start_line(b"SI"), Err(Incomplete(Needed::Size(3))));
+    assert!(matches!(parse::start_line(b"SIP/3.10 200 OK\r\n"), Ok((_, StartLine::Status(StatusLine { version: Version(3, 10), .. })))));
+  }
+
+  #[test]
+  fn strict_start_line_parse_test() {
+    assert!(matches!(parse::strict_start_line(b"SIP/2.0 200 OK\r\n"), Ok((_, StartLine::Status(_)))));
+    assert!(matches!(parse::strict_start_line(b"SIP sip:x SIP/2.0\r\n"), Ok((_, StartLine::Request(_)))));
+    assert_eq!(parse::strict_start_line(b"SIP/2.1 200 OK\r\n"), Err(Error((&b"SIP/2.1 200 OK\r\n"[..], Verify))));
+    assert_eq!(parse::strict_start_line(b"BYE sip:x SIP/3.0\r\n"), Err(Error((&b"SIP/3.0\r\n"[..], Verify))));
+    assert_eq!(parse::strict_request_line(b"BYE sip:x SIP/2.00\r\n"), Err(Error((&b"SIP/2.00\r\n"[..], Verify))));
+    assert!(parse::strict_status_line(b"SIP/2.0 180 Ringing\r\n").is_ok());
+  }
+
+  #[test]
+  fn start_line_assemble_test() {
+    let mut buf = [0u8; 128];
+    for input in [
+      &b"INVITE sip:bob@biloxi.com;transport=tcp SIP/2.0\r\n"[..],
+      b"REGISTER sips:ss2.biloxi.example.com SIP/2.0\r\n",
+      b"MESSAGE tel:+1-201-555-0123 SIP/2.0\r\n",
+      b"SIP/2.0 180 Ringing\r\n",
+      b"SIP/2.0 200 \r\n",
+      b"SIP/2.0 603 Decline%21\r\n"
+    ].iter() {
+      let (_, line) = parse::start_line(input).unwrap();
+      let n = assemble::start_line(line, &mut buf).unwrap();
+      assert_eq!(&buf[..n], *input);
+
+      let owned = line.into_owned();
+      assert_eq!(owned.as_borrowed(), line);
+    }
+
+    let line = StatusLine { version: Version(2, 0), code: StatusCode::BUSY_HERE, reason: b"Busy \"Here\"" };
+    assert_eq!(assemble::status_line(line, &mut buf), Err(AssembleError::NotEscaped { written: 12 }));
+    let line = StatusLine { reason: b"Busy %22Here%22", ..line };
+    let n = assemble::status_line(line, &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"SIP/2.0 486 Busy %22Here%22\r\n");
+
+    assert_eq!(
+      assemble::status_line(line, &mut buf[..14]),
+      Err(AssembleError::NotEnoughOutSpace { written: 12 })
+    );
+  }
+}
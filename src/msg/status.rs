@@ -0,0 +1,267 @@
+// Response code of a Status-Line. RFC 3261 defines the classes 1xx to 6xx,
+// codes which are not registered are kept as is and, per 8.1.3.2, are to be
+// treated as the x00 code of their class.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash)]
+pub struct StatusCode(u16);
+
+macro_rules! status_codes (
+  ($( $name:ident = $code:literal, $reason:literal; )+) => (
+    impl StatusCode {
+      $( pub const $name: StatusCode = StatusCode($code); )+
+
+      // Reason-Phrase registered for the code, if any
+      pub fn registered_reason(&self) -> Option<&'static str> {
+        match self.0 {
+          $( $code => Some($reason), )+
+          _ => None
+        }
+      }
+    }
+  );
+);
+
+status_codes! {
+  // RFC 3261 21.1 Provisional 1xx
+  TRYING = 100, "Trying";
+  RINGING = 180, "Ringing";
+  CALL_IS_BEING_FORWARDED = 181, "Call Is Being Forwarded";
+  QUEUED = 182, "Queued";
+  SESSION_PROGRESS = 183, "Session Progress";
+  EARLY_DIALOG_TERMINATED = 199, "Early Dialog Terminated";
+
+  // 21.2 Successful 2xx
+  OK = 200, "OK";
+  ACCEPTED = 202, "Accepted";
+  NO_NOTIFICATION = 204, "No Notification";
+
+  // 21.3 Redirection 3xx
+  MULTIPLE_CHOICES = 300, "Multiple Choices";
+  MOVED_PERMANENTLY = 301, "Moved Permanently";
+  MOVED_TEMPORARILY = 302, "Moved Temporarily";
+  USE_PROXY = 305, "Use Proxy";
+  ALTERNATIVE_SERVICE = 380, "Alternative Service";
+
+  // 21.4 Request Failure 4xx
+  BAD_REQUEST = 400, "Bad Request";
+  UNAUTHORIZED = 401, "Unauthorized";
+  PAYMENT_REQUIRED = 402, "Payment Required";
+  FORBIDDEN = 403, "Forbidden";
+  NOT_FOUND = 404, "Not Found";
+  METHOD_NOT_ALLOWED = 405, "Method Not Allowed";
+  NOT_ACCEPTABLE = 406, "Not Acceptable";
+  PROXY_AUTHENTICATION_REQUIRED = 407, "Proxy Authentication Required";
+  REQUEST_TIMEOUT = 408, "Request Timeout";
+  GONE = 410, "Gone";
+  CONDITIONAL_REQUEST_FAILED = 412, "Conditional Request Failed";
+  REQUEST_ENTITY_TOO_LARGE = 413, "Request Entity Too Large";
+  REQUEST_URI_TOO_LONG = 414, "Request-URI Too Long";
+  UNSUPPORTED_MEDIA_TYPE = 415, "Unsupported Media Type";
+  UNSUPPORTED_URI_SCHEME = 416, "Unsupported URI Scheme";
+  UNKNOWN_RESOURCE_PRIORITY = 417, "Unknown Resource-Priority";
+  BAD_EXTENSION = 420, "Bad Extension";
+  EXTENSION_REQUIRED = 421, "Extension Required";
+  SESSION_INTERVAL_TOO_SMALL = 422, "Session Interval Too Small";
+  INTERVAL_TOO_BRIEF = 423, "Interval Too Brief";
+  BAD_LOCATION_INFORMATION = 424, "Bad Location Information";
+  BAD_ALERT_MESSAGE = 425, "Bad Alert Message";
+  USE_IDENTITY_HEADER = 428, "Use Identity Header";
+  PROVIDE_REFERRER_IDENTITY = 429, "Provide Referrer Identity";
+  FLOW_FAILED = 430, "Flow Failed";
+  ANONYMITY_DISALLOWED = 433, "Anonymity Disallowed";
+  BAD_IDENTITY_INFO = 436, "Bad Identity-Info";
+  UNSUPPORTED_CREDENTIAL = 437, "Unsupported Credential";
+  INVALID_IDENTITY_HEADER = 438, "Invalid Identity Header";
+  FIRST_HOP_LACKS_OUTBOUND_SUPPORT = 439, "First Hop Lacks Outbound Support";
+  MAX_BREADTH_EXCEEDED = 440, "Max-Breadth Exceeded";
+  BAD_INFO_PACKAGE = 469, "Bad Info Package";
+  CONSENT_NEEDED = 470, "Consent Needed";
+  TEMPORARILY_UNAVAILABLE = 480, "Temporarily Unavailable";
+  CALL_TRANSACTION_DOES_NOT_EXIST = 481, "Call/Transaction Does Not Exist";
+  LOOP_DETECTED = 482, "Loop Detected";
+  TOO_MANY_HOPS = 483, "Too Many Hops";
+  ADDRESS_INCOMPLETE = 484, "Address Incomplete";
+  AMBIGUOUS = 485, "Ambiguous";
+  BUSY_HERE = 486, "Busy Here";
+  REQUEST_TERMINATED = 487, "Request Terminated";
+  NOT_ACCEPTABLE_HERE = 488, "Not Acceptable Here";
+  BAD_EVENT = 489, "Bad Event";
+  REQUEST_PENDING = 491, "Request Pending";
+  UNDECIPHERABLE = 493, "Undecipherable";
+  SECURITY_AGREEMENT_REQUIRED = 494, "Security Agreement Required";
+
+  // 21.5 Server Failure 5xx
+  SERVER_INTERNAL_ERROR = 500, "Server Internal Error";
+  NOT_IMPLEMENTED = 501, "Not Implemented";
+  BAD_GATEWAY = 502, "Bad Gateway";
+  SERVICE_UNAVAILABLE = 503, "Service Unavailable";
+  SERVER_TIMEOUT = 504, "Server Time-out";
+  VERSION_NOT_SUPPORTED = 505, "Version Not Supported";
+  MESSAGE_TOO_LARGE = 513, "Message Too Large";
+  PUSH_NOTIFICATION_SERVICE_NOT_SUPPORTED = 555, "Push Notification Service Not Supported";
+  PRECONDITION_FAILURE = 580, "Precondition Failure";
+
+  // 21.6 Global Failures 6xx
+  BUSY_EVERYWHERE = 600, "Busy Everywhere";
+  DECLINE = 603, "Decline";
+  DOES_NOT_EXIST_ANYWHERE = 604, "Does Not Exist Anywhere";
+  NOT_ACCEPTABLE_ANYWHERE = 606, "Not Acceptable";
+  UNWANTED = 607, "Unwanted";
+  REJECTED = 608, "Rejected";
+}
+
+impl StatusCode {
+  // Codes out of 100-699 don't belong to any class
+  pub fn new(code: u16) -> Option<StatusCode> {
+    if (100..700).contains(&code) {
+      Some(StatusCode(code))
+    } else {
+      None
+    }
+  }
+
+  pub fn as_u16(&self) -> u16 {
+    self.0
+  }
+
+  // x00 code of the class, which is how an unknown code is handled
+  pub fn class(&self) -> StatusCode {
+    StatusCode(self.0 / 100 * 100)
+  }
+
+  pub fn is_provisional(&self) -> bool {
+    self.0 < 200
+  }
+
+  pub fn is_final(&self) -> bool {
+    self.0 >= 200
+  }
+
+  pub fn is_success(&self) -> bool {
+    (200..300).contains(&self.0)
+  }
+
+  pub fn is_redirection(&self) -> bool {
+    (300..400).contains(&self.0)
+  }
+
+  pub fn is_client_error(&self) -> bool {
+    (400..500).contains(&self.0)
+  }
+
+  pub fn is_server_error(&self) -> bool {
+    (500..600).contains(&self.0)
+  }
+
+  pub fn is_global_failure(&self) -> bool {
+    self.0 >= 600
+  }
+
+  pub fn is_registered(&self) -> bool {
+    self.registered_reason().is_some()
+  }
+
+  // Reason-Phrase to put into a response, an unregistered code gets
+  // the name of its class
+  pub fn reason_phrase(&self) -> &'static str {
+    self.registered_reason().unwrap_or(match self.0 / 100 {
+      1 => "Provisional",
+      2 => "Successful",
+      3 => "Redirection",
+      4 => "Request Failure",
+      5 => "Server Failure",
+      _ => "Global Failure"
+    })
+  }
+}
+
+impl From<StatusCode> for u16 {
+  fn from(code: StatusCode) -> u16 {
+    code.0
+  }
+}
+
+pub mod parse {
+  use crate::msg::{Binary, Byte};
+  use crate::msg::status::StatusCode;
+  use nom::IResult;
+  use nom::Err::Error;
+  use nom::error::ErrorKind::{Digit, Verify};
+
+  // Status-Code     =  Informational / Redirection / Success
+  //                    / Client-Error / Server-Error / Global-Failure
+  //                    / extension-code
+  // extension-code  =  3DIGIT
+  #[inline]
+  pub fn status_code(input: &Binary) -> IResult<&Binary, StatusCode> {
+    let (rest, digits) = take!(input, 3)?;
+    if !digits.iter().all(Byte::is_ascii_digit) {
+      return Err(Error((input, Digit)));
+    }
+    let code = digits.iter().fold(0u16, |acc, d| acc * 10 + (d - b'0') as u16);
+    match StatusCode::new(code) {
+      Some(code) => Ok((rest, code)),
+      None => Err(Error((input, Verify)))
+    }
+  }
+}
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::status::StatusCode;
+  use crate::msg::output::{Output, AssembleResult};
+
+  pub fn status_code(code: StatusCode, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.put_number(code.as_u16() as u64)?;
+    out.finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{StatusCode, parse, assemble};
+  use nom::{Err::{Incomplete, Error}, Needed, error::ErrorKind::{Digit, Verify}};
+
+  #[test]
+  fn status_code_test() {
+    assert_eq!(StatusCode::new(99), None);
+    assert_eq!(StatusCode::new(700), None);
+    assert_eq!(StatusCode::new(180), Some(StatusCode::RINGING));
+
+    let ringing = StatusCode::RINGING;
+    assert!(ringing.is_provisional() && !ringing.is_final());
+    assert!(StatusCode::OK.is_success() && StatusCode::OK.is_final());
+    assert!(StatusCode::MOVED_TEMPORARILY.is_redirection());
+    assert!(StatusCode::NOT_FOUND.is_client_error());
+    assert!(StatusCode::SERVICE_UNAVAILABLE.is_server_error());
+    assert!(StatusCode::DECLINE.is_global_failure());
+    assert!(!StatusCode::DECLINE.is_client_error());
+
+    assert_eq!(StatusCode::REQUEST_TERMINATED.reason_phrase(), "Request Terminated");
+    assert_eq!(StatusCode::CALL_TRANSACTION_DOES_NOT_EXIST.reason_phrase(), "Call/Transaction Does Not Exist");
+
+    let unknown = StatusCode::new(499).unwrap();
+    assert!(!unknown.is_registered());
+    assert_eq!(unknown.registered_reason(), None);
+    assert_eq!(unknown.reason_phrase(), "Request Failure");
+    assert_eq!(unknown.class(), StatusCode::BAD_REQUEST);
+    assert_eq!(u16::from(unknown), 499);
+  }
+
+  #[test]
+  fn status_code_parse_test() {
+    assert_eq!(parse::status_code(b"200 OK"), Ok((&b" OK"[..], StatusCode::OK)));
+    assert_eq!(parse::status_code(b"699"), Ok((&b""[..], StatusCode::new(699).unwrap())));
+    assert_eq!(parse::status_code(b"20"), Err(Incomplete(Needed::Size(3))));
+    assert_eq!(parse::status_code(b"2x0"), Err(Error((&b"2x0"[..], Digit))));
+    assert_eq!(parse::status_code(b"099"), Err(Error((&b"099"[..], Verify))));
+    assert_eq!(parse::status_code(b"700"), Err(Error((&b"700"[..], Verify))));
+  }
+
+  #[test]
+  fn status_code_assemble_test() {
+    let mut buf = [0u8; 3];
+    assert_eq!(assemble::status_code(StatusCode::BUSY_HERE, &mut buf), Ok(3));
+    assert_eq!(&buf, b"486");
+  }
+}
@@ -1,4 +1,3 @@
-
 // SIP elements MAY support Request-URIs with schemes other than
 // "sip" and "sips", for example the "tel" URI scheme of RFC
 // 2806 [9].  SIP elements MAY translate non-SIP URIs using any
@@ -11,8 +10,671 @@
 // SIPS-URI         =  "sips:" [ userinfo ] hostport
 //                    uri-parameters [ headers ]
 
-pub enum Uri {
-  Absolute(),
-  Sip(),
-  Sips()
+use super::{Binary, Byte};
+use super::abnf::host::Host;
+use super::abnf::uri_params::{UriParameters, UriParam};
+use super::abnf::uri_headers::UriHeaders;
+use super::abnf::escape::{escape_at, decode};
+use super::header::HeaderName;
+use super::method::Method;
+use std::borrow::Cow;
+use super::abnf::absolute_uri::{AbsoluteUri, Part, Authority, is_reserved};
+
+// Everything a SIP-URI or SIPS-URI is made of. User, password,
+// parameters and headers are kept exactly as they appear in the input,
+// i.e. still escaped.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct SipUri<'a> {
+  pub user: Option<&'a Binary>,
+  pub password: Option<&'a Binary>,
+  pub host: Host<'a>,
+  pub port: Option<u16>,
+  pub parameters: UriParameters<'a>,
+  pub headers: UriHeaders<'a>
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Uri<'a> {
+  Absolute(AbsoluteUri<'a>),
+  Sip(SipUri<'a>),
+  Sips(SipUri<'a>)
+}
+
+// RFC 3261 19.1.4 URI Comparison
+impl<'a> Uri<'a> {
+  pub fn equivalent(&self, other: &Uri) -> bool {
+    match (self, other) {
+      (Uri::Sip(a), Uri::Sip(b)) | (Uri::Sips(a), Uri::Sips(b)) => a.equivalent(b),
+      (Uri::Absolute(a), Uri::Absolute(b)) => absolute_equivalent(a, b),
+      _ => false
+    }
+  }
+}
+
+impl<'a> SipUri<'a> {
+  // Scheme is not a part of SipUri, so sip: and sips: URIs with the
+  // same components are equivalent here, Uri::equivalent tells them apart
+  pub fn equivalent(&self, other: &SipUri) -> bool {
+    // userinfo is case-sensitive, everything else is not
+    option_same(self.user, other.user, false)
+      && option_same(self.password, other.password, false)
+      && host_equivalent(self.host, other.host)
+      && self.port == other.port
+      && parameters_equivalent(self.parameters, other.parameters)
+      && headers_equivalent(self.headers, other.headers)
+  }
+}
+
+// Unit of an escaped component. "%HH" of a character outside of the
+// reserved set is the same as the character itself, an escaped reserved
+// character is not (e.g. "%3B" in user differs from ";").
+#[derive(Eq, PartialEq)]
+enum Unit {
+  Plain(Byte),
+  Reserved(Byte)
+}
+
+struct Units<'a> {
+  rest: &'a Binary,
+  ignore_case: bool
+}
+
+impl<'a> Iterator for Units<'a> {
+  type Item = Unit;
+
+  fn next(&mut self) -> Option<Unit> {
+    let (&b, rest) = self.rest.split_first()?;
+    let escape = escape_at(self.rest, 0);
+
+    let unit = match escape {
+      Some(d) if is_reserved(d) => {
+        self.rest = &rest[2..];
+        Unit::Reserved(d)
+      },
+      Some(d) => {
+        self.rest = &rest[2..];
+        Unit::Plain(d)
+      },
+      None => {
+        self.rest = rest;
+        Unit::Plain(b)
+      }
+    };
+
+    Some(match unit {
+      Unit::Plain(b) if self.ignore_case => Unit::Plain(b.to_ascii_lowercase()),
+      u => u
+    })
+  }
+}
+
+fn same(a: &Binary, b: &Binary, ignore_case: bool) -> bool {
+  Iterator::eq(Units { rest: a, ignore_case }, Units { rest: b, ignore_case })
+}
+
+fn option_same(a: Option<&Binary>, b: Option<&Binary>, ignore_case: bool) -> bool {
+  match (a, b) {
+    (Some(a), Some(b)) => same(a, b, ignore_case),
+    (None, None) => true,
+    _ => false
+  }
+}
+
+fn host_equivalent(a: Host, b: Host) -> bool {
+  match (a, b) {
+    (Host::Hostname(a), Host::Hostname(b)) => a.as_bytes().eq_ignore_ascii_case(b.as_bytes()),
+    (a, b) => a == b
+  }
+}
+
+enum ParamValue<'a> {
+  Bytes(Option<&'a Binary>),
+  Method(Method<'a>),
+  Ttl(u8),
+  Maddr(Host<'a>)
+}
+
+fn param_parts(p: UriParam<'_>) -> (&Binary, ParamValue<'_>) {
+  match p {
+    UriParam::Transport(t) => (b"transport", ParamValue::Bytes(Some(t.as_bytes()))),
+    UriParam::User(u) => (b"user", ParamValue::Bytes(Some(u.as_bytes()))),
+    UriParam::Method(m) => (b"method", ParamValue::Method(m)),
+    UriParam::Ttl(ttl) => (b"ttl", ParamValue::Ttl(ttl)),
+    UriParam::Maddr(h) => (b"maddr", ParamValue::Maddr(h)),
+    UriParam::Lr => (b"lr", ParamValue::Bytes(None)),
+    UriParam::Other(name, value) => (name, ParamValue::Bytes(value))
+  }
+}
+
+fn param_values_equivalent(a: ParamValue, b: ParamValue) -> bool {
+  match (a, b) {
+    (ParamValue::Bytes(a), ParamValue::Bytes(b)) => option_same(a, b, true),
+    // methods are case-sensitive (RFC 3261 7.1)
+    (ParamValue::Method(a), ParamValue::Method(b)) => a == b,
+    (ParamValue::Ttl(a), ParamValue::Ttl(b)) => a == b,
+    (ParamValue::Maddr(a), ParamValue::Maddr(b)) => host_equivalent(a, b),
+    _ => false
+  }
+}
+
+// user, ttl, method, maddr and transport appearing in only one URI
+// make URIs different, all the others are ignored unless present in both
+fn is_significant(name: &Binary) -> bool {
+  [&b"user"[..], b"ttl", b"method", b"maddr", b"transport"]
+    .iter()
+    .any(|n| same(name, n, true))
+}
+
+fn find_param<'a>(params: UriParameters<'a>, name: &Binary) -> Option<ParamValue<'a>> {
+  params.iter()
+    .map(param_parts)
+    .find(|(n, _)| same(n, name, true))
+    .map(|(_, v)| v)
+}
+
+fn parameters_equivalent(a: UriParameters, b: UriParameters) -> bool {
+  let one_side = |a: UriParameters, b: UriParameters| {
+    a.iter().map(param_parts).all(|(name, value)| {
+      match find_param(b, name) {
+        Some(other) => param_values_equivalent(value, other),
+        None => !is_significant(name)
+      }
+    })
+  };
+
+  one_side(a, b) && one_side(b, a)
+}
+
+// Header components match one to one, so a header repeated in one URI
+// has to be repeated in the other as well. Names follow the header name
+// rules, compact forms included, values are compared case-sensitively
+// once unescaped.
+fn headers_equivalent(a: UriHeaders, b: UriHeaders) -> bool {
+  let (a, b) = (decoded_headers(a), decoded_headers(b));
+  if a.len() != b.len() {
+    return false;
+  }
+
+  let mut matched = vec![false; b.len()];
+  a.iter().all(|(name, value)| {
+    let found = b.iter().enumerate().position(|(i, (n, v))| {
+      !matched[i] && HeaderName::from_bytes(name) == HeaderName::from_bytes(n) && value == v
+    });
+    found.map(|i| matched[i] = true).is_some()
+  })
+}
+
+fn decoded_headers(h: UriHeaders<'_>) -> Vec<(Cow<'_, Binary>, Cow<'_, Binary>)> {
+  h.iter().map(|(n, v)| (decode(n), decode(v))).collect()
+}
+
+fn authority_equivalent(a: Option<Authority>, b: Option<Authority>) -> bool {
+  match (a, b) {
+    (Some(Authority::Server(a)), Some(Authority::Server(b))) => {
+      option_same(a.userinfo, b.userinfo, false)
+        && host_equivalent(a.host, b.host)
+        && a.port == b.port
+    },
+    (Some(Authority::RegName(a)), Some(Authority::RegName(b))) => same(a, b, false),
+    (Some(Authority::Empty), Some(Authority::Empty)) | (None, None) => true,
+    _ => false
+  }
+}
+
+// absoluteURIs are compared by RFC 2396 means: scheme and host are
+// case-insensitive, escapes of unreserved characters are not significant
+fn absolute_equivalent(a: &AbsoluteUri, b: &AbsoluteUri) -> bool {
+  a.scheme.eq_ignore_ascii_case(b.scheme) && match (a.part, b.part) {
+    (Part::Opaque(a), Part::Opaque(b)) => same(a, b, false),
+    (
+      Part::Hier { authority: a_auth, path: a_path, query: a_query },
+      Part::Hier { authority: b_auth, path: b_path, query: b_query }
+    ) => {
+      authority_equivalent(a_auth, b_auth)
+        && option_same(a_path.map(|p| p.as_bytes()), b_path.map(|p| p.as_bytes()), false)
+        && option_same(a_query, b_query, false)
+    },
+    _ => false
+  }
+}
+
+pub mod parse {
+  use crate::msg::Binary;
+  use crate::msg::abnf::userinfo::userinfo;
+  use crate::msg::abnf::host::hostport;
+  use crate::msg::abnf::uri_params::lazy_uri_parameters;
+  use crate::msg::abnf::uri_headers::{lazy_headers, UriHeaders};
+  use crate::msg::abnf::absolute_uri::{absolute_uri, AbsoluteUri};
+  use crate::msg::error::{ParseResult, rule};
+  use crate::msg::uri::{Uri, SipUri};
+  use nom::IResult;
+
+  // [ userinfo ] hostport uri-parameters [ headers ]
+  #[inline]
+  pub fn sip_uri(input: &Binary) -> IResult<&Binary, SipUri<'_>> {
+    // userinfo is recognized by its trailing "@", whatever else
+    // happened it belongs to hostport
+    let (rest, (user, password)) = match userinfo(input) {
+      Ok((r, (u, p))) => (r, (Some(u), p)),
+      Err(_) => (input, (None, None))
+    };
+
+    let (rest, (host, port)) = hostport(rest)?;
+    let (rest, parameters) = lazy_uri_parameters(rest)?;
+    let (rest, headers) = match lazy_headers(rest) {
+      Ok((r, h)) => (r, h),
+      Err(_) => (rest, UriHeaders::default())
+    };
+
+    Ok((rest, SipUri { user, password, host, port, parameters, headers }))
+  }
+
+  // sip_uri() with the failure named by the rule which didn't match,
+  // offsets count from `base`. Parameters and headers end wherever they
+  // stop matching, so hostport is all that can fail.
+  pub fn checked_sip_uri<'a>(base: &Binary, input: &'a Binary) -> ParseResult<'a, SipUri<'a>> {
+    rule("hostport", base, sip_uri(input))
+  }
+
+  // Request-URI      =  SIP-URI / SIPS-URI / absoluteURI
+  //
+  // sip and sips schemes never fall back to absoluteURI,
+  // a malformed SIP-URI is an error rather than an opaque URI
+  named!(#[inline], pub uri<Uri>, alt!(
+    preceded!(tag_no_case!("sip:"), sip_uri) => { Uri::Sip } |
+    preceded!(tag_no_case!("sips:"), sip_uri) => { Uri::Sips } |
+    verify!(absolute_uri, |a: &AbsoluteUri| {
+      !a.scheme.eq_ignore_ascii_case(b"sip") && !a.scheme.eq_ignore_ascii_case(b"sips")
+    }) => { Uri::Absolute }
+  ));
+
+  // uri() with the failure named by the rule which didn't match
+  pub fn checked_uri<'a>(base: &Binary, input: &'a Binary) -> ParseResult<'a, Uri<'a>> {
+    let scheme = |s: &[u8]| input.len() >= s.len() && input[..s.len()].eq_ignore_ascii_case(s);
+
+    if scheme(b"sip:") {
+      let (rest, u) = checked_sip_uri(base, &input[4..])?;
+      Ok((rest, Uri::Sip(u)))
+    } else if scheme(b"sips:") {
+      let (rest, u) = checked_sip_uri(base, &input[5..])?;
+      Ok((rest, Uri::Sips(u)))
+    } else {
+      let (rest, u) = rule("absoluteURI", base, absolute_uri(input))?;
+      Ok((rest, Uri::Absolute(u)))
+    }
+  }
+}
+
+pub mod assemble {
+  use crate::msg::Binary;
+  use crate::msg::uri::{Uri, SipUri};
+  use crate::msg::abnf::userinfo::assemble::userinfo;
+  use crate::msg::abnf::host::assemble::hostport;
+  use crate::msg::abnf::uri_params::assemble::uri_parameters;
+  use crate::msg::abnf::uri_headers::assemble::headers;
+  use crate::msg::abnf::absolute_uri::assemble::absolute_uri;
+  use crate::msg::output::{Output, AssembleResult};
+
+  // [ userinfo ] hostport uri-parameters [ headers ]
+  pub fn sip_uri(u: SipUri, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    if let Some(user) = u.user {
+      out.with(|b| userinfo(user, u.password, b))?;
+    }
+    out.with(|b| hostport(u.host, u.port, b))?;
+    out.with(|b| uri_parameters(u.parameters, b))?;
+    out.with(|b| headers(u.headers, b))?;
+    out.finish()
+  }
+
+  pub fn uri(u: Uri, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    match u {
+      Uri::Sip(sip) => {
+        out.put(b"sip:")?;
+        out.with(|b| sip_uri(sip, b))?;
+      },
+      Uri::Sips(sip) => {
+        out.put(b"sips:")?;
+        out.with(|b| sip_uri(sip, b))?;
+      },
+      Uri::Absolute(abs) => out.with(|b| absolute_uri(abs, b))?
+    }
+    out.finish()
+  }
+}
+
+// URIs which outlive the receive buffer, e.g. for a registrar table.
+// Conversion to the owned form copies every component once, conversion
+// back only borrows, so all the borrowed API (equivalent, assemble, ...)
+// is available through as_borrowed.
+pub mod owned {
+  use crate::msg::abnf::host::owned::Host;
+  use crate::msg::abnf::uri_params::owned::UriParameters;
+  use crate::msg::abnf::uri_headers::owned::UriHeaders;
+  use crate::msg::abnf::absolute_uri::owned::AbsoluteUri;
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub struct SipUri {
+    pub user: Option<Vec<u8>>,
+    pub password: Option<Vec<u8>>,
+    pub host: Host,
+    pub port: Option<u16>,
+    pub parameters: UriParameters,
+    pub headers: UriHeaders
+  }
+
+  #[derive(Debug, Eq, PartialEq, Clone, Hash)]
+  pub enum Uri {
+    Absolute(AbsoluteUri),
+    Sip(SipUri),
+    Sips(SipUri)
+  }
+
+  impl SipUri {
+    pub fn as_borrowed(&self) -> super::SipUri<'_> {
+      super::SipUri {
+        user: self.user.as_deref(),
+        password: self.password.as_deref(),
+        host: self.host.as_borrowed(),
+        port: self.port,
+        parameters: self.parameters.as_borrowed(),
+        headers: self.headers.as_borrowed()
+      }
+    }
+  }
+
+  impl<'a> super::SipUri<'a> {
+    pub fn into_owned(self) -> SipUri {
+      SipUri {
+        user: self.user.map(<[u8]>::to_vec),
+        password: self.password.map(<[u8]>::to_vec),
+        host: self.host.into_owned(),
+        port: self.port,
+        parameters: self.parameters.into_owned(),
+        headers: self.headers.into_owned()
+      }
+    }
+  }
+
+  impl Uri {
+    pub fn as_borrowed(&self) -> super::Uri<'_> {
+      match self {
+        Uri::Absolute(u) => super::Uri::Absolute(u.as_borrowed()),
+        Uri::Sip(u) => super::Uri::Sip(u.as_borrowed()),
+        Uri::Sips(u) => super::Uri::Sips(u.as_borrowed())
+      }
+    }
+  }
+
+  impl<'a> super::Uri<'a> {
+    pub fn into_owned(self) -> Uri {
+      match self {
+        super::Uri::Absolute(u) => Uri::Absolute(u.into_owned()),
+        super::Uri::Sip(u) => Uri::Sip(u.into_owned()),
+        super::Uri::Sips(u) => Uri::Sips(u.into_owned())
+      }
+    }
+  }
+
+  impl<'a> From<super::SipUri<'a>> for SipUri {
+    fn from(u: super::SipUri<'a>) -> SipUri {
+      u.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a SipUri> for super::SipUri<'a> {
+    fn from(u: &'a SipUri) -> super::SipUri<'a> {
+      u.as_borrowed()
+    }
+  }
+
+  impl<'a> From<super::Uri<'a>> for Uri {
+    fn from(u: super::Uri<'a>) -> Uri {
+      u.into_owned()
+    }
+  }
+
+  impl<'a> From<&'a Uri> for super::Uri<'a> {
+    fn from(u: &'a Uri) -> super::Uri<'a> {
+      u.as_borrowed()
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{parse, assemble, Uri, SipUri};
+  use crate::msg::abnf::host::{Host, Hostname};
+  use crate::msg::abnf::uri_params::{UriParam, Transport, User};
+  use crate::msg::method::Method;
+  use crate::msg::abnf::absolute_uri::Part;
+  use crate::msg::abnf::uri_params::UriParameters;
+  use crate::msg::abnf::uri_headers::UriHeaders;
+  use crate::msg::output::AssembleError;
+  use crate::msg::error::ParseError;
+  use nom::{Err::{Incomplete, Error}, Needed, error::ErrorKind::Alt};
+  use std::net::Ipv4Addr;
+
+  fn sip(uri: &str) -> SipUri<'_> {
+    match parse::uri(uri.as_bytes()) {
+      Ok((&[], Uri::Sip(u))) => u,
+      other => panic!("{} is parsed as {:?}", uri, other)
+    }
+  }
+
+  #[test]
+  fn uri_parse_test() {
+    let u = sip("sip:alice@atlanta.com");
+    assert_eq!(u.user, Some("alice".as_bytes()));
+    assert_eq!(u.password, None);
+    assert_eq!(u.host, Host::Hostname(Hostname::new(b"atlanta.com").unwrap()));
+    assert_eq!(u.port, None);
+    assert!(u.parameters.is_empty());
+    assert!(u.headers.is_empty());
+
+    let u = sip("sip:alice:secretword@atlanta.com;transport=tcp");
+    assert_eq!(u.user, Some("alice".as_bytes()));
+    assert_eq!(u.password, Some("secretword".as_bytes()));
+    assert_eq!(u.parameters.iter().collect::<Vec<_>>(), vec![UriParam::Transport(Transport::TCP)]);
+
+    let u = sip("sip:+1-212-555-1212:1234@gateway.com;user=phone");
+    assert_eq!(u.user, Some("+1-212-555-1212".as_bytes()));
+    assert_eq!(u.password, Some("1234".as_bytes()));
+    assert_eq!(u.parameters.iter().collect::<Vec<_>>(), vec![UriParam::User(User::Phone)]);
+
+    let u = sip("sip:alice@192.0.2.4:5060");
+    assert_eq!(u.host, Host::Ipv4(Ipv4Addr::new(192, 0, 2, 4)));
+    assert_eq!(u.port, Some(5060));
+
+    let u = sip("sip:atlanta.com;method=REGISTER?to=alice%40atlanta.com");
+    assert_eq!(u.user, None);
+    assert_eq!(u.host, Host::Hostname(Hostname::new(b"atlanta.com").unwrap()));
+    assert_eq!(u.parameters.iter().collect::<Vec<_>>(), vec![UriParam::Method(Method::Register)]);
+    assert_eq!(u.headers.iter().collect::<Vec<_>>(), vec![("to".as_bytes(), "alice%40atlanta.com".as_bytes())]);
+
+    let u = sip("sip:alice;day=tuesday@atlanta.com");
+    assert_eq!(u.user, Some("alice;day=tuesday".as_bytes()));
+
+    let u = sip("sip:%61lice@atlanta.com:5070");
+    assert_eq!(u.user, Some("%61lice".as_bytes()));
+    assert_eq!(u.port, Some(5070));
+
+    match parse::uri("sips:alice@atlanta.com?subject=project%20x&priority=urgent SIP/2.0".as_bytes()) {
+      Ok((rest, Uri::Sips(u))) => {
+        assert_eq!(rest, " SIP/2.0".as_bytes());
+        assert_eq!(u.headers.iter().collect::<Vec<_>>(), vec![
+          ("subject".as_bytes(), "project%20x".as_bytes()),
+          ("priority".as_bytes(), "urgent".as_bytes())
+        ]);
+      },
+      other => panic!("unexpected {:?}", other)
+    }
+
+    match parse::uri("SIP:bob@biloxi.com>;tag=a6c85cf".as_bytes()) {
+      Ok((rest, Uri::Sip(u))) => {
+        assert_eq!(rest, ">;tag=a6c85cf".as_bytes());
+        assert_eq!(u.user, Some("bob".as_bytes()));
+      },
+      other => panic!("unexpected {:?}", other)
+    }
+  }
+
+  #[test]
+  fn absolute_uri_parse_test() {
+    match parse::uri("tel:+1-201-555-0123 SIP/2.0".as_bytes()) {
+      Ok((rest, Uri::Absolute(u))) => {
+        assert_eq!(rest, " SIP/2.0".as_bytes());
+        assert_eq!(u.scheme, "tel".as_bytes());
+        assert_eq!(u.part, Part::Opaque("+1-201-555-0123".as_bytes()));
+      },
+      other => panic!("unexpected {:?}", other)
+    }
+  }
+
+  #[test]
+  fn uri_parse_fail_test() {
+    assert_eq!(parse::uri("si".as_bytes()), Err(Incomplete(Needed::Size(4))));
+    assert_eq!(parse::uri("1tel:+1234".as_bytes()), Err(Error(("1tel:+1234".as_bytes(), Alt))));
+    assert_eq!(parse::checked_uri(b"sip:a@b;ttl=x ", b"sip:a@b;ttl=x ").map(|(r, _)| r), Ok(&b" "[..]));
+    assert_eq!(parse::checked_uri(b"sip:a@b?x ", b"sip:a@b?x ").map(|(r, _)| r), Ok(&b"?x "[..]));
+    assert_eq!(parse::checked_uri(b"sip:a@ b", b"sip:a@ b"), Err(ParseError::new("hostport", 6)));
+    assert!(parse::uri("sip:alice@".as_bytes()).is_err());
+    assert!(parse::uri("sip:@@@ ".as_bytes()).is_err());
+    assert!(parse::uri("sip:alice@atlanta.com:0".as_bytes()).is_err());
+  }
+
+  #[test]
+  fn uri_assemble_test() {
+    let mut buf = [0u8; 128];
+    for uri in &[
+      "sip:alice@atlanta.com",
+      "sip:alice:secretword@atlanta.com;transport=tcp",
+      "sips:alice@atlanta.com?subject=project%20x&priority=urgent",
+      "sip:+1-212-555-1212:1234@gateway.com;user=phone",
+      "sip:alice@192.0.2.4:5060;lr;maddr=[2001:db8::10]",
+      "sip:atlanta.com;method=REGISTER?to=alice%40atlanta.com",
+      "sip:alice;day=tuesday@atlanta.com",
+      "tel:+1-201-555-0123"
+    ] {
+      let (_, parsed) = parse::uri(uri.as_bytes()).unwrap();
+      let n = assemble::uri(parsed, &mut buf).unwrap();
+      assert_eq!(std::str::from_utf8(&buf[..n]).unwrap(), *uri);
+    }
+
+    let sips = SipUri {
+      user: Some(b"bob%20smith"),
+      password: None,
+      host: Host::Hostname(Hostname::new(b"biloxi.com").unwrap()),
+      port: Some(5061),
+      parameters: UriParameters::default(),
+      headers: UriHeaders::default()
+    };
+    let uri = Uri::Sips(sips);
+    let n = assemble::uri(uri, &mut buf).unwrap();
+    assert_eq!(&buf[..n], b"sips:bob%20smith@biloxi.com:5061".as_ref());
+
+    assert_eq!(assemble::uri(uri, &mut buf[..10]), Err(AssembleError::NotEnoughOutSpace { written: 5 }));
+    let uri = Uri::Sips(SipUri { user: Some(b"bob smith"), ..sips });
+    assert_eq!(assemble::uri(uri, &mut buf), Err(AssembleError::NotEscaped { written: 5 }));
+  }
+
+  fn equivalent(a: &str, b: &str) -> bool {
+    let (_, a) = parse::uri(a.as_bytes()).unwrap();
+    let (_, b) = parse::uri(b.as_bytes()).unwrap();
+    a.equivalent(&b) && b.equivalent(&a)
+  }
+
+  #[test]
+  fn uri_equivalent_test() {
+    // RFC 3261 19.1.4, equivalent URIs
+    assert!(equivalent("sip:%61lice@atlanta.com;transport=TCP", "sip:alice@AtLanTa.CoM;Transport=tcp"));
+    assert!(equivalent("sip:carol@chicago.com", "sip:carol@chicago.com;newparam=5"));
+    assert!(equivalent("sip:carol@chicago.com", "sip:carol@chicago.com;security=on"));
+    assert!(equivalent("sip:carol@chicago.com;newparam=5", "sip:carol@chicago.com;security=on"));
+    assert!(equivalent(
+      "sip:biloxi.com;transport=tcp;method=REGISTER?to=sip:bob%40biloxi.com",
+      "sip:biloxi.com;method=REGISTER;transport=tcp?to=sip:bob%40biloxi.com"
+    ));
+    assert!(equivalent(
+      "sip:alice@atlanta.com?subject=project%20x&priority=urgent",
+      "sip:alice@atlanta.com?priority=urgent&subject=project%20x"
+    ));
+
+    // RFC 3261 19.1.4, non-equivalent URIs
+    assert!(!equivalent("SIP:ALICE@AtLanTa.CoM;Transport=udp", "sip:alice@AtLanTa.CoM;Transport=UDP"));
+    assert!(!equivalent("sip:bob@biloxi.com", "sip:bob@biloxi.com:5060"));
+    assert!(!equivalent("sip:bob@biloxi.com", "sip:bob@biloxi.com;transport=udp"));
+    assert!(!equivalent("sip:bob@biloxi.com", "sip:bob@biloxi.com:6000;transport=tcp"));
+    assert!(!equivalent("sip:carol@chicago.com", "sip:carol@chicago.com?Subject=next%20meeting"));
+    assert!(equivalent("sip:carol@chicago.com?Subject=next%20meeting", "sip:carol@chicago.com?subject=next%20meeting"));
+    assert!(equivalent("sip:carol@chicago.com?a=%41", "sip:carol@chicago.com?a=A"));
+    assert!(equivalent("sip:carol@chicago.com?m=%3Csip:a%40b%3E&a=1", "sip:carol@chicago.com?a=1&Contact=%3csip:a%40b%3e"));
+    assert!(!equivalent("sip:carol@chicago.com?Subject=Next", "sip:carol@chicago.com?Subject=next"));
+    assert!(!equivalent("sip:carol@chicago.com?a=1&a=1", "sip:carol@chicago.com?a=1"));
+    assert!(!equivalent("sip:carol@chicago.com?a=1&a=1&b=2", "sip:carol@chicago.com?a=1&b=2&b=2"));
+    assert!(equivalent("sip:carol@chicago.com?a=1&b=2&a=1", "sip:carol@chicago.com?a=1&a=1&b=2"));
+    assert!(!equivalent("sip:bob@phone21.boxesbybob.com", "sip:bob@192.0.2.4"));
+    assert!(!equivalent("sip:carol@chicago.com;security=on", "sip:carol@chicago.com;security=off"));
+
+    assert!(!equivalent("sip:alice@atlanta.com", "sips:alice@atlanta.com"));
+    assert!(!equivalent("sip:alice@atlanta.com", "sip:atlanta.com"));
+    assert!(!equivalent("sip:alice:x@atlanta.com", "sip:alice@atlanta.com"));
+    assert!(!equivalent("sip:a%3Bb@atlanta.com", "sip:a;b@atlanta.com"));
+    assert!(!equivalent("sip:atlanta.com;maddr=239.255.255.1", "sip:atlanta.com"));
+    assert!(!equivalent("sip:atlanta.com;ttl=1", "sip:atlanta.com;ttl=2"));
+    assert!(!equivalent("sip:atlanta.com;method=invite", "sip:atlanta.com;method=INVITE"));
+    assert!(equivalent("sip:atlanta.com;method=INVITE", "sip:atlanta.com;METHOD=INVITE"));
+    assert!(equivalent("sip:atlanta.com;maddr=Example.COM;lr", "sip:atlanta.com;lr;maddr=example.com"));
+
+    assert!(equivalent("tel:+1-201-555-%30123", "TEL:+1-201-555-0123"));
+    assert!(!equivalent("tel:+1-201-555-0123", "sip:+1-201-555-0123@atlanta.com"));
+    assert!(equivalent("http://User@Example.COM/a%7e", "HTTP://User@example.com/a~"));
+    assert!(!equivalent("http://User@example.com/", "http://user@example.com/"));
+  }
+
+  #[test]
+  fn uri_owned_test() {
+    use super::owned;
+    use std::collections::HashMap;
+
+    let mut table: HashMap<owned::Uri, u32> = HashMap::new();
+    let inputs = [
+      "sip:alice:pw@atlanta.com:5061;transport=tcp;maddr=10.0.0.1;method=REFER;x;y=1?subject=hi",
+      "sips:[::1]",
+      "http://user@example.com:8080/a/b;p?q=1",
+      "tel:+1-201-555-0123",
+      "ftp://r@g;n/"
+    ];
+
+    for (i, input) in inputs.iter().enumerate() {
+      let buf = input.as_bytes().to_vec();
+      let (_, uri) = parse::uri(&buf).unwrap();
+      let owned = uri.into_owned();
+      assert_eq!(owned.as_borrowed(), uri);
+      assert_eq!(Uri::from(&owned), uri);
+      assert_eq!(owned::Uri::from(uri), owned);
+      drop(buf);
+      table.insert(owned, i as u32);
+    }
+
+    for (i, input) in inputs.iter().enumerate() {
+      let (_, uri) = parse::uri(input.as_bytes()).unwrap();
+      assert_eq!(table.get(&uri.into_owned()), Some(&(i as u32)));
+
+      let mut out = [0u8; 128];
+      let key = table.keys().find(|k| table[*k] == i as u32).unwrap();
+      let n = assemble::uri(key.as_borrowed(), &mut out).unwrap();
+      assert_eq!(&out[..n], input.as_bytes());
+    }
+
+    let (_, uri) = parse::uri(inputs[0].as_bytes()).unwrap();
+    if let Uri::Sip(sip) = uri {
+      let params: Vec<_> = sip.parameters.iter().map(UriParam::into_owned).collect();
+      let borrowed: Vec<_> = params.iter().map(|p| p.as_borrowed()).collect();
+      assert_eq!(borrowed, sip.parameters.iter().collect::<Vec<_>>());
+    } else {
+      panic!("sip uri expected");
+    }
+  }
 }
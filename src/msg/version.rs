@@ -4,37 +4,63 @@
 // ...
 // The SIP-Version string is case-insensitive,
 // but implementations MUST send upper-case.
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
 pub struct Version(pub u8, pub u8);
 
-pub const DEFAULT_VERSION: Version = Version(2, 2);
+pub const DEFAULT_VERSION: Version = Version(2, 0);
 
 pub mod parse {
-  use crate::msg::abnf::digit::single as single_digit;
-  use crate::msg::version::Version;
+  use crate::msg::Binary;
+  use crate::msg::abnf::digit::decimal;
+  use crate::msg::version::{Version, DEFAULT_VERSION};
+  use nom::{IResult, Needed};
+  use nom::Err::{Error, Incomplete};
+  use nom::error::ErrorKind::Verify;
+
+  // 1*DIGIT of a version number, which is followed by something on the
+  // wire
+  #[inline]
+  fn number(input: &Binary) -> IResult<&Binary, u8> {
+    if input.iter().all(u8::is_ascii_digit) {
+      return Err(Incomplete(Needed::Size(1)));
+    }
+    let (rest, n) = decimal(input, u8::MAX as u64)?;
+    Ok((rest, n as u8))
+  }
 
   // SIP-Version    =  "SIP" "/" 1*DIGIT "." 1*DIGIT
   named!(#[inline], pub version<Version>, do_parse!(
     tag_no_case!(b"SIP") >> char!('/') >>
-    major: single_digit >>
+    major: number >>
     char!('.') >>
-    minor: single_digit >>
+    minor: number >>
     ( Version(major, minor) )
   ));
 
+  // Exactly SIP/2.0, for a UAS which can't handle anything else and
+  // answers 505 (Version Not Supported) to it
+  #[inline]
+  pub fn strict_version(input: &Binary) -> IResult<&Binary, Version> {
+    let (rest, v) = version(input)?;
+    if v != DEFAULT_VERSION || input.len() - rest.len() != 7 {
+      return Err(Error((input, Verify)));
+    }
+    Ok((rest, v))
+  }
 }
 
 pub mod assemble {
   use crate::msg::Binary;
   use crate::msg::version::Version;
-  use std::io::Write;
-
-  pub fn version(v: Version, mut buf: &mut Binary) -> std::io::Result<usize> {
-    let mut sum = buf.write(b"SIP/")?;
-    sum += buf.write(&[v.0 + b'0'; 1])?;
-    sum += buf.write(b".")?;
-    sum += buf.write(&[v.1 + b'0'; 1])?;
-    Ok(sum)
+  use crate::msg::output::{Output, AssembleResult};
+
+  pub fn version(v: Version, buf: &mut Binary) -> AssembleResult {
+    let mut out = Output::new(buf);
+    out.put(b"SIP/")?;
+    out.put_number(v.0 as u64)?;
+    out.put_byte(b'.')?;
+    out.put_number(v.1 as u64)?;
+    out.finish()
   }
 }
 
@@ -42,13 +68,14 @@ pub mod assemble {
 mod tests {
 
   use super::{Version, parse, assemble};
+  use crate::msg::output::AssembleError;
   use nom::{
     Err::{
       Incomplete, Error
     },
     Needed,
     error::ErrorKind::{
-      Tag, Char
+      Tag, Char, Digit, Verify
     }
   };
   use std::str::from_utf8;
@@ -61,14 +88,26 @@ mod tests {
     assert_eq!(parse::version("sip/".as_bytes()), Err(Incomplete(Needed::Size(1))));
     assert_eq!(parse::version("sip/2".as_bytes()), Err(Incomplete(Needed::Size(1))));
     assert_eq!(parse::version("sip/4.".as_bytes()), Err(Incomplete(Needed::Size(1))));
+    assert_eq!(parse::version("sip/4.10".as_bytes()), Err(Incomplete(Needed::Size(1))));
 
-    assert_eq!(parse::version("SIP/1.1".as_bytes()), Ok(("".as_bytes(), Version(1, 1))));
+    assert_eq!(parse::version("SIP/1.1\r".as_bytes()), Ok(("\r".as_bytes(), Version(1, 1))));
     assert_eq!(parse::version("SIP/2.1 ".as_bytes()), Ok((" ".as_bytes(), Version(2, 1))));
     assert_eq!(parse::version(" SIP/2.1 ".as_bytes()), Err(Error((" SIP/2.1 ".as_bytes(), Tag))));
     assert_eq!(parse::version("SiP/1.2 ".as_bytes()), Ok((" ".as_bytes(), Version(1, 2))));
     assert_eq!(parse::version("sip/2,1 ".as_bytes()), Err(Error((",1 ".as_bytes(), Char))));
-    assert_eq!(parse::version("sip/245.1 ".as_bytes()), Err(Error(("45.1 ".as_bytes(), Char))));
-    assert_eq!(parse::version("sip/23454,1 ".as_bytes()), Err(Error(("3454,1 ".as_bytes(), Char))));
+    assert_eq!(parse::version("sip/245.1 ".as_bytes()), Ok((" ".as_bytes(), Version(245, 1))));
+    assert_eq!(parse::version("sip/02.010 ".as_bytes()), Ok((" ".as_bytes(), Version(2, 10))));
+    assert_eq!(parse::version("sip/23454,1 ".as_bytes()), Err(Error(("23454,1 ".as_bytes(), Digit))));
+    assert_eq!(parse::version("sip/2.x ".as_bytes()), Err(Error(("x ".as_bytes(), Digit))));
+  }
+
+  #[test]
+  fn strict_version_test() {
+    assert_eq!(parse::strict_version("SIP/2.0 ".as_bytes()), Ok((" ".as_bytes(), Version(2, 0))));
+    assert_eq!(parse::strict_version("sip/2.0\r".as_bytes()), Ok(("\r".as_bytes(), Version(2, 0))));
+    assert_eq!(parse::strict_version("SIP/2.1 ".as_bytes()), Err(Error(("SIP/2.1 ".as_bytes(), Verify))));
+    assert_eq!(parse::strict_version("SIP/02.0 ".as_bytes()), Err(Error(("SIP/02.0 ".as_bytes(), Verify))));
+    assert_eq!(parse::strict_version("SIP/2.0".as_bytes()), Err(Incomplete(Needed::Size(1))));
   }
 
   #[test]
@@ -81,5 +120,12 @@ mod tests {
     assert_eq!(assemble::version(Version(5, 3), &mut buffer[7..]).unwrap(), 7);
     assert_eq!("SIP/1.2SIP/5.3", from_utf8(&buffer[..14]).unwrap());
 
+    assert_eq!(assemble::version(Version(10, 255), &mut buffer[14..]).unwrap(), 10);
+    assert_eq!("SIP/1.2SIP/5.3SIP/10.255", from_utf8(&buffer[..24]).unwrap());
+
+    assert_eq!(
+      assemble::version(Version(2, 0), &mut buffer[..6]),
+      Err(AssembleError::NotEnoughOutSpace { written: 6 })
+    );
   }
 }